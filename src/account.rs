@@ -0,0 +1,196 @@
+use crate::api::{self, ApiError, ApiResult, Client, ResendVerifyResponse, Throttled};
+use crate::app::AppRoute;
+use crate::lockout::{self, Countdown};
+use crate::nav::Nav;
+use crate::session::SessionContext;
+use crate::toast;
+use web_sys::MouseEvent;
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+// Sections of the account page
+mod calendar;
+mod delete;
+mod devices;
+mod email;
+mod password;
+mod totp;
+mod username;
+use calendar::*;
+use delete::*;
+use devices::*;
+use email::*;
+use password::*;
+use totp::*;
+use username::*;
+
+// Progress of one of the forms on the account page
+#[derive(Clone, PartialEq, Debug)]
+enum Status {
+    Idle,
+    Sending,
+    Done(&'static str),
+    Failed(String),
+    // Locked out until then, in milliseconds since the epoch
+    Locked(f64),
+}
+
+impl Status {
+    fn failed(text: &str) -> Self {
+        Status::Failed(String::from(text))
+    }
+
+    // Rate limited requests count down until they can be sent again.
+    // Otherwise the toast explains what went wrong,
+    // and on 401 the route guard sends the user to login.
+    fn from_error(err: &ApiError) -> Self {
+        if let Some(until) = lockout::from_error(err) {
+            return Status::Locked(until);
+        }
+        toast::api_error(err);
+        Status::Idle
+    }
+
+    // The server answered InvalidRequest
+    fn rejected() -> Self {
+        Status::failed(api::REJECTED)
+    }
+
+    // Submit buttons are disabled while this is true
+    fn busy(&self) -> bool {
+        matches!(self, Status::Sending | Status::Locked(_))
+    }
+
+    // onelapsed is called when a lockout runs out
+    fn view(&self, onelapsed: Callback<()>) -> Html {
+        match self {
+            Status::Idle => html! {},
+            Status::Sending => html! { <p>{ "Saving..." }</p> },
+            Status::Done(text) => html! { <p>{ *text }</p> },
+            Status::Failed(text) => html! { <p class="failuretext">{ text.clone() }</p> },
+            Status::Locked(until) => html! { <Countdown until={*until} onelapsed={onelapsed} /> },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AccountMsg {
+    Session(SessionContext),
+    Resend,
+    ResendRecieved(ApiResult<Throttled<ResendVerifyResponse>>),
+    // A lockout ran out
+    Unlocked,
+}
+
+// Account settings, the route guard keeps logged out users away
+pub struct Account {
+    session: SessionContext,
+    resend: Status,
+    _session_handle: ContextHandle<SessionContext>,
+}
+
+impl Account {
+    fn verification(&self, ctx: &Context<Self>) -> Html {
+        if self.session.verified() {
+            return html! { <p>{ "Email verified." }</p> };
+        }
+
+        let cb = ctx.link().callback(|_: MouseEvent| AccountMsg::Resend);
+        html! {
+            <>
+                <p class="failuretext">{ "Email not verified." }</p>
+                <button onclick={cb} disabled={self.resend.busy()}>{ "Resend verification email" }</button>
+                { self.resend.view(ctx.link().callback(|_| AccountMsg::Unlocked)) }
+            </>
+        }
+    }
+}
+
+impl Component for Account {
+    type Message = AccountMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (session, handle) = ctx
+            .link()
+            .context::<SessionContext>(ctx.link().callback(AccountMsg::Session))
+            .expect("No session context");
+        Self {
+            session,
+            resend: Status::Idle,
+            _session_handle: handle,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            AccountMsg::Session(session) => {
+                self.session = session;
+                true
+            }
+            AccountMsg::Resend => {
+                if self.resend.busy() {
+                    return false;
+                }
+                ctx.link().send_future(async {
+                    AccountMsg::ResendRecieved(Client::new().resend_verify().await)
+                });
+                self.resend = Status::Sending;
+                true
+            }
+            AccountMsg::ResendRecieved(resp) => {
+                self.resend = match resp {
+                    Ok(resp) => match resp.body {
+                        ResendVerifyResponse::Success => Status::Done("Verification email sent."),
+                        ResendVerifyResponse::AlreadyVerified => {
+                            Status::Done("Email already verified.")
+                        }
+                        ResendVerifyResponse::Lockout => {
+                            Status::Locked(lockout::until(resp.retry_after))
+                        }
+                        ResendVerifyResponse::InvalidRequest => Status::rejected(),
+                    },
+                    Err(e) => Status::from_error(&e),
+                };
+                true
+            }
+            AccountMsg::Unlocked => {
+                self.resend = Status::Idle;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if !self.session.is_authenticated() {
+            return html! {
+                <>
+                    <Nav route={AppRoute::Account} />
+                    <main id="account" class="content">
+                        <p>{ "Checking session..." }</p>
+                    </main>
+                </>
+            };
+        }
+
+        html! {
+            <>
+                <Nav route={AppRoute::Account} />
+                <main id="account" class="content">
+                    <section id="account-status">
+                        <h1>{ "Account" }</h1>
+                        <p>{ format!("Signed in as {}", self.session.username().unwrap_or_default()) }</p>
+                        { self.verification(ctx) }
+                    </section>
+                    <ChangeEmail />
+                    <ChangeUsername />
+                    <ChangePassword />
+                    <TwoFactor />
+                    <CalendarPrefs />
+                    <Devices />
+                    <DeleteAccount />
+                </main>
+            </>
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use super::Status;
+use crate::api::{ApiResult, Client, Preferences, SavePreferencesResponse};
+use crate::prefs;
+use crate::time;
+use web_sys::{HtmlInputElement, HtmlSelectElement, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum CalendarPrefsMsg {
+    // The server's copy of the preferences came in
+    Loaded(bool),
+    Submit,
+    Recieved(Preferences, ApiResult<SavePreferencesResponse>),
+    // A lockout ran out
+    Unlocked,
+}
+
+// First day of the week and time zone, for the calendar and time tracking
+pub struct CalendarPrefs {
+    status: Status,
+    dayref: NodeRef,
+    zoneref: NodeRef,
+}
+
+impl Component for CalendarPrefs {
+    type Message = CalendarPrefsMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link()
+            .send_future(async { CalendarPrefsMsg::Loaded(prefs::load().await) });
+        Self {
+            status: Status::Idle,
+            dayref: NodeRef::default(),
+            zoneref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            CalendarPrefsMsg::Loaded(changed) => changed,
+            CalendarPrefsMsg::Submit => {
+                if self.status.busy() {
+                    return false;
+                }
+                let daybox = self.dayref.cast::<HtmlSelectElement>();
+                let zonebox = self.zoneref.cast::<HtmlInputElement>();
+                if daybox.is_none() || zonebox.is_none() {
+                    return false;
+                }
+                let first_day = match daybox.unwrap().value().parse::<u32>() {
+                    Ok(day) if day < 7 => day,
+                    _ => {
+                        self.status = Status::failed("Invalid first day of the week!");
+                        return true;
+                    }
+                };
+                // An empty box follows the browser
+                let zone = zonebox.unwrap().value().trim().to_string();
+                let time_zone = if zone.is_empty() {
+                    None
+                } else if time::valid_zone(&zone) {
+                    Some(zone)
+                } else {
+                    self.status = Status::failed("Unknown time zone!");
+                    return true;
+                };
+
+                let req = Preferences {
+                    first_day,
+                    time_zone,
+                };
+                ctx.link().send_future(async move {
+                    let resp = Client::new().save_preferences(&req).await;
+                    CalendarPrefsMsg::Recieved(req, resp)
+                });
+                self.status = Status::Sending;
+                true
+            }
+            CalendarPrefsMsg::Recieved(req, resp) => {
+                self.status = match resp {
+                    Ok(SavePreferencesResponse::Success) => {
+                        prefs::set(req);
+                        Status::Done("Preferences saved.")
+                    }
+                    Ok(SavePreferencesResponse::BadTimeZone) => {
+                        Status::failed("Unknown time zone!")
+                    }
+                    Ok(SavePreferencesResponse::InvalidRequest) => Status::rejected(),
+                    Err(e) => Status::from_error(&e),
+                };
+                true
+            }
+            CalendarPrefsMsg::Unlocked => {
+                self.status = Status::Idle;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            CalendarPrefsMsg::Submit
+        });
+        let current = prefs::get();
+        let days = time::WEEKDAYS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                html! {
+                    <option value={i.to_string()} selected={i as u32 == current.first_day}>{ *name }</option>
+                }
+            })
+            .collect::<Html>();
+        html! {
+            <section id="account-calendar">
+                <h2>{ "Calendar" }</h2>
+                <form>
+                    <label for="account-firstday">{ "Weeks start on" }</label>
+                    <select id="account-firstday" name="first_day" ref={self.dayref.clone()}>
+                        { days }
+                    </select>
+                    <label for="account-timezone">{ "Time zone" }</label>
+                    <input type="text" id="account-timezone" name="time_zone"
+                        value={current.time_zone.unwrap_or_default()}
+                        placeholder={time::browser_zone()}
+                        ref={self.zoneref.clone()} />
+                    <button type="submit" onclick={cb} disabled={self.status.busy()}>{ "Save" }</button>
+                </form>
+                <p>{ "Leave the time zone empty to use the one your browser is in." }</p>
+                { self.status.view(ctx.link().callback(|_| CalendarPrefsMsg::Unlocked)) }
+            </section>
+        }
+    }
+}
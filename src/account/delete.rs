@@ -0,0 +1,122 @@
+use super::Status;
+use crate::api::{ApiResult, Client, DeleteAccountRequest, DeleteAccountResponse};
+use crate::app::AppRoute;
+use crate::session;
+use crate::timers;
+use gloo::storage::{LocalStorage, Storage};
+use web_sys::{HtmlInputElement, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum DeleteAccountMsg {
+    // Show or hide the confirmation
+    Confirm,
+    Submit,
+    Recieved(ApiResult<DeleteAccountResponse>),
+    // A lockout ran out
+    Unlocked,
+}
+
+pub struct DeleteAccount {
+    status: Status,
+    confirming: bool,
+    passref: NodeRef,
+}
+
+impl Component for DeleteAccount {
+    type Message = DeleteAccountMsg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            status: Status::Idle,
+            confirming: false,
+            passref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            DeleteAccountMsg::Confirm => {
+                self.confirming = !self.confirming;
+                self.status = Status::Idle;
+                true
+            }
+            DeleteAccountMsg::Submit => {
+                if self.status.busy() {
+                    return false;
+                }
+                let password = match self.passref.cast::<HtmlInputElement>() {
+                    Some(passbox) => passbox.value(),
+                    None => return false,
+                };
+                if password.is_empty() {
+                    self.status = Status::failed("Missing field value!");
+                    return true;
+                }
+
+                let req = DeleteAccountRequest { password };
+                ctx.link().send_future(async move {
+                    DeleteAccountMsg::Recieved(Client::new().delete_account(&req).await)
+                });
+                self.status = Status::Sending;
+                true
+            }
+            DeleteAccountMsg::Recieved(resp) => {
+                self.status = match resp {
+                    Ok(DeleteAccountResponse::Success) => {
+                        // Every token died with the account, just forget them
+                        timers::cancel_session_refresh();
+                        LocalStorage::delete("persist");
+                        session::clear();
+                        yew_router::replace_route(AppRoute::Home);
+                        return false;
+                    }
+                    Ok(DeleteAccountResponse::PasswordWrong) => Status::failed("Invalid password!"),
+                    Ok(DeleteAccountResponse::InvalidRequest) => Status::rejected(),
+                    Err(e) => Status::from_error(&e),
+                };
+                true
+            }
+            DeleteAccountMsg::Unlocked => {
+                self.status = Status::Idle;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let confirm_cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            DeleteAccountMsg::Confirm
+        });
+        if !self.confirming {
+            return html! {
+                <section id="account-delete">
+                    <h2>{ "Delete account" }</h2>
+                    <button class="dangerbutton" onclick={confirm_cb}>{ "Delete account" }</button>
+                </section>
+            };
+        }
+
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            DeleteAccountMsg::Submit
+        });
+        html! {
+            <section id="account-delete">
+                <h2>{ "Delete account" }</h2>
+                <p class="failuretext">{ "This can not be undone. All of your data will be removed." }</p>
+                <form>
+                    <label for="account-deletepass">{ "Password" }</label>
+                    <input type="password" id="account-deletepass" name="password" ref={self.passref.clone()} />
+                    <button type="submit" class="dangerbutton" onclick={cb} disabled={self.status.busy()}>
+                        { "Yes, delete my account" }
+                    </button>
+                    <button onclick={confirm_cb}>{ "Cancel" }</button>
+                </form>
+                { self.status.view(ctx.link().callback(|_| DeleteAccountMsg::Unlocked)) }
+            </section>
+        }
+    }
+}
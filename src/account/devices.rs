@@ -0,0 +1,166 @@
+use super::Status;
+use crate::api::{
+    ApiResult, Client, Device, DeviceKind, DevicesResponse, RevokeDeviceRequest, RevokeResponse,
+};
+use crate::app::AppRoute;
+use crate::util;
+use web_sys::MouseEvent;
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum DevicesMsg {
+    Load,
+    Loaded(ApiResult<DevicesResponse>),
+    Revoke(u64),
+    Revoked(u64, ApiResult<RevokeResponse>),
+    // A lockout ran out
+    Unlocked,
+}
+
+// Every session and remember me token of the user
+pub struct Devices {
+    status: Status,
+    devices: Vec<Device>,
+}
+
+// Turn a user agent into something like "Firefox on Linux"
+fn device_label(user_agent: &str) -> String {
+    let browser = if user_agent.contains("Firefox/") {
+        "Firefox"
+    } else if user_agent.contains("Edg/") {
+        "Edge"
+    } else if user_agent.contains("OPR/") {
+        "Opera"
+    } else if user_agent.contains("Chrome/") {
+        "Chrome"
+    } else if user_agent.contains("Safari/") {
+        "Safari"
+    } else {
+        "Unknown browser"
+    };
+    let os = if user_agent.contains("Android") {
+        "Android"
+    } else if user_agent.contains("iPhone") || user_agent.contains("iPad") {
+        "iOS"
+    } else if user_agent.contains("Windows") {
+        "Windows"
+    } else if user_agent.contains("Mac OS") {
+        "macOS"
+    } else if user_agent.contains("Linux") {
+        "Linux"
+    } else {
+        "unknown system"
+    };
+    format!("{} on {}", browser, os)
+}
+
+impl Devices {
+    fn row(&self, ctx: &Context<Self>, device: &Device) -> Html {
+        let id = device.id;
+        let cb = ctx
+            .link()
+            .callback(move |_: MouseEvent| DevicesMsg::Revoke(id));
+        let kind = match device.kind {
+            DeviceKind::Session => "Session",
+            DeviceKind::Persist => "Remembered",
+        };
+        html! {
+            <tr class={if device.current { "currentdevice" } else { "" }}>
+                <td title={device.user_agent.clone()}>
+                    { device_label(&device.user_agent) }
+                    { if device.current { " (this device)" } else { "" } }
+                </td>
+                <td>{ kind }</td>
+                <td>{ util::format_timestamp(device.created) }</td>
+                <td>{ util::format_timestamp(device.last_used) }</td>
+                <td><button onclick={cb} disabled={self.status.busy()}>{ "Revoke" }</button></td>
+            </tr>
+        }
+    }
+}
+
+impl Component for Devices {
+    type Message = DevicesMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(DevicesMsg::Load);
+        Self {
+            status: Status::Idle,
+            devices: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            DevicesMsg::Load => {
+                ctx.link()
+                    .send_future(async { DevicesMsg::Loaded(Client::new().devices().await) });
+                false
+            }
+            DevicesMsg::Loaded(resp) => {
+                match resp {
+                    Ok(DevicesResponse::Success(devices)) => {
+                        self.devices = devices;
+                        self.status = Status::Idle;
+                    }
+                    Ok(DevicesResponse::InvalidRequest) => self.status = Status::rejected(),
+                    Err(e) => self.status = Status::from_error(&e),
+                }
+                true
+            }
+            DevicesMsg::Revoke(id) => {
+                let current = self
+                    .devices
+                    .iter()
+                    .any(|device| device.id == id && device.current);
+                if current {
+                    // Same thing as logging out
+                    yew_router::push_route(AppRoute::Logout);
+                    return false;
+                }
+
+                let req = RevokeDeviceRequest { id };
+                ctx.link().send_future(async move {
+                    DevicesMsg::Revoked(id, Client::new().revoke_device(&req).await)
+                });
+                self.status = Status::Sending;
+                true
+            }
+            DevicesMsg::Revoked(id, resp) => {
+                match resp {
+                    Ok(RevokeResponse::Success) => {
+                        self.devices.retain(|device| device.id != id);
+                        self.status = Status::Idle;
+                    }
+                    Ok(RevokeResponse::InvalidRequest) => self.status = Status::rejected(),
+                    Err(e) => self.status = Status::from_error(&e),
+                }
+                true
+            }
+            DevicesMsg::Unlocked => {
+                self.status = Status::Idle;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <section id="account-devices">
+                <h2>{ "Devices" }</h2>
+                <table>
+                    <tr>
+                        <th>{ "Device" }</th>
+                        <th>{ "Type" }</th>
+                        <th>{ "Signed in" }</th>
+                        <th>{ "Last used" }</th>
+                        <th></th>
+                    </tr>
+                    { for self.devices.iter().map(|device| self.row(ctx, device)) }
+                </table>
+                { self.status.view(ctx.link().callback(|_| DevicesMsg::Unlocked)) }
+            </section>
+        }
+    }
+}
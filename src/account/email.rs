@@ -0,0 +1,109 @@
+use super::Status;
+use crate::api::{ApiResult, ChangeEmailRequest, ChangeEmailResponse, Client};
+use crate::session;
+use email_address_parser::EmailAddress;
+use web_sys::{HtmlInputElement, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum ChangeEmailMsg {
+    Submit,
+    Recieved(ApiResult<ChangeEmailResponse>),
+    // A lockout ran out
+    Unlocked,
+}
+
+pub struct ChangeEmail {
+    status: Status,
+    emailref: NodeRef,
+    passref: NodeRef,
+}
+
+impl Component for ChangeEmail {
+    type Message = ChangeEmailMsg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            status: Status::Idle,
+            emailref: NodeRef::default(),
+            passref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ChangeEmailMsg::Submit => {
+                if self.status.busy() {
+                    return false;
+                }
+                let emailbox = self.emailref.cast::<HtmlInputElement>();
+                let passbox = self.passref.cast::<HtmlInputElement>();
+                if emailbox.is_none() || passbox.is_none() {
+                    return false;
+                }
+                let email = emailbox.unwrap().value();
+                let password = passbox.unwrap().value();
+                if email.is_empty() || !EmailAddress::is_valid(&email, None) {
+                    self.status = Status::failed("Invalid email address!");
+                    return true;
+                }
+                if password.is_empty() {
+                    self.status = Status::failed("Missing field value!");
+                    return true;
+                }
+
+                let req = ChangeEmailRequest { email, password };
+                ctx.link().send_future(async move {
+                    ChangeEmailMsg::Recieved(Client::new().change_email(&req).await)
+                });
+                self.status = Status::Sending;
+                true
+            }
+            ChangeEmailMsg::Recieved(resp) => {
+                self.status = match resp {
+                    Ok(ChangeEmailResponse::Success(token, claim)) => {
+                        // The claim now says the email is not verified
+                        if session::login(token, claim).is_err() {
+                            Status::failed("Something went wrong. Please try again.")
+                        } else {
+                            Status::Done("Email changed. Check your inbox to verify it.")
+                        }
+                    }
+                    Ok(ChangeEmailResponse::EmailTaken) => Status::failed("Email taken!"),
+                    Ok(ChangeEmailResponse::InvalidEmail) => {
+                        Status::failed("Invalid email address!")
+                    }
+                    Ok(ChangeEmailResponse::PasswordWrong) => Status::failed("Invalid password!"),
+                    Ok(ChangeEmailResponse::InvalidRequest) => Status::rejected(),
+                    Err(e) => Status::from_error(&e),
+                };
+                true
+            }
+            ChangeEmailMsg::Unlocked => {
+                self.status = Status::Idle;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            ChangeEmailMsg::Submit
+        });
+        html! {
+            <section id="account-email">
+                <h2>{ "Change email" }</h2>
+                <form>
+                    <label for="account-emailbox">{ "New email" }</label>
+                    <input type="text" id="account-emailbox" name="email" ref={self.emailref.clone()} />
+                    <label for="account-emailpass">{ "Password" }</label>
+                    <input type="password" id="account-emailpass" name="password" ref={self.passref.clone()} />
+                    <button type="submit" onclick={cb} disabled={self.status.busy()}>{ "Change email" }</button>
+                </form>
+                { self.status.view(ctx.link().callback(|_| ChangeEmailMsg::Unlocked)) }
+            </section>
+        }
+    }
+}
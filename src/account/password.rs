@@ -0,0 +1,164 @@
+use super::Status;
+use crate::api::{ApiResult, ChangePasswordRequest, ChangePasswordResponse, Client};
+use crate::breach::{self, Breach};
+use crate::config;
+use crate::strength::StrengthMeter;
+use crate::util;
+use web_sys::{HtmlInputElement, KeyboardEvent, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum ChangePasswordMsg {
+    Submit,
+    PasswordTyped,
+    BreachChecked(ChangePasswordRequest, Breach),
+    Recieved(ApiResult<ChangePasswordResponse>),
+    // A lockout ran out
+    Unlocked,
+}
+
+pub struct ChangePassword {
+    status: Status,
+    // A breached password the user was already warned about
+    warned_for: Option<String>,
+    currentref: NodeRef,
+    passref: NodeRef,
+    pass2ref: NodeRef,
+}
+
+impl ChangePassword {
+    fn send(&mut self, ctx: &Context<Self>, req: ChangePasswordRequest) {
+        ctx.link().send_future(async move {
+            ChangePasswordMsg::Recieved(Client::new().change_password(&req).await)
+        });
+        self.status = Status::Sending;
+    }
+}
+
+impl Component for ChangePassword {
+    type Message = ChangePasswordMsg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            status: Status::Idle,
+            warned_for: None,
+            currentref: NodeRef::default(),
+            passref: NodeRef::default(),
+            pass2ref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ChangePasswordMsg::Submit => {
+                if self.status.busy() {
+                    return false;
+                }
+                let currentbox = self.currentref.cast::<HtmlInputElement>();
+                let passbox = self.passref.cast::<HtmlInputElement>();
+                let pass2box = self.pass2ref.cast::<HtmlInputElement>();
+                if currentbox.is_none() || passbox.is_none() || pass2box.is_none() {
+                    return false;
+                }
+                let current = currentbox.unwrap().value();
+                let password = passbox.unwrap().value();
+                let password2 = pass2box.unwrap().value();
+
+                if current.is_empty() {
+                    self.status = Status::failed("Missing field value!");
+                    return true;
+                }
+                if let Some(problem) = util::check_password(&password) {
+                    self.status = Status::Failed(problem.message());
+                    return true;
+                }
+                if password != password2 {
+                    self.status = Status::failed("Mismatched passwords!");
+                    return true;
+                }
+
+                let req = ChangePasswordRequest { current, password };
+                let warned = self.warned_for.as_ref() == Some(&req.password);
+                if config::get().breach_url.is_empty() || warned {
+                    self.send(ctx, req);
+                } else {
+                    ctx.link().send_future(async move {
+                        let breach = breach::check(&req.password).await;
+                        ChangePasswordMsg::BreachChecked(req, breach)
+                    });
+                    self.status = Status::Sending;
+                }
+                true
+            }
+            ChangePasswordMsg::BreachChecked(req, breach) => {
+                match breach.message() {
+                    Some(message) if config::get().breach_block => {
+                        self.status = Status::Failed(message)
+                    }
+                    Some(message) => {
+                        // Let the user insist
+                        self.warned_for = Some(req.password);
+                        self.status = Status::Failed(format!(
+                            "{} Press \"Change password\" again to use it anyway.",
+                            message
+                        ));
+                    }
+                    None => self.send(ctx, req),
+                }
+                true
+            }
+            // Redraw the strength meter
+            ChangePasswordMsg::PasswordTyped => true,
+            ChangePasswordMsg::Recieved(resp) => {
+                self.status = match resp {
+                    Ok(ChangePasswordResponse::Success) => Status::Done("Password changed."),
+                    Ok(ChangePasswordResponse::PasswordWrong) => {
+                        Status::failed("Invalid password!")
+                    }
+                    Ok(ChangePasswordResponse::WeakPassword) => {
+                        Status::failed("Password too weak!")
+                    }
+                    Ok(ChangePasswordResponse::InvalidRequest) => Status::rejected(),
+                    Err(e) => Status::from_error(&e),
+                };
+                true
+            }
+            ChangePasswordMsg::Unlocked => {
+                self.status = Status::Idle;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            ChangePasswordMsg::Submit
+        });
+        let pass_cb = ctx
+            .link()
+            .callback(|_: KeyboardEvent| ChangePasswordMsg::PasswordTyped);
+        let password = self
+            .passref
+            .cast::<HtmlInputElement>()
+            .map(|passbox| passbox.value())
+            .unwrap_or_default();
+        html! {
+            <section id="account-password">
+                <h2>{ "Change password" }</h2>
+                <form>
+                    <label for="account-currentpass">{ "Current password" }</label>
+                    <input type="password" id="account-currentpass" name="current" ref={self.currentref.clone()} />
+                    <label for="account-newpass">{ "New password" }</label>
+                    <input type="password" id="account-newpass" name="password" ref={self.passref.clone()} onkeyup={pass_cb} />
+                    <StrengthMeter password={password} />
+                    <label for="account-newpass2">{ "Re-enter new password" }</label>
+                    <input type="password" id="account-newpass2" name="password2" ref={self.pass2ref.clone()} />
+                    <button type="submit" onclick={cb} disabled={self.status.busy()}>{ "Change password" }</button>
+                </form>
+                { self.status.view(ctx.link().callback(|_| ChangePasswordMsg::Unlocked)) }
+            </section>
+        }
+    }
+}
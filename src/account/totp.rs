@@ -0,0 +1,275 @@
+use super::Status;
+use crate::api::{
+    ApiResult, Client, SecondFactor, TotpConfirmRequest, TotpConfirmResponse, TotpDisableRequest,
+    TotpDisableResponse, TotpEnrollResponse, TotpStatusResponse,
+};
+use crate::qr::Qr;
+use web_sys::{HtmlInputElement, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum TwoFactorMsg {
+    Load,
+    Loaded(ApiResult<TotpStatusResponse>),
+    Enroll,
+    Enrolled(ApiResult<TotpEnrollResponse>),
+    Confirm,
+    Confirmed(ApiResult<TotpConfirmResponse>),
+    // The user saved their recovery codes
+    Saved,
+    Disable,
+    Disabled(ApiResult<TotpDisableResponse>),
+    // A lockout ran out
+    Unlocked,
+}
+
+// Where two-factor setup is at
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum TwoFactorState {
+    Loading,
+    Off,
+    // Scan the QR code, then type a first code
+    Enrolling { uri: String, secret: String },
+    // Just turned on, show the recovery codes once
+    Recovery(Vec<String>),
+    On { recovery_left: u32 },
+}
+
+// Authenticator app (TOTP) two-factor, set up and turned off from account settings
+pub struct TwoFactor {
+    state: TwoFactorState,
+    status: Status,
+    coderef: NodeRef,
+    passref: NodeRef,
+}
+
+impl TwoFactor {
+    fn code(&self) -> String {
+        self.coderef
+            .cast::<HtmlInputElement>()
+            .map(|codebox| codebox.value().trim().replace(' ', ""))
+            .unwrap_or_default()
+    }
+
+    fn off(&self, ctx: &Context<Self>) -> Html {
+        let cb = ctx.link().callback(|_: MouseEvent| TwoFactorMsg::Enroll);
+        html! {
+            <>
+                <p>{ "Two-factor authentication is off." }</p>
+                <button onclick={cb} disabled={self.status.busy()}>{ "Set up two-factor" }</button>
+            </>
+        }
+    }
+
+    fn enrolling(&self, ctx: &Context<Self>, uri: &str, secret: &str) -> Html {
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            TwoFactorMsg::Confirm
+        });
+        html! {
+            <>
+                <p>{ "Scan this with your authenticator app:" }</p>
+                <Qr data={uri.to_string()} />
+                <p>{ "Or enter this key by hand:" }</p>
+                <p class="totpsecret">{ secret.to_string() }</p>
+                <form>
+                    <label for="account-totpcode">{ "Code from the app" }</label>
+                    <input type="text" id="account-totpcode" name="code" inputmode="numeric"
+                        autocomplete="one-time-code" ref={self.coderef.clone()} />
+                    <button type="submit" onclick={cb} disabled={self.status.busy()}>{ "Turn on" }</button>
+                </form>
+            </>
+        }
+    }
+
+    fn recovery(&self, ctx: &Context<Self>, codes: &[String]) -> Html {
+        let cb = ctx.link().callback(|_: MouseEvent| TwoFactorMsg::Saved);
+        html! {
+            <>
+                <p>{ "Two-factor is on. Keep these recovery codes somewhere safe, each one can be used once if you lose your phone. They won't be shown again." }</p>
+                <ul class="recoverycodes">
+                    { for codes.iter().map(|code| html! { <li>{ code.clone() }</li> }) }
+                </ul>
+                <button onclick={cb}>{ "I saved them" }</button>
+            </>
+        }
+    }
+
+    fn on(&self, ctx: &Context<Self>, recovery_left: u32) -> Html {
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            TwoFactorMsg::Disable
+        });
+        html! {
+            <>
+                <p>{ format!("Two-factor authentication is on. {} recovery codes left.", recovery_left) }</p>
+                <form>
+                    <label for="account-totppass">{ "Password" }</label>
+                    <input type="password" id="account-totppass" name="password" ref={self.passref.clone()} />
+                    <label for="account-totpcode">{ "Code from the app, or a recovery code" }</label>
+                    <input type="text" id="account-totpcode" name="code" ref={self.coderef.clone()} />
+                    <button type="submit" onclick={cb} disabled={self.status.busy()}>{ "Turn off" }</button>
+                </form>
+            </>
+        }
+    }
+}
+
+impl Component for TwoFactor {
+    type Message = TwoFactorMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(TwoFactorMsg::Load);
+        Self {
+            state: TwoFactorState::Loading,
+            status: Status::Idle,
+            coderef: NodeRef::default(),
+            passref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            TwoFactorMsg::Load => {
+                ctx.link()
+                    .send_future(async { TwoFactorMsg::Loaded(Client::new().totp_status().await) });
+                false
+            }
+            TwoFactorMsg::Loaded(resp) => {
+                match resp {
+                    Ok(TotpStatusResponse::Enabled { recovery_left }) => {
+                        self.state = TwoFactorState::On { recovery_left }
+                    }
+                    Ok(TotpStatusResponse::Disabled) => self.state = TwoFactorState::Off,
+                    Ok(TotpStatusResponse::InvalidRequest) => self.status = Status::rejected(),
+                    Err(e) => self.status = Status::from_error(&e),
+                }
+                true
+            }
+            TwoFactorMsg::Enroll => {
+                ctx.link().send_future(async {
+                    TwoFactorMsg::Enrolled(Client::new().totp_enroll().await)
+                });
+                self.status = Status::Sending;
+                true
+            }
+            TwoFactorMsg::Enrolled(resp) => {
+                self.status = Status::Idle;
+                match resp {
+                    Ok(TotpEnrollResponse::Success { uri, secret }) => {
+                        self.state = TwoFactorState::Enrolling { uri, secret }
+                    }
+                    // Another tab beat us to it
+                    Ok(TotpEnrollResponse::AlreadyEnabled) => {
+                        ctx.link().send_message(TwoFactorMsg::Load)
+                    }
+                    Ok(TotpEnrollResponse::InvalidRequest) => self.status = Status::rejected(),
+                    Err(e) => self.status = Status::from_error(&e),
+                }
+                true
+            }
+            TwoFactorMsg::Confirm => {
+                if self.status.busy() {
+                    return false;
+                }
+                let code = self.code();
+                if code.is_empty() {
+                    self.status = Status::failed("Missing field value!");
+                    return true;
+                }
+
+                let req = TotpConfirmRequest { code };
+                ctx.link().send_future(async move {
+                    TwoFactorMsg::Confirmed(Client::new().totp_confirm(&req).await)
+                });
+                self.status = Status::Sending;
+                true
+            }
+            TwoFactorMsg::Confirmed(resp) => {
+                self.status = match resp {
+                    Ok(TotpConfirmResponse::Success(codes)) => {
+                        self.state = TwoFactorState::Recovery(codes);
+                        Status::Idle
+                    }
+                    Ok(TotpConfirmResponse::BadCode) => {
+                        Status::failed("Wrong code! Check the clock on your phone is right.")
+                    }
+                    Ok(TotpConfirmResponse::InvalidRequest) => Status::rejected(),
+                    Err(e) => Status::from_error(&e),
+                };
+                true
+            }
+            TwoFactorMsg::Saved => {
+                if let TwoFactorState::Recovery(codes) = &self.state {
+                    self.state = TwoFactorState::On {
+                        recovery_left: codes.len() as u32,
+                    };
+                }
+                true
+            }
+            TwoFactorMsg::Disable => {
+                if self.status.busy() {
+                    return false;
+                }
+                let password = self
+                    .passref
+                    .cast::<HtmlInputElement>()
+                    .map(|passbox| passbox.value())
+                    .unwrap_or_default();
+                let code = self.code();
+                if password.is_empty() || code.is_empty() {
+                    self.status = Status::failed("Missing field value!");
+                    return true;
+                }
+
+                // App codes are all digits, recovery codes aren't
+                let code = if code.chars().all(|c| c.is_ascii_digit()) {
+                    SecondFactor::Totp(code)
+                } else {
+                    SecondFactor::Recovery(code)
+                };
+                let req = TotpDisableRequest { password, code };
+                ctx.link().send_future(async move {
+                    TwoFactorMsg::Disabled(Client::new().totp_disable(&req).await)
+                });
+                self.status = Status::Sending;
+                true
+            }
+            TwoFactorMsg::Disabled(resp) => {
+                self.status = match resp {
+                    Ok(TotpDisableResponse::Success) => {
+                        self.state = TwoFactorState::Off;
+                        Status::Done("Two-factor authentication turned off.")
+                    }
+                    Ok(TotpDisableResponse::PasswordWrong) => Status::failed("Invalid password!"),
+                    Ok(TotpDisableResponse::BadCode) => Status::failed("Wrong code!"),
+                    Ok(TotpDisableResponse::InvalidRequest) => Status::rejected(),
+                    Err(e) => Status::from_error(&e),
+                };
+                true
+            }
+            TwoFactorMsg::Unlocked => {
+                self.status = Status::Idle;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let body = match &self.state {
+            TwoFactorState::Loading => html! { <p>{ "Loading..." }</p> },
+            TwoFactorState::Off => self.off(ctx),
+            TwoFactorState::Enrolling { uri, secret } => self.enrolling(ctx, uri, secret),
+            TwoFactorState::Recovery(codes) => self.recovery(ctx, codes),
+            TwoFactorState::On { recovery_left } => self.on(ctx, *recovery_left),
+        };
+        html! {
+            <section id="account-totp">
+                <h2>{ "Two-factor authentication" }</h2>
+                { body }
+                { self.status.view(ctx.link().callback(|_| TwoFactorMsg::Unlocked)) }
+            </section>
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use super::Status;
+use crate::api::{ApiResult, Availability, ChangeUsernameResponse, Client, UsernameRequest};
+use crate::session;
+use crate::util;
+use web_sys::{Event, HtmlInputElement, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum ChangeUsernameMsg {
+    Check,
+    Checked(String, ApiResult<Availability>),
+    Submit,
+    Recieved(ApiResult<ChangeUsernameResponse>),
+    // A lockout ran out
+    Unlocked,
+}
+
+pub struct ChangeUsername {
+    status: Status,
+    // Last username checked and whether it can be used
+    availability: Option<(String, Availability)>,
+    userref: NodeRef,
+}
+
+impl ChangeUsername {
+    fn username(&self) -> Option<String> {
+        self.userref
+            .cast::<HtmlInputElement>()
+            .map(|userbox| userbox.value())
+    }
+
+    fn availability_view(&self) -> Html {
+        match self.availability {
+            Some((_, Availability::Available)) => html! { <p>{ "Available!" }</p> },
+            Some((_, Availability::Taken)) => {
+                html! { <p class="failuretext">{ "Username taken!" }</p> }
+            }
+            Some((_, Availability::Invalid)) => {
+                html! {
+                    <p class="failuretext">
+                        { "Invalid username! Use only letters, digits, '_' and '-'." }
+                    </p>
+                }
+            }
+            None => html! {},
+        }
+    }
+}
+
+impl Component for ChangeUsername {
+    type Message = ChangeUsernameMsg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            status: Status::Idle,
+            availability: None,
+            userref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ChangeUsernameMsg::Check => {
+                let username = match self.username() {
+                    Some(username) if !username.is_empty() => username,
+                    _ => {
+                        self.availability = None;
+                        return true;
+                    }
+                };
+                if util::check_username(&username).is_some() {
+                    // No need to ask the server
+                    self.availability = Some((username, Availability::Invalid));
+                    return true;
+                }
+                let req = UsernameRequest { username };
+                ctx.link().send_future(async move {
+                    let resp = Client::new().username_available(&req).await;
+                    ChangeUsernameMsg::Checked(req.username, resp)
+                });
+                false
+            }
+            ChangeUsernameMsg::Checked(username, resp) => {
+                // Ignore answers for something the user already changed
+                if self.username().as_ref() != Some(&username) {
+                    return false;
+                }
+                match resp {
+                    Ok(availability) => self.availability = Some((username, availability)),
+                    Err(e) => self.status = Status::from_error(&e),
+                }
+                true
+            }
+            ChangeUsernameMsg::Submit => {
+                if self.status.busy() {
+                    return false;
+                }
+                let username = match self.username() {
+                    Some(username) if !username.is_empty() => username,
+                    _ => {
+                        self.status = Status::failed("Missing field value!");
+                        return true;
+                    }
+                };
+                if let Some((checked, availability)) = &self.availability {
+                    if *checked == username && *availability != Availability::Available {
+                        return false;
+                    }
+                }
+
+                let req = UsernameRequest { username };
+                ctx.link().send_future(async move {
+                    ChangeUsernameMsg::Recieved(Client::new().change_username(&req).await)
+                });
+                self.status = Status::Sending;
+                true
+            }
+            ChangeUsernameMsg::Recieved(resp) => {
+                self.status = match resp {
+                    Ok(ChangeUsernameResponse::Success(token, claim)) => {
+                        if session::login(token, claim).is_err() {
+                            Status::failed("Something went wrong. Please try again.")
+                        } else {
+                            self.availability = None;
+                            Status::Done("Username changed.")
+                        }
+                    }
+                    Ok(ChangeUsernameResponse::UsernameTaken) => Status::failed("Username taken!"),
+                    Ok(ChangeUsernameResponse::InvalidUsername) => {
+                        Status::failed("Invalid username!")
+                    }
+                    Ok(ChangeUsernameResponse::InvalidRequest) => Status::rejected(),
+                    Err(e) => Status::from_error(&e),
+                };
+                true
+            }
+            ChangeUsernameMsg::Unlocked => {
+                self.status = Status::Idle;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let check_cb = ctx.link().callback(|_: Event| ChangeUsernameMsg::Check);
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            ChangeUsernameMsg::Submit
+        });
+        html! {
+            <section id="account-username">
+                <h2>{ "Change username" }</h2>
+                <form>
+                    <label for="account-userbox">{ "New username" }</label>
+                    <input type="text" id="account-userbox" name="username" ref={self.userref.clone()} onchange={check_cb} />
+                    { self.availability_view() }
+                    <button type="submit" onclick={cb} disabled={self.status.busy()}>{ "Change username" }</button>
+                </form>
+                { self.status.view(ctx.link().callback(|_| ChangeUsernameMsg::Unlocked)) }
+            </section>
+        }
+    }
+}
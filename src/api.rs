@@ -0,0 +1,322 @@
+use crate::config;
+use crate::toast;
+use gloo::storage::{SessionStorage, Storage};
+use js_sys::Date;
+use nittei_common::auth::{
+    AuthToken, LoginRequest, PersistLoginRequest, PersistLoginResponse, PersistRequest,
+    PersistResponse, RegisterRequest, RegisterResponse, RenewResponse,
+};
+use reqwasm::http::{Request, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Wire types for endpoints nittei_common does not have
+mod messages;
+pub use messages::*;
+
+// RON or JSON bodies
+mod codec;
+pub use codec::Codec;
+
+// Everything that can go wrong talking to the API
+mod error;
+pub use error::*;
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+// A response and the server's Retry-After header, for endpoints that can lock the user out
+#[derive(Clone, Debug)]
+pub struct Throttled<T> {
+    pub body: T,
+    // Seconds to wait before trying again, if the server said
+    pub retry_after: Option<u32>,
+}
+
+// Client for the nittei API.
+// Holds the session token (if any) so every request is sent with the right Authorization header.
+#[derive(Clone, Debug, Default)]
+pub struct Client {
+    token: Option<AuthToken>,
+}
+
+impl Client {
+    // Client using whatever session token is currently stored
+    pub fn new() -> Self {
+        Self {
+            token: SessionStorage::get("session_key").ok(),
+        }
+    }
+
+    // Client that never sends an Authorization header
+    pub fn anonymous() -> Self {
+        Self { token: None }
+    }
+
+    // Client using a specific token
+    pub fn with_token(token: AuthToken) -> Self {
+        Self { token: Some(token) }
+    }
+
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    pub async fn login(&self, req: &LoginRequest) -> ApiResult<Throttled<LoginReply>> {
+        self.post_throttled("/auth/login", "login", req).await
+    }
+
+    pub async fn second_factor(
+        &self,
+        req: &SecondFactorRequest,
+    ) -> ApiResult<Throttled<SecondFactorResponse>> {
+        self.post_throttled("/auth/login/second_factor", "second-factor", req)
+            .await
+    }
+
+    pub async fn totp_status(&self) -> ApiResult<TotpStatusResponse> {
+        self.get("/auth/totp", "totp-status").await
+    }
+
+    pub async fn totp_enroll(&self) -> ApiResult<TotpEnrollResponse> {
+        self.get("/auth/totp/enroll", "totp-enroll").await
+    }
+
+    pub async fn totp_confirm(&self, req: &TotpConfirmRequest) -> ApiResult<TotpConfirmResponse> {
+        self.post("/auth/totp/confirm", "totp-confirm", req).await
+    }
+
+    pub async fn totp_disable(&self, req: &TotpDisableRequest) -> ApiResult<TotpDisableResponse> {
+        self.post("/auth/totp/disable", "totp-disable", req).await
+    }
+
+    pub async fn register(&self, req: &RegisterRequest) -> ApiResult<Throttled<RegisterResponse>> {
+        self.post_throttled("/auth/register", "register", req).await
+    }
+
+    pub async fn renew(&self) -> ApiResult<RenewResponse> {
+        self.get("/auth/renew", "renew").await
+    }
+
+    pub async fn persist_login(
+        &self,
+        req: &PersistLoginRequest,
+    ) -> ApiResult<PersistLoginResponse> {
+        self.post("/auth/persist_login", "persist-login", req).await
+    }
+
+    pub async fn persist_request(&self, req: &PersistRequest) -> ApiResult<PersistResponse> {
+        self.post("/auth/persist_request", "persist", req).await
+    }
+
+    pub async fn revoke(&self, req: &RevokeRequest) -> ApiResult<RevokeResponse> {
+        self.post("/auth/revoke", "revoke", req).await
+    }
+
+    pub async fn verify(&self, req: &VerifyRequest) -> ApiResult<VerifyResponse> {
+        self.post("/auth/verify", "verify", req).await
+    }
+
+    pub async fn resend_verify(&self) -> ApiResult<Throttled<ResendVerifyResponse>> {
+        self.get_throttled("/auth/resend_verify", "resend-verify")
+            .await
+    }
+
+    pub async fn forgot(&self, req: &ForgotRequest) -> ApiResult<Throttled<ForgotResponse>> {
+        self.post_throttled("/auth/forgot", "forgot", req).await
+    }
+
+    pub async fn reset(&self, req: &ResetRequest) -> ApiResult<ResetResponse> {
+        self.post("/auth/reset", "reset", req).await
+    }
+
+    pub async fn change_email(&self, req: &ChangeEmailRequest) -> ApiResult<ChangeEmailResponse> {
+        self.post("/account/email", "change-email", req).await
+    }
+
+    pub async fn username_available(&self, req: &UsernameRequest) -> ApiResult<Availability> {
+        self.post("/account/username_available", "username-available", req)
+            .await
+    }
+
+    pub async fn email_available(&self, req: &EmailRequest) -> ApiResult<Availability> {
+        self.post("/account/email_available", "email-available", req)
+            .await
+    }
+
+    pub async fn change_username(
+        &self,
+        req: &UsernameRequest,
+    ) -> ApiResult<ChangeUsernameResponse> {
+        self.post("/account/username", "change-username", req).await
+    }
+
+    pub async fn change_password(
+        &self,
+        req: &ChangePasswordRequest,
+    ) -> ApiResult<ChangePasswordResponse> {
+        self.post("/account/password", "change-password", req).await
+    }
+
+    pub async fn delete_account(
+        &self,
+        req: &DeleteAccountRequest,
+    ) -> ApiResult<DeleteAccountResponse> {
+        self.post("/account/delete", "delete-account", req).await
+    }
+
+    pub async fn devices(&self) -> ApiResult<DevicesResponse> {
+        self.get("/auth/devices", "devices").await
+    }
+
+    pub async fn revoke_device(&self, req: &RevokeDeviceRequest) -> ApiResult<RevokeResponse> {
+        self.post("/auth/devices/revoke", "revoke-device", req)
+            .await
+    }
+
+    pub async fn entries(&self, req: &EntriesRequest) -> ApiResult<EntriesResponse> {
+        self.post("/track/entries", "entries", req).await
+    }
+
+    pub async fn running(&self) -> ApiResult<RunningResponse> {
+        self.get("/track/running", "running").await
+    }
+
+    pub async fn start_timer(&self, req: &StartTimerRequest) -> ApiResult<EntryResponse> {
+        self.post("/track/start", "start-timer", req).await
+    }
+
+    pub async fn stop_timer(&self, req: &StopTimerRequest) -> ApiResult<EntryResponse> {
+        self.post("/track/stop", "stop-timer", req).await
+    }
+
+    pub async fn save_entry(&self, req: &SaveEntryRequest) -> ApiResult<EntryResponse> {
+        self.post("/track/entry", "save-entry", req).await
+    }
+
+    pub async fn delete_entry(&self, req: &DeleteEntryRequest) -> ApiResult<DeleteEntryResponse> {
+        self.post("/track/delete", "delete-entry", req).await
+    }
+
+    pub async fn preferences(&self) -> ApiResult<PreferencesResponse> {
+        self.get("/account/preferences", "preferences").await
+    }
+
+    pub async fn save_preferences(&self, req: &Preferences) -> ApiResult<SavePreferencesResponse> {
+        self.post("/account/preferences", "save-preferences", req)
+            .await
+    }
+
+    pub async fn events(&self, req: &EventsRequest) -> ApiResult<EventsResponse> {
+        self.post("/calendar/events", "events", req).await
+    }
+
+    pub async fn save_event(&self, req: &SaveEventRequest) -> ApiResult<EventResponse> {
+        self.post("/calendar/event", "save-event", req).await
+    }
+
+    pub async fn delete_event(&self, req: &DeleteEventRequest) -> ApiResult<DeleteEventResponse> {
+        self.post("/calendar/delete", "delete-event", req).await
+    }
+
+    pub async fn import_events(
+        &self,
+        req: &ImportEventsRequest,
+    ) -> ApiResult<ImportEventsResponse> {
+        self.post("/calendar/import", "import-events", req).await
+    }
+
+    // Add the content type, accepted codec and the bearer token to a request
+    fn prepare(&self, req: Request, kind: &str) -> Request {
+        let codec = config::get().codec;
+        let req = req
+            .header("Content-Type", &codec.content_type(kind))
+            .header("Accept", codec.accept());
+        match &self.token {
+            Some(token) => req.header("Authorization", &format!("Bearer {}", token)),
+            None => req,
+        }
+    }
+
+    async fn get<Resp: DeserializeOwned>(&self, path: &str, kind: &str) -> ApiResult<Resp> {
+        let resp = self.get_throttled(path, kind).await?;
+        Ok(resp.body)
+    }
+
+    async fn post<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        path: &str,
+        kind: &str,
+        body: &Req,
+    ) -> ApiResult<Resp> {
+        let resp = self.post_throttled(path, kind, body).await?;
+        Ok(resp.body)
+    }
+
+    async fn get_throttled<Resp: DeserializeOwned>(
+        &self,
+        path: &str,
+        kind: &str,
+    ) -> ApiResult<Throttled<Resp>> {
+        let req = Request::get(&format!("{}{}", config::get().api_url, path));
+        fetch(path, self.prepare(req, kind)).await
+    }
+
+    async fn post_throttled<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        path: &str,
+        kind: &str,
+        body: &Req,
+    ) -> ApiResult<Throttled<Resp>> {
+        let body = config::get()
+            .codec
+            .encode(body)
+            .map_err(|kind| ApiError::new(path, kind))?;
+        let req = Request::post(&format!("{}{}", config::get().api_url, path)).body(body);
+        fetch(path, self.prepare(req, kind)).await
+    }
+}
+
+// Check the API can be reached at all, any HTTP response counts
+pub async fn probe() -> bool {
+    Request::get(&config::get().api_url).send().await.is_ok()
+}
+
+// Seconds the server wants us to wait, from either form of Retry-After
+fn retry_after(resp: &Response) -> Option<u32> {
+    let value = resp.headers().get("Retry-After")?;
+    let value = value.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(secs);
+    }
+    // Otherwise it is an HTTP date
+    let at = Date::parse(value);
+    if at.is_nan() {
+        return None;
+    }
+    Some(((at - Date::now()) / 1000.0).ceil().max(0.0) as u32)
+}
+
+// Send a request and decode the response in whichever codec the server answered with
+async fn fetch<Resp: DeserializeOwned>(endpoint: &str, req: Request) -> ApiResult<Throttled<Resp>> {
+    let fail = |kind| ApiError::new(endpoint, kind);
+    let resp = req.send().await.map_err(|_| fail(ErrorKind::Offline))?;
+    // Any answer at all means we are back online
+    toast::set_offline(false);
+    match resp.status() {
+        200 => (),
+        401 => return Err(fail(ErrorKind::Unauthorized)),
+        429 => return Err(fail(ErrorKind::RateLimited(retry_after(&resp)))),
+        status @ 500..=599 => return Err(fail(ErrorKind::Server(status))),
+        status => return Err(fail(ErrorKind::Status(status))),
+    }
+
+    let codec = Codec::from_content_type(resp.headers().get("Content-Type").as_deref());
+    // Lockout answers come back as 200s, some with a Retry-After
+    let retry_after = retry_after(&resp);
+    let text = resp
+        .text()
+        .await
+        .map_err(|e| fail(ErrorKind::Decode(format!("{:?}", e))))?;
+    let body = codec.decode(&text).map_err(fail)?;
+    Ok(Throttled { body, retry_after })
+}
@@ -0,0 +1,78 @@
+use super::ErrorKind;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+// How request and response bodies are written on the wire.
+// RON is what the server has always spoken; JSON is easier on logs, curl and other clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Codec {
+    #[default]
+    Ron,
+    Json,
+}
+
+impl Codec {
+    // Content-Type for a request body, kind is the endpoint i.e. "login"
+    pub fn content_type(&self, kind: &str) -> String {
+        match self {
+            Codec::Ron => format!("application/x-{}-request", kind),
+            Codec::Json => String::from("application/json"),
+        }
+    }
+
+    // What we ask the server to answer in
+    pub fn accept(&self) -> &'static str {
+        match self {
+            Codec::Ron => "application/ron",
+            Codec::Json => "application/json",
+        }
+    }
+
+    // The codec a response was written in, going by its Content-Type.
+    // Servers that don't negotiate answer in RON without saying so.
+    pub fn from_content_type(content_type: Option<&str>) -> Self {
+        match content_type {
+            Some(content_type) if content_type.contains("json") => Codec::Json,
+            _ => Codec::Ron,
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<String, ErrorKind> {
+        match self {
+            Codec::Ron => ron::to_string(value).map_err(|e| ErrorKind::Encode(e.to_string())),
+            Codec::Json => {
+                serde_json::to_string(value).map_err(|e| ErrorKind::Encode(e.to_string()))
+            }
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, text: &str) -> Result<T, ErrorKind> {
+        match self {
+            Codec::Ron => ron::from_str(text).map_err(|e| ErrorKind::Decode(e.to_string())),
+            Codec::Json => serde_json::from_str(text).map_err(|e| ErrorKind::Decode(e.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Codec::Ron => write!(f, "ron"),
+            Codec::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ron" => Ok(Codec::Ron),
+            "json" => Ok(Codec::Json),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+
+// Shown when the server answers InvalidRequest, it didn't understand what we sent
+pub const REJECTED: &str = "The server did not accept the request. Reloading the page may help.";
+
+// What went wrong talking to the API
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    // Could not reach the server at all
+    Offline,
+    // The server rejected our session token
+    Unauthorized,
+    // Too many requests (429), with how many seconds to wait if the server said
+    RateLimited(Option<u32>),
+    // The server broke (5xx)
+    Server(u16),
+    // Any other non-200 response
+    Status(u16),
+    // Request body could not be serialized
+    Encode(String),
+    // Response body could not be read or deserialized.
+    // Usually means the client and server are different versions.
+    Decode(String),
+}
+
+// A failed API call, and which endpoint it was
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiError {
+    pub endpoint: String,
+    pub kind: ErrorKind,
+}
+
+impl ApiError {
+    pub fn new(endpoint: &str, kind: ErrorKind) -> Self {
+        Self {
+            endpoint: String::from(endpoint),
+            kind,
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.kind == ErrorKind::Offline
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        self.kind == ErrorKind::Unauthorized
+    }
+
+    // Something to tell the user
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ErrorKind::Offline => String::from("You are offline."),
+            ErrorKind::Unauthorized => {
+                String::from("Your session has expired. Please log in again.")
+            }
+            ErrorKind::RateLimited(Some(secs)) => {
+                format!("Too many requests. Try again in {} seconds.", secs)
+            }
+            ErrorKind::RateLimited(None) => String::from("Too many requests. Please wait."),
+            ErrorKind::Server(status) => format!(
+                "The server had a problem ({}). Try again in a moment.",
+                status
+            ),
+            ErrorKind::Status(status) => {
+                format!("Unexpected response from the server ({}).", status)
+            }
+            ErrorKind::Encode(_) => String::from("Could not send the request."),
+            ErrorKind::Decode(_) => {
+                String::from("This page is out of date with the server. Reload to update it.")
+            }
+        }
+    }
+}
+
+// For logs and bug reports
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Offline => write!(f, "{}: network error", self.endpoint),
+            ErrorKind::Unauthorized => write!(f, "{}: HTTP 401", self.endpoint),
+            ErrorKind::RateLimited(Some(secs)) => {
+                write!(f, "{}: HTTP 429, retry after {}s", self.endpoint, secs)
+            }
+            ErrorKind::RateLimited(None) => write!(f, "{}: HTTP 429", self.endpoint),
+            ErrorKind::Server(status) | ErrorKind::Status(status) => {
+                write!(f, "{}: HTTP {}", self.endpoint, status)
+            }
+            ErrorKind::Encode(detail) => write!(f, "{}: encode failed: {}", self.endpoint, detail),
+            ErrorKind::Decode(detail) => write!(f, "{}: decode failed: {}", self.endpoint, detail),
+        }
+    }
+}
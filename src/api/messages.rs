@@ -0,0 +1,453 @@
+// Requests and responses for endpoints not covered by nittei_common::auth
+use nittei_common::auth::{AuthClaim, AuthToken, PersistToken};
+use serde::{Deserialize, Serialize};
+
+// Revoke the session token sent in the Authorization header
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RevokeRequest {
+    // Remember me token to revoke along with the session
+    pub persist: Option<PersistToken>,
+    // Revoke every session and remember me token of the user
+    pub everywhere: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevokeResponse {
+    Success,
+    InvalidRequest,
+}
+
+// Verify the email address of the user in the Authorization header
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyRequest {
+    pub code: u128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum VerifyResponse {
+    // New session with the verified flag set
+    Success(AuthToken, AuthClaim),
+    ExpiredCode,
+    BadCode,
+    InvalidRequest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResendVerifyResponse {
+    Success,
+    AlreadyVerified,
+    Lockout,
+    InvalidRequest,
+}
+
+// Ask for a password reset email
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ForgotRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForgotResponse {
+    Success,
+    InvalidEmail,
+    Lockout,
+    InvalidRequest,
+}
+
+// Set a new password with the code from a reset email
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ResetRequest {
+    pub code: u128,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetResponse {
+    Success,
+    ExpiredCode,
+    BadCode,
+    UsedCode,
+    WeakPassword,
+    InvalidRequest,
+}
+
+// Change the email address, the new one has to be verified again
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChangeEmailRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ChangeEmailResponse {
+    // New session with the new email and the verified flag cleared
+    Success(AuthToken, AuthClaim),
+    EmailTaken,
+    InvalidEmail,
+    PasswordWrong,
+    InvalidRequest,
+}
+
+// Check if a username can be used
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UsernameRequest {
+    pub username: String,
+}
+
+// Check if an email address can be used
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EmailRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Availability {
+    Available,
+    Taken,
+    Invalid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ChangeUsernameResponse {
+    // New session with the new username
+    Success(AuthToken, AuthClaim),
+    UsernameTaken,
+    InvalidUsername,
+    InvalidRequest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChangePasswordRequest {
+    pub current: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangePasswordResponse {
+    Success,
+    PasswordWrong,
+    WeakPassword,
+    InvalidRequest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeleteAccountResponse {
+    Success,
+    PasswordWrong,
+    InvalidRequest,
+}
+
+// What kind of token a device holds
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+    Session,
+    Persist,
+}
+
+// A session or remember me token of the user
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Device {
+    pub id: u64,
+    pub kind: DeviceKind,
+    // Unix timestamps, in seconds
+    pub created: u64,
+    pub last_used: u64,
+    pub user_agent: String,
+    // This is the token making the request
+    pub current: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum DevicesResponse {
+    Success(Vec<Device>),
+    InvalidRequest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RevokeDeviceRequest {
+    pub id: u64,
+}
+
+// Answer to a LoginRequest. Same as nittei_common's LoginResponse,
+// plus asking for a second factor when the account has one.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum LoginReply {
+    Success(AuthToken, AuthClaim),
+    PasswordWrong,
+    EmailInvalid,
+    LockedOut,
+    InvalidRequest,
+    // Password was right, now send a code along with this challenge
+    SecondFactor(u128),
+}
+
+// A code proving the user has their second factor
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum SecondFactor {
+    // From the authenticator app
+    Totp(String),
+    // One of the codes handed out on enrollment, each works once
+    Recovery(String),
+}
+
+// Second step of logging in
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SecondFactorRequest {
+    pub challenge: u128,
+    pub code: SecondFactor,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum SecondFactorResponse {
+    Success(AuthToken, AuthClaim),
+    BadCode,
+    // Took too long, log in again
+    ExpiredChallenge,
+    LockedOut,
+    InvalidRequest,
+}
+
+// Whether the user in the Authorization header has two-factor on
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TotpStatusResponse {
+    Enabled { recovery_left: u32 },
+    Disabled,
+    InvalidRequest,
+}
+
+// Start setting up two-factor, the secret is not used until confirmed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TotpEnrollResponse {
+    // otpauth:// URI for authenticator apps, and the secret for typing in by hand
+    Success { uri: String, secret: String },
+    AlreadyEnabled,
+    InvalidRequest,
+}
+
+// Turn two-factor on with a first code from the authenticator app
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TotpConfirmRequest {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TotpConfirmResponse {
+    // Recovery codes, only ever shown this once
+    Success(Vec<String>),
+    BadCode,
+    InvalidRequest,
+}
+
+// Turn two-factor off
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TotpDisableRequest {
+    pub password: String,
+    pub code: SecondFactor,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TotpDisableResponse {
+    Success,
+    PasswordWrong,
+    BadCode,
+    InvalidRequest,
+}
+
+// A stretch of tracked time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub id: u64,
+    // What the time was spent on
+    pub activity: String,
+    // Unix timestamps, in seconds. No end means the timer is still running.
+    pub start: u64,
+    pub end: Option<u64>,
+    pub notes: String,
+}
+
+// Entries overlapping a range of time, by unix timestamp
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntriesRequest {
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EntriesResponse {
+    Success(Vec<Entry>),
+    InvalidRequest,
+}
+
+// The timer of the user in the Authorization header
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RunningResponse {
+    Running(Entry),
+    Stopped,
+    InvalidRequest,
+}
+
+// Start the timer, the server sets the start time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StartTimerRequest {
+    pub activity: String,
+}
+
+// Stop the timer, the server sets the end time
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StopTimerRequest {
+    pub id: u64,
+}
+
+// Add an entry by hand, or change one
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SaveEntryRequest {
+    // None adds a new entry
+    pub id: Option<u64>,
+    pub activity: String,
+    pub start: u64,
+    pub end: u64,
+    pub notes: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EntryResponse {
+    // The entry as the server saved it
+    Success(Entry),
+    NotFound,
+    // Starting a timer while another one runs
+    AlreadyRunning(Entry),
+    // End before start, or no activity
+    BadTimes,
+    InvalidRequest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeleteEntryRequest {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeleteEntryResponse {
+    Success,
+    NotFound,
+    InvalidRequest,
+}
+
+// How the user wants dates shown
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Preferences {
+    // Day weeks start on, 0 for Sunday
+    pub first_day: u32,
+    // IANA time zone, i.e. "Europe/Paris". None follows the browser.
+    pub time_zone: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum PreferencesResponse {
+    Success(Preferences),
+    InvalidRequest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SavePreferencesResponse {
+    Success,
+    BadTimeZone,
+    InvalidRequest,
+}
+
+// Something scheduled on the calendar
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CalendarEvent {
+    pub id: u64,
+    // iCalendar UID, stays the same through export and import
+    pub uid: String,
+    pub title: String,
+    // Unix timestamps, in seconds. For repeating events, the first occurrence.
+    pub start: u64,
+    pub end: u64,
+    pub notes: String,
+    // RRULE value if the event repeats, expanded by the client
+    pub rrule: Option<String>,
+    // Starts of occurrences taken out of the series
+    pub exdates: Vec<u64>,
+}
+
+// Events overlapping a range of time, by unix timestamp.
+// Repeating events come back when they start before the range ends
+// and their rule doesn't end before it starts.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventsRequest {
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EventsResponse {
+    Success(Vec<CalendarEvent>),
+    InvalidRequest,
+}
+
+// Add an event, or change one
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SaveEventRequest {
+    // None adds a new event
+    pub id: Option<u64>,
+    pub title: String,
+    pub start: u64,
+    pub end: u64,
+    pub notes: String,
+    pub rrule: Option<String>,
+    pub exdates: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EventResponse {
+    // The event as the server saved it
+    Success(CalendarEvent),
+    NotFound,
+    // End before start, or no title
+    BadTimes,
+    InvalidRequest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeleteEventRequest {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeleteEventResponse {
+    Success,
+    NotFound,
+    InvalidRequest,
+}
+
+// An event read from an .ics file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ImportedEvent {
+    pub uid: String,
+    pub title: String,
+    pub start: u64,
+    pub end: u64,
+    pub notes: String,
+    pub rrule: Option<String>,
+    pub exdates: Vec<u64>,
+}
+
+// Events to add, ones with a UID already in the calendar are skipped
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ImportEventsRequest {
+    pub events: Vec<ImportedEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportEventsResponse {
+    // How many were added
+    Success(u32),
+    InvalidRequest,
+}
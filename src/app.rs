@@ -1,23 +1,46 @@
+use crate::account::Account;
+use crate::api;
+use crate::calendar::Calendar;
+use crate::config::{self, ConfigError};
+use crate::error_page::ErrorPage;
+use crate::forgot::Forgot;
+use crate::guard::Guard;
 use crate::home::Home;
 use crate::login::Login;
+use crate::logout::Logout;
+use crate::prefs;
 use crate::register::Register;
+use crate::reset::Reset;
+use crate::session::{self, SessionContext};
+use crate::sync;
+use crate::timer::Timer;
+use crate::toast::Notices;
 use crate::track::Track;
 use crate::util;
 use crate::verify::Verify;
 use html_escape::encode_text;
-use url_escape::decode;
+use std::rc::Rc;
+use url_escape::{decode, encode_component};
 use yew::prelude::*;
 use yew_router::prelude::*;
 
 // All the routes available to the user
 #[derive(Routable, Clone, PartialEq)]
 pub enum AppRoute {
-    #[at("/login/verify/:code")]
-    LoginVerify { code: u128 },
+    #[at("/login/return/:to")]
+    LoginReturn { to: String },
     #[at("/login")]
     Login,
+    #[at("/logout/all")]
+    LogoutAll,
+    #[at("/logout")]
+    Logout,
     #[at("/register")]
     Register,
+    #[at("/reset/:code")]
+    Reset { code: u128 },
+    #[at("/reset")]
+    Forgot,
     #[at("/account")]
     Account,
     #[at("/user/:username")]
@@ -32,6 +55,10 @@ pub enum AppRoute {
     Donate,
     #[at("/track")]
     Track,
+    #[at("/timer")]
+    Timer,
+    #[at("/calendar")]
+    Calendar,
     #[at("/404")]
     PageNotFound,
     #[at("/")]
@@ -41,9 +68,13 @@ pub enum AppRoute {
 impl Into<String> for AppRoute {
     fn into(self) -> String {
         match self {
-            AppRoute::LoginVerify { code: _ } => String::from("Login"),
+            AppRoute::LoginReturn { to: _ } => String::from("Login"),
             AppRoute::Login => String::from("Login"),
+            AppRoute::LogoutAll => String::from("Logout everywhere"),
+            AppRoute::Logout => String::from("Logout"),
             AppRoute::Register => String::from("Register"),
+            AppRoute::Reset { code: _ } => String::from("Reset password"),
+            AppRoute::Forgot => String::from("Forgot password"),
             AppRoute::Account => String::from("Account"),
             AppRoute::UserPage { username: s } => encode_text(&decode(&s)).to_string(),
             AppRoute::Verify { code } => format!("verify {}", code.to_string()),
@@ -51,53 +82,152 @@ impl Into<String> for AppRoute {
             AppRoute::About => String::from("About"),
             AppRoute::Donate => String::from("Donate"),
             AppRoute::Track => String::from("Track"),
+            AppRoute::Timer => String::from("Timer"),
+            AppRoute::Calendar => String::from("Calendar"),
             AppRoute::Home => String::from("Home"),
             AppRoute::PageNotFound => String::from("Not Found"),
         }
     }
 }
 
+impl AppRoute {
+    // Pages only for logged in users
+    pub fn needs_auth(&self) -> bool {
+        matches!(
+            self,
+            AppRoute::Account
+                | AppRoute::Feed
+                | AppRoute::Track
+                | AppRoute::Calendar
+                | AppRoute::Verify { .. }
+        )
+    }
+
+    // Pages that make no sense for logged in users
+    pub fn anonymous_only(&self) -> bool {
+        matches!(
+            self,
+            AppRoute::Login | AppRoute::LoginReturn { .. } | AppRoute::Register
+        )
+    }
+
+    // Login page that comes back here afterwards
+    pub fn login_return(&self) -> AppRoute {
+        AppRoute::LoginReturn {
+            to: encode_component(&self.to_path()).to_string(),
+        }
+    }
+
+    // Where a logged in user should end up after visiting this route
+    pub fn after_login(&self) -> AppRoute {
+        match self {
+            AppRoute::LoginReturn { to } => {
+                AppRoute::recognize(&decode(to)).unwrap_or(AppRoute::Feed)
+            }
+            _ => AppRoute::Feed,
+        }
+    }
+}
+
 // QOL
 pub type AppRouter = Router<AppRoute>;
 
 // Main page messages
-pub enum Msg {}
+pub enum Msg {
+    Session(SessionContext),
+    Probe,
+    Probed(bool),
+}
 
 // Overall page component containing everything else
-pub struct Main {}
+// Owns the session and hands it down to every page through a context
+pub struct Main {
+    session: SessionContext,
+    // Set when the app can't run, shown instead of any page
+    error: Option<ConfigError>,
+}
 
 impl Component for Main {
     type Message = Msg;
     type Properties = ();
 
-    fn create(_ctx: &Context<Self>) -> Self {
-        util::try_login();
-        Self {}
+    fn create(ctx: &Context<Self>) -> Self {
+        session::subscribe(ctx.link().callback(Msg::Session));
+        let error = config::error();
+        if error.is_none() {
+            prefs::init();
+            sync::start();
+            util::try_login();
+            ctx.link().send_message(Msg::Probe);
+        }
+        Self {
+            session: Rc::new(session::current()),
+            error,
+        }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, _msg: Self::Message) -> bool {
-        false
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Session(session) => {
+                let changed = self.session != session;
+                self.session = session;
+                changed
+            }
+            Msg::Probe => {
+                ctx.link()
+                    .send_future(async { Msg::Probed(api::probe().await) });
+                false
+            }
+            Msg::Probed(reachable) => {
+                let error = if reachable {
+                    None
+                } else {
+                    Some(ConfigError::Unreachable(config::get().api_url))
+                };
+                let changed = self.error != error;
+                self.error = error;
+                changed
+            }
+        }
     }
 
-    fn view(&self, _ctx: &Context<Self>) -> Html {
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if let Some(error) = &self.error {
+            let onretry = ctx.link().callback(|_| Msg::Probe);
+            return html! { <ErrorPage error={error.clone()} onretry={onretry} /> };
+        }
+
         // Render whatever page the URL bar says to
         html! {
-            <AppRouter render={AppRouter::render(switch)} />
+            <ContextProvider<SessionContext> context={self.session.clone()}>
+                <Notices />
+                <AppRouter render={AppRouter::render(switch)} />
+            </ContextProvider<SessionContext>>
         }
     }
 }
 
-// Display the right component based on what route is in use
+// Check the route is allowed before showing it
 fn switch(switch: &AppRoute) -> Html {
-    match switch {
+    html! { <Guard route={switch.clone()} /> }
+}
+
+// Display the right component based on what route is in use
+pub fn page(route: &AppRoute) -> Html {
+    match route {
         AppRoute::Home => html! { <Home /> },
-        AppRoute::LoginVerify { code } => {
-            html! { <Login href={Some(AppRoute::Verify { code: *code })} /> }
-        }
+        AppRoute::LoginReturn { to: _ } => html! { <Login href={Some(route.after_login())} /> },
         AppRoute::Login => html! { <Login /> },
-        AppRoute::Register => html! { <Register /> },
+        AppRoute::Logout => html! { <Logout /> },
+        AppRoute::LogoutAll => html! { <Logout everywhere={true} /> },
+        AppRoute::Register if config::get().registration => html! { <Register /> },
+        AppRoute::Forgot => html! { <Forgot /> },
+        AppRoute::Reset { code } => html! { <Reset code={*code} /> },
         AppRoute::Verify { code } => html! { <Verify code={*code} /> },
         AppRoute::Track => html! { <Track /> },
+        AppRoute::Timer => html! { <Timer /> },
+        AppRoute::Calendar => html! { <Calendar /> },
+        AppRoute::Account => html! { <Account /> },
         _ => html! { <Home /> },
     }
 }
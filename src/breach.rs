@@ -0,0 +1,73 @@
+use crate::config;
+use reqwasm::http::Request;
+use sha1::Sha1;
+
+// Checks new passwords against passwords leaked in data breaches, the
+// Have I Been Pwned way: only the first 5 hex digits of the SHA-1 hash are sent,
+// the server answers with every hash suffix it knows for that prefix and the match
+// happens here. The full hash never leaves the browser.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Breach {
+    // Not in any known breach
+    Clean,
+    // Seen this many times in breaches
+    Found(u64),
+    // Checking is turned off or the range server could not be reached
+    Unknown,
+}
+
+impl Breach {
+    // Something to tell the user, if anything
+    pub fn message(&self) -> Option<String> {
+        match self {
+            Breach::Found(count) => Some(format!(
+                "This password has been seen {} times in data breaches. Please pick another.",
+                count
+            )),
+            Breach::Clean | Breach::Unknown => None,
+        }
+    }
+}
+
+// Upper case hex SHA-1, split into the prefix that is sent and the suffix that isn't
+fn hash(password: &str) -> (String, String) {
+    let hex = Sha1::from(password).digest().to_string().to_uppercase();
+    let (prefix, suffix) = hex.split_at(5);
+    (String::from(prefix), String::from(suffix))
+}
+
+// Find suffix in a range response, lines of "SUFFIX:COUNT"
+fn find(range: &str, suffix: &str) -> Breach {
+    range
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        // Padding entries have a count of 0
+        .filter(|count| *count > 0)
+        .map_or(Breach::Clean, Breach::Found)
+}
+
+// Look a password up in the configured range API
+pub async fn check(password: &str) -> Breach {
+    let url = config::get().breach_url;
+    if url.is_empty() || password.is_empty() {
+        return Breach::Unknown;
+    }
+
+    let (prefix, suffix) = hash(password);
+    let resp = Request::get(&format!("{}{}", url, prefix))
+        // Make every response about the same size so the prefix can't be guessed from it
+        .header("Add-Padding", "true")
+        .send()
+        .await;
+    let resp = match resp {
+        Ok(resp) if resp.status() == 200 => resp,
+        _ => return Breach::Unknown,
+    };
+    match resp.text().await {
+        Ok(range) => find(&range, &suffix),
+        Err(_) => Breach::Unknown,
+    }
+}
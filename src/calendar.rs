@@ -0,0 +1,665 @@
+use crate::api::{
+    self, ApiResult, CalendarEvent, Client, DeleteEventRequest, DeleteEventResponse,
+    EntriesRequest, EntriesResponse, EventResponse, EventsRequest, EventsResponse,
+    SaveEventRequest,
+};
+use crate::app::AppRoute;
+use crate::nav::Nav;
+use crate::prefs;
+use crate::recur::Rule;
+use crate::time;
+use crate::toast;
+use std::rc::Rc;
+use web_sys::MouseEvent;
+use yew::prelude::*;
+
+// Week and day views, a column of half hour slots per day
+mod days;
+// Form for adding and changing events
+mod editor;
+// Month view, a grid of days
+mod month;
+// iCalendar import and export
+mod transfer;
+use days::*;
+use editor::*;
+use month::*;
+use transfer::*;
+
+// Something shown on the calendar, an event or tracked time
+#[derive(Clone, PartialEq, Debug)]
+pub struct Item {
+    // Id of the event, None for tracked time which is changed on the tracking page
+    pub event: Option<u64>,
+    pub title: String,
+    pub start: u64,
+    pub end: u64,
+    // One occurrence of a repeating event
+    pub repeats: bool,
+}
+
+impl Item {
+    pub fn overlaps(&self, from: u64, to: u64) -> bool {
+        self.start < to && self.end > from
+    }
+
+    pub fn class(&self) -> &'static str {
+        match (self.event, self.repeats) {
+            (Some(_), true) => "calitem calevent calrepeat",
+            (Some(_), false) => "calitem calevent",
+            (None, _) => "calitem calentry",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum View {
+    Month,
+    Week,
+    Day,
+}
+
+impl View {
+    fn name(&self) -> &'static str {
+        match self {
+            View::Month => "Month",
+            View::Week => "Week",
+            View::Day => "Day",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum CalendarMsg {
+    SetView(View),
+    Prev,
+    Next,
+    Today,
+    Load,
+    // Answers are tagged with the start of the range they were asked for,
+    // so ones for a range the user already left are dropped
+    Loaded(u64, ApiResult<EventsResponse>),
+    LoadedEntries(u64, ApiResult<EntriesResponse>),
+    // The server's preferences came in, true if they changed
+    PrefsLoaded(bool),
+    // Open the day view on a day
+    ShowDay(u64),
+    // Open the editor for a new event between two times
+    Create(u64, u64),
+    // Open the editor for an event, by id and the start of the occurrence clicked
+    Open(u64, u64),
+    Cancel,
+    Save(SaveEventRequest, Scope),
+    Delete(Scope),
+    // The changes went through, or what the server refused
+    Changed(ApiResult<Option<&'static str>>),
+    // Show or hide import and export
+    ToggleTransfer,
+}
+
+// What the editor is open for
+#[derive(Clone, PartialEq, Debug)]
+struct Draft {
+    // None for a new event
+    event: Option<CalendarEvent>,
+    start: u64,
+    end: u64,
+    // Start of the occurrence opened, for repeating events
+    occurrence: Option<u64>,
+}
+
+impl Draft {
+    // The series and the occurrence opened, if one occurrence of a repeating event was
+    fn series(&self) -> Option<(&CalendarEvent, Rule, u64)> {
+        let event = self.event.as_ref()?;
+        let rule = Rule::parse(event.rrule.as_deref()?)?;
+        Some((event, rule, self.occurrence?))
+    }
+}
+
+// One request in saving or deleting, they are sent in order
+#[derive(Clone, Debug)]
+enum Change {
+    Save(SaveEventRequest),
+    Delete(u64),
+}
+
+// Send changes one after the other, stopping at the first one refused
+async fn apply(changes: Vec<Change>) -> ApiResult<Option<&'static str>> {
+    let client = Client::new();
+    for change in changes {
+        match change {
+            Change::Save(req) => match client.save_event(&req).await? {
+                EventResponse::Success(_) => (),
+                EventResponse::NotFound => return Ok(Some("That event does not exist anymore.")),
+                EventResponse::BadTimes => return Ok(Some("An event has to end after it starts.")),
+                EventResponse::InvalidRequest => return Ok(Some(api::REJECTED)),
+            },
+            Change::Delete(id) => match client.delete_event(&DeleteEventRequest { id }).await? {
+                DeleteEventResponse::InvalidRequest => return Ok(Some(api::REJECTED)),
+                // Already gone is as good as deleted
+                DeleteEventResponse::Success | DeleteEventResponse::NotFound => (),
+            },
+        }
+    }
+    Ok(None)
+}
+
+// A timestamp moved by some seconds
+fn shifted(ts: u64, by: i64) -> u64 {
+    (ts as i64 + by).max(0) as u64
+}
+
+// A request saving an event as it is
+fn unchanged(event: &CalendarEvent) -> SaveEventRequest {
+    SaveEventRequest {
+        id: Some(event.id),
+        title: event.title.clone(),
+        start: event.start,
+        end: event.end,
+        notes: event.notes.clone(),
+        rrule: event.rrule.clone(),
+        exdates: event.exdates.clone(),
+    }
+}
+
+// The series cut short so its last occurrence is the one before at
+fn truncated(event: &CalendarEvent, rule: &Rule, at: u64) -> SaveEventRequest {
+    let mut rule = rule.clone();
+    rule.count = None;
+    rule.until = Some(at - 1);
+    let mut req = unchanged(event);
+    req.rrule = Some(rule.to_string());
+    req.exdates.retain(|exdate| *exdate < at);
+    req
+}
+
+// Requests making an edit of a draft, for the occurrences in scope
+fn save_changes(draft: &Draft, req: SaveEventRequest, scope: Scope) -> Vec<Change> {
+    let (series, rule, at) = match draft.series() {
+        Some(series) => series,
+        None => return vec![Change::Save(req)],
+    };
+    // How far the occurrence was moved
+    let shift = req.start as i64 - at as i64;
+    match scope {
+        // The occurrence leaves the series and becomes an event of its own
+        Scope::This => {
+            let mut old = unchanged(series);
+            old.exdates.push(at);
+            let new = SaveEventRequest {
+                id: None,
+                rrule: None,
+                exdates: Vec::new(),
+                ..req
+            };
+            vec![Change::Save(old), Change::Save(new)]
+        }
+        // The series ends before the occurrence and a new one picks up from it
+        Scope::Following if at > series.start => {
+            let mut rrule = req.rrule.as_deref().and_then(Rule::parse);
+            // A counted series keeps the number of occurrences left
+            if let Some(new) = rrule.as_mut() {
+                if let (Some(count), true) = (rule.count, new.count == rule.count) {
+                    let before = rule.count_before(series.start, at);
+                    new.count = Some(count.saturating_sub(before).max(1));
+                }
+            }
+            let new = SaveEventRequest {
+                id: None,
+                rrule: rrule.map(|rule| rule.to_string()),
+                exdates: series
+                    .exdates
+                    .iter()
+                    .filter(|exdate| **exdate > at)
+                    .map(|exdate| shifted(*exdate, shift))
+                    .collect(),
+                ..req
+            };
+            vec![
+                Change::Save(truncated(series, &rule, at)),
+                Change::Save(new),
+            ]
+        }
+        // The whole series moves by as much as the occurrence did
+        Scope::Following | Scope::All => {
+            let start = shifted(series.start, shift);
+            let all = SaveEventRequest {
+                id: Some(series.id),
+                start,
+                end: start + (req.end - req.start),
+                exdates: series
+                    .exdates
+                    .iter()
+                    .map(|exdate| shifted(*exdate, shift))
+                    .collect(),
+                ..req
+            };
+            vec![Change::Save(all)]
+        }
+    }
+}
+
+// Requests deleting a draft, for the occurrences in scope
+fn delete_changes(draft: &Draft, scope: Scope) -> Vec<Change> {
+    let id = match &draft.event {
+        Some(event) => event.id,
+        None => return Vec::new(),
+    };
+    let (series, rule, at) = match draft.series() {
+        Some(series) => series,
+        None => return vec![Change::Delete(id)],
+    };
+    match scope {
+        Scope::This => {
+            let mut old = unchanged(series);
+            old.exdates.push(at);
+            vec![Change::Save(old)]
+        }
+        Scope::Following if at > series.start => {
+            vec![Change::Save(truncated(series, &rule, at))]
+        }
+        Scope::Following | Scope::All => vec![Change::Delete(id)],
+    }
+}
+
+// Events and tracked time by month, week or day
+pub struct Calendar {
+    view: View,
+    // Start of the day the view is around, unix timestamp
+    anchor: u64,
+    first_day: u32,
+    events: Vec<CalendarEvent>,
+    // Tracked time in the same range, read only
+    entries: Vec<Item>,
+    loading: bool,
+    draft: Option<Draft>,
+    // Waiting on the server to save or delete an event
+    saving: bool,
+    failure: Option<String>,
+    transfer: bool,
+}
+
+impl Calendar {
+    // Time shown by the current view, from and to as unix timestamps
+    fn range(&self) -> (u64, u64) {
+        match self.view {
+            View::Month => {
+                let from = time::start_of_week(time::add_months(self.anchor, 0), self.first_day);
+                (from, time::add_days(from, 42))
+            }
+            View::Week => {
+                let from = time::start_of_week(self.anchor, self.first_day);
+                (from, time::add_days(from, 7))
+            }
+            View::Day => (self.anchor, time::add_days(self.anchor, 1)),
+        }
+    }
+
+    // Move the view back (-1) or forward (1)
+    fn step(&mut self, by: i32) {
+        self.anchor = match self.view {
+            View::Month => time::add_months(self.anchor, by),
+            View::Week => time::add_days(self.anchor, 7 * by),
+            View::Day => time::add_days(self.anchor, by),
+        };
+    }
+
+    fn title(&self) -> String {
+        match self.view {
+            View::Month => time::format_month(self.anchor),
+            View::Week => format!("Week of {}", time::format_day(self.range().0)),
+            View::Day => time::format_day(self.anchor),
+        }
+    }
+
+    // Events and tracked time together, by start.
+    // Repeating events come in once for each occurrence in view.
+    fn items(&self) -> Rc<Vec<Item>> {
+        let (from, to) = self.range();
+        let mut items: Vec<Item> = self
+            .events
+            .iter()
+            .flat_map(|event| {
+                let length = event.end.saturating_sub(event.start);
+                let rule = event.rrule.as_deref().and_then(Rule::parse);
+                let starts = match &rule {
+                    Some(rule) => rule.occurrences(event.start, length, &event.exdates, from, to),
+                    // Rules we can't follow show the first time only
+                    None => vec![event.start],
+                };
+                starts.into_iter().map(move |start| Item {
+                    event: Some(event.id),
+                    title: event.title.clone(),
+                    start,
+                    end: start + length,
+                    repeats: rule.is_some(),
+                })
+            })
+            .chain(self.entries.iter().cloned())
+            .collect();
+        items.sort_by_key(|item| (item.start, item.end));
+        Rc::new(items)
+    }
+
+    fn header(&self, ctx: &Context<Self>) -> Html {
+        let prev = ctx.link().callback(|_: MouseEvent| CalendarMsg::Prev);
+        let today = ctx.link().callback(|_: MouseEvent| CalendarMsg::Today);
+        let next = ctx.link().callback(|_: MouseEvent| CalendarMsg::Next);
+        let transfer = ctx
+            .link()
+            .callback(|_: MouseEvent| CalendarMsg::ToggleTransfer);
+        let views = [View::Month, View::Week, View::Day]
+            .into_iter()
+            .map(|view| {
+                let class = if view == self.view {
+                    "calviewcurrent"
+                } else {
+                    ""
+                };
+                let onclick = ctx
+                    .link()
+                    .callback(move |_: MouseEvent| CalendarMsg::SetView(view));
+                html! { <button class={class} onclick={onclick}>{ view.name() }</button> }
+            })
+            .collect::<Html>();
+        html! {
+            <section id="calendar-header">
+                <div class="calnav">
+                    <button onclick={prev}>{ "<" }</button>
+                    <button onclick={today}>{ "Today" }</button>
+                    <button onclick={next}>{ ">" }</button>
+                </div>
+                <h1>{ self.title() }</h1>
+                <div class="calviews">
+                    { views }
+                    <button onclick={transfer}>{ "Import / export" }</button>
+                </div>
+            </section>
+        }
+    }
+
+    fn editor(&self, ctx: &Context<Self>) -> Html {
+        let draft = match &self.draft {
+            Some(draft) => draft.clone(),
+            None => return html! {},
+        };
+        // A new key for each draft, so the form starts over
+        let key = format!(
+            "{:?}-{}-{}",
+            draft.event.as_ref().map(|event| event.id),
+            draft.start,
+            draft.end
+        );
+        let onsave = ctx
+            .link()
+            .callback(|(req, scope)| CalendarMsg::Save(req, scope));
+        let ondelete = ctx.link().callback(CalendarMsg::Delete);
+        let series = draft.series().is_some();
+        let oncancel = ctx.link().callback(|_| CalendarMsg::Cancel);
+        html! {
+            <section id="calendar-editor">
+                <EventEditor key={key} event={draft.event} start={draft.start} end={draft.end}
+                    series={series} saving={self.saving} onsave={onsave} ondelete={ondelete} oncancel={oncancel} />
+            </section>
+        }
+    }
+
+    fn transfer(&self, ctx: &Context<Self>) -> Html {
+        if !self.transfer {
+            return html! {};
+        }
+        let (from, to) = self.range();
+        let onimported = ctx.link().callback(|_| CalendarMsg::Load);
+        html! {
+            <section id="calendar-transfer">
+                <Transfer from={from} to={to} onimported={onimported} />
+            </section>
+        }
+    }
+
+    fn body(&self, ctx: &Context<Self>) -> Html {
+        let items = self.items();
+        let oncreate = ctx
+            .link()
+            .callback(|(start, end): (u64, u64)| CalendarMsg::Create(start, end));
+        let onopen = ctx
+            .link()
+            .callback(|(id, at): (u64, u64)| CalendarMsg::Open(id, at));
+        match self.view {
+            View::Month => {
+                let onday = ctx.link().callback(CalendarMsg::ShowDay);
+                html! {
+                    <Month month={time::add_months(self.anchor, 0)} first_day={self.first_day}
+                        items={items} onday={onday} oncreate={oncreate} onopen={onopen} />
+                }
+            }
+            View::Week => html! {
+                <Days start={self.range().0} days={7} items={items} oncreate={oncreate} onopen={onopen} />
+            },
+            View::Day => html! {
+                <Days start={self.anchor} days={1} items={items} oncreate={oncreate} onopen={onopen} />
+            },
+        }
+    }
+}
+
+impl Component for Calendar {
+    type Message = CalendarMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(CalendarMsg::Load);
+        ctx.link()
+            .send_future(async { CalendarMsg::PrefsLoaded(prefs::load().await) });
+        Self {
+            view: View::Month,
+            anchor: time::start_of_day(time::now()),
+            first_day: prefs::get().first_day,
+            events: Vec::new(),
+            entries: Vec::new(),
+            loading: true,
+            draft: None,
+            saving: false,
+            failure: None,
+            transfer: false,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            CalendarMsg::SetView(view) => {
+                if view == self.view {
+                    return false;
+                }
+                self.view = view;
+                ctx.link().send_message(CalendarMsg::Load);
+                true
+            }
+            CalendarMsg::Prev => {
+                self.step(-1);
+                ctx.link().send_message(CalendarMsg::Load);
+                true
+            }
+            CalendarMsg::Next => {
+                self.step(1);
+                ctx.link().send_message(CalendarMsg::Load);
+                true
+            }
+            CalendarMsg::Today => {
+                self.anchor = time::start_of_day(time::now());
+                ctx.link().send_message(CalendarMsg::Load);
+                true
+            }
+            CalendarMsg::ShowDay(day) => {
+                self.view = View::Day;
+                self.anchor = time::start_of_day(day);
+                ctx.link().send_message(CalendarMsg::Load);
+                true
+            }
+            CalendarMsg::Load => {
+                let (from, to) = self.range();
+                ctx.link().send_future(async move {
+                    let req = EventsRequest { from, to };
+                    CalendarMsg::Loaded(from, Client::new().events(&req).await)
+                });
+                ctx.link().send_future(async move {
+                    let req = EntriesRequest { from, to };
+                    CalendarMsg::LoadedEntries(from, Client::new().entries(&req).await)
+                });
+                self.loading = true;
+                true
+            }
+            CalendarMsg::Loaded(from, resp) => {
+                if from != self.range().0 {
+                    return false;
+                }
+                self.loading = false;
+                match resp {
+                    Ok(EventsResponse::Success(events)) => self.events = events,
+                    Ok(EventsResponse::InvalidRequest) => {
+                        self.failure = Some(String::from(api::REJECTED))
+                    }
+                    Err(e) => {
+                        let retry = ctx.link().callback(|_| CalendarMsg::Load);
+                        toast::api_error_with_retry(&e, Some(retry));
+                    }
+                }
+                true
+            }
+            CalendarMsg::LoadedEntries(from, resp) => {
+                if from != self.range().0 {
+                    return false;
+                }
+                match resp {
+                    Ok(EntriesResponse::Success(entries)) => {
+                        self.entries = entries
+                            .into_iter()
+                            .map(|entry| Item {
+                                event: None,
+                                title: entry.activity,
+                                start: entry.start,
+                                // A running timer counts up to now
+                                end: entry.end.unwrap_or_else(time::now),
+                                repeats: false,
+                            })
+                            .collect();
+                    }
+                    Ok(EntriesResponse::InvalidRequest) => {
+                        self.failure = Some(String::from(api::REJECTED))
+                    }
+                    // Loaded already toasts about the same problem
+                    Err(_) => (),
+                }
+                true
+            }
+            CalendarMsg::PrefsLoaded(changed) => {
+                if !changed {
+                    return false;
+                }
+                // The time zone may have moved midnight
+                self.first_day = prefs::get().first_day;
+                self.anchor = time::start_of_day(self.anchor);
+                ctx.link().send_message(CalendarMsg::Load);
+                true
+            }
+            CalendarMsg::Create(start, end) => {
+                self.draft = Some(Draft {
+                    event: None,
+                    start,
+                    end,
+                    occurrence: None,
+                });
+                self.failure = None;
+                true
+            }
+            CalendarMsg::Open(id, at) => {
+                let event = match self.events.iter().find(|event| event.id == id) {
+                    Some(event) => event.clone(),
+                    None => return false,
+                };
+                // Repeating events open on the occurrence clicked
+                let repeats = event.rrule.as_deref().and_then(Rule::parse).is_some();
+                self.draft = Some(if repeats {
+                    Draft {
+                        start: at,
+                        end: at + event.end.saturating_sub(event.start),
+                        event: Some(event),
+                        occurrence: Some(at),
+                    }
+                } else {
+                    Draft {
+                        start: event.start,
+                        end: event.end,
+                        event: Some(event),
+                        occurrence: None,
+                    }
+                });
+                self.failure = None;
+                true
+            }
+            CalendarMsg::Cancel => {
+                self.draft = None;
+                true
+            }
+            CalendarMsg::Save(req, scope) => {
+                let draft = match &self.draft {
+                    Some(draft) if !self.saving => draft,
+                    _ => return false,
+                };
+                let changes = save_changes(draft, req, scope);
+                ctx.link()
+                    .send_future(async move { CalendarMsg::Changed(apply(changes).await) });
+                self.saving = true;
+                self.failure = None;
+                true
+            }
+            CalendarMsg::Delete(scope) => {
+                let draft = match &self.draft {
+                    Some(draft) if !self.saving => draft,
+                    _ => return false,
+                };
+                let changes = delete_changes(draft, scope);
+                ctx.link()
+                    .send_future(async move { CalendarMsg::Changed(apply(changes).await) });
+                self.saving = true;
+                self.failure = None;
+                true
+            }
+            CalendarMsg::Changed(resp) => {
+                self.saving = false;
+                match resp {
+                    // A change can touch occurrences all over, so load the range again
+                    Ok(None) => {
+                        self.draft = None;
+                        ctx.link().send_message(CalendarMsg::Load);
+                    }
+                    Ok(Some(failure)) => self.failure = Some(String::from(failure)),
+                    Err(e) => toast::api_error(&e),
+                }
+                true
+            }
+            CalendarMsg::ToggleTransfer => {
+                self.transfer = !self.transfer;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let failure_text = self.failure.clone().unwrap_or_default();
+        html! {
+            <>
+                <Nav route={AppRoute::Calendar} />
+                <main class="content" id="calendar">
+                    { self.header(ctx) }
+                    <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>{ failure_text }</p>
+                    <p style={if self.loading { "" } else { "display: none;" }}>{ "Loading..." }</p>
+                    { self.transfer(ctx) }
+                    { self.editor(ctx) }
+                    { self.body(ctx) }
+                </main>
+            </>
+        }
+    }
+}
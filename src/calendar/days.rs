@@ -0,0 +1,218 @@
+use super::Item;
+use crate::time;
+use std::rc::Rc;
+use web_sys::MouseEvent;
+use yew::prelude::*;
+
+// Half hour slots in a day
+const SLOTS: u32 = 48;
+
+#[derive(Clone, Copy, Debug)]
+pub enum DaysMsg {
+    // Mouse went down on a slot, by day and slot
+    Press(u32, u32),
+    Enter(u32, u32),
+    Release,
+    // Mouse left the grid, forget the drag
+    Leave,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct DaysProps {
+    // Start of the first day shown
+    pub start: u64,
+    // 7 for a week, 1 for a day
+    pub days: u32,
+    pub items: Rc<Vec<Item>>,
+    // Dragging over slots adds an event over them
+    pub oncreate: Callback<(u64, u64)>,
+    // Clicking an event opens it, by id and the start of the occurrence clicked
+    pub onopen: Callback<(u64, u64)>,
+}
+
+// Days side by side, each a column of half hour slots
+pub struct Days {
+    // Slots being dragged over: the day, and the first and last slot
+    drag: Option<(u32, u32, u32)>,
+}
+
+impl Days {
+    // Start of a slot, slot 48 is the end of the day
+    fn slot_time(day: u64, slot: u32) -> u64 {
+        time::at(day, 0, slot * 30)
+    }
+
+    // The selected slots, in order
+    fn selection(&self) -> Option<(u32, u32, u32)> {
+        self.drag
+            .map(|(day, from, to)| (day, from.min(to), from.max(to)))
+    }
+
+    // Items of a day placed side by side where they overlap.
+    // Each goes in the first lane free by the time it starts.
+    fn lanes<'a>(items: &[&'a Item]) -> (Vec<(&'a Item, usize)>, usize) {
+        let mut ends: Vec<u64> = Vec::new();
+        let placed = items
+            .iter()
+            .map(|item| {
+                let lane = match ends.iter().position(|end| *end <= item.start) {
+                    Some(lane) => lane,
+                    None => {
+                        ends.push(0);
+                        ends.len() - 1
+                    }
+                };
+                ends[lane] = item.end;
+                (*item, lane)
+            })
+            .collect();
+        (placed, ends.len().max(1))
+    }
+
+    fn item(
+        props: &DaysProps,
+        day: u64,
+        next: u64,
+        item: &Item,
+        lane: usize,
+        lanes: usize,
+    ) -> Html {
+        let length = (next - day) as f64;
+        let top = (item.start.max(day) - day) as f64 / length * 100.0;
+        let height = (item.end.min(next) - item.start.max(day)) as f64 / length * 100.0;
+        let width = 100.0 / lanes as f64;
+        let style = format!(
+            "top: {:.3}%; height: {:.3}%; left: {:.3}%; width: {:.3}%;",
+            top,
+            height,
+            lane as f64 * width,
+            width
+        );
+
+        let onopen = props.onopen.clone();
+        let (event, at) = (item.event, item.start);
+        // Don't start a drag on the slots underneath
+        let onmousedown = Callback::from(|e: MouseEvent| e.stop_propagation());
+        let onclick = Callback::from(move |e: MouseEvent| {
+            e.stop_propagation();
+            if let Some(id) = event {
+                onopen.emit((id, at));
+            }
+        });
+        html! {
+            <div class={item.class()} style={style} onmousedown={onmousedown} onclick={onclick}>
+                <span class="caltime">
+                    { format!("{} - {}", time::format_clock(item.start), time::format_clock(item.end)) }
+                </span>
+                <p>{ item.title.clone() }</p>
+            </div>
+        }
+    }
+
+    fn column(&self, ctx: &Context<Self>, index: u32) -> Html {
+        let props = ctx.props();
+        let day = time::add_days(props.start, index as i32);
+        let next = time::add_days(day, 1);
+        let items: Vec<&Item> = props
+            .items
+            .iter()
+            .filter(|item| item.overlaps(day, next))
+            .collect();
+        let (placed, lanes) = Self::lanes(&items);
+
+        let selection = self.selection();
+        let slots = (0..SLOTS).map(|slot| {
+            let selected = matches!(selection, Some((d, from, to)) if d == index && (from..=to).contains(&slot));
+            let class = match (selected, slot % 2) {
+                (true, _) => "calslot calselected",
+                (false, 0) => "calslot calhour",
+                (false, _) => "calslot",
+            };
+            let onmousedown = ctx.link().callback(move |e: MouseEvent| {
+                // No text selection while dragging
+                e.prevent_default();
+                DaysMsg::Press(index, slot)
+            });
+            let onmouseenter = ctx
+                .link()
+                .callback(move |_: MouseEvent| DaysMsg::Enter(index, slot));
+            html! { <div class={class} onmousedown={onmousedown} onmouseenter={onmouseenter} /> }
+        });
+
+        let mut class = String::from("calcolumn");
+        if day == time::start_of_day(time::now()) {
+            class.push_str(" caltoday");
+        }
+        html! {
+            <div class={class}>
+                { for slots }
+                { for placed.into_iter().map(|(item, lane)| Self::item(props, day, next, item, lane, lanes)) }
+            </div>
+        }
+    }
+}
+
+impl Component for Days {
+    type Message = DaysMsg;
+    type Properties = DaysProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { drag: None }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            DaysMsg::Press(day, slot) => {
+                self.drag = Some((day, slot, slot));
+                true
+            }
+            DaysMsg::Enter(day, slot) => match &mut self.drag {
+                // Drags stay within the day they started on
+                Some((drag_day, _, to)) if *drag_day == day && *to != slot => {
+                    *to = slot;
+                    true
+                }
+                _ => false,
+            },
+            DaysMsg::Release => {
+                let (index, from, to) = match self.selection() {
+                    Some(selection) => selection,
+                    None => return false,
+                };
+                self.drag = None;
+                let day = time::add_days(ctx.props().start, index as i32);
+                ctx.props()
+                    .oncreate
+                    .emit((Self::slot_time(day, from), Self::slot_time(day, to + 1)));
+                true
+            }
+            DaysMsg::Leave => self.drag.take().is_some(),
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let headers = (0..props.days).map(|index| {
+            let f = time::fields(time::add_days(props.start, index as i32));
+            let name = time::WEEKDAYS[f.weekday as usize];
+            html! { <div class="calweekday">{ format!("{} {}", &name[..3], f.day) }</div> }
+        });
+        let hours = (0..24)
+            .map(|hour| html! { <div class="calhourlabel">{ format!("{:02}:00", hour) }</div> });
+        let onmouseup = ctx.link().callback(|_: MouseEvent| DaysMsg::Release);
+        let onmouseleave = ctx.link().callback(|_: MouseEvent| DaysMsg::Leave);
+        let columns = format!("grid-template-columns: 4em repeat({}, 1fr);", props.days);
+        html! {
+            <section id="calendar-days" class="caldays">
+                <div class="calheaders" style={columns.clone()}>
+                    <div />
+                    { for headers }
+                </div>
+                <div class="calgrid" style={columns} onmouseup={onmouseup} onmouseleave={onmouseleave}>
+                    <div class="calhours">{ for hours }</div>
+                    { for (0..props.days).map(|index| self.column(ctx, index)) }
+                </div>
+            </section>
+        }
+    }
+}
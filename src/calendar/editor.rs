@@ -0,0 +1,485 @@
+use crate::api::{CalendarEvent, SaveEventRequest};
+use crate::prefs;
+use crate::recur::{Freq, Rule};
+use crate::time;
+use web_sys::{Event, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement, MouseEvent};
+use yew::prelude::*;
+
+// Which occurrences of a repeating event a change is for
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scope {
+    This,
+    Following,
+    All,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Ends {
+    Never,
+    Count,
+    Until,
+}
+
+#[derive(Clone, Debug)]
+pub enum EditorMsg {
+    // One of the repeat selects changed, which fields show depends on them
+    Changed,
+    Save,
+    Delete,
+    Cancel,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct EditorProps {
+    // Event to change, None to add a new one
+    pub event: Option<CalendarEvent>,
+    // Times the form starts out with, the occurrence opened for repeating events
+    pub start: u64,
+    pub end: u64,
+    // One occurrence of a repeating event was opened, ask which ones to change
+    pub series: bool,
+    // Waiting on the server, the buttons are disabled meanwhile
+    pub saving: bool,
+    pub onsave: Callback<(SaveEventRequest, Scope)>,
+    pub ondelete: Callback<Scope>,
+    pub oncancel: Callback<()>,
+}
+
+// Form for an event on the calendar
+pub struct EventEditor {
+    // The rule the event had when opened
+    rule: Option<Rule>,
+    // It repeats on days the form can't show, like imported "15th and last"
+    custom: bool,
+    // None when the event doesn't repeat
+    freq: Option<Freq>,
+    ends: Ends,
+    failure: Option<&'static str>,
+    // Delete was clicked once already
+    confirming: bool,
+    titleref: NodeRef,
+    startdateref: NodeRef,
+    starttimeref: NodeRef,
+    enddateref: NodeRef,
+    endtimeref: NodeRef,
+    notesref: NodeRef,
+    freqref: NodeRef,
+    intervalref: NodeRef,
+    // Weekday checkboxes for weekly rules, 0 for Sunday
+    dayrefs: Vec<NodeRef>,
+    monthlyref: NodeRef,
+    endsref: NodeRef,
+    countref: NodeRef,
+    untilref: NodeRef,
+    scoperef: NodeRef,
+}
+
+impl EventEditor {
+    fn input(node: &NodeRef) -> String {
+        node.cast::<HtmlInputElement>()
+            .map(|input| input.value())
+            .unwrap_or_default()
+    }
+
+    fn select(node: &NodeRef) -> String {
+        node.cast::<HtmlSelectElement>()
+            .map(|select| select.value())
+            .unwrap_or_default()
+    }
+
+    fn checked(node: &NodeRef) -> bool {
+        node.cast::<HtmlInputElement>()
+            .is_some_and(|input| input.checked())
+    }
+
+    // Which one of its weekday in the month a day is, 5th ones count as last
+    fn nth(day: u32) -> i32 {
+        match (day as i32 - 1) / 7 + 1 {
+            5 => -1,
+            nth => nth,
+        }
+    }
+
+    // Whether the form can show the days a rule repeats on, for an event starting at start
+    fn fits(rule: &Rule, start: u64) -> bool {
+        let f = time::fields(start);
+        match rule.freq {
+            Freq::Daily => rule.by_day.is_empty() && rule.by_month_day.is_empty(),
+            Freq::Weekly => {
+                rule.by_month_day.is_empty() && rule.by_day.iter().all(|(nth, _)| nth.is_none())
+            }
+            Freq::Monthly => match (rule.by_day.as_slice(), rule.by_month_day.as_slice()) {
+                ([], []) => true,
+                ([], [day]) => *day == f.day as i32,
+                ([(Some(nth), weekday)], []) => *nth == Self::nth(f.day) && *weekday == f.weekday,
+                _ => false,
+            },
+        }
+    }
+
+    // The days of the original rule are kept, the form can't show them
+    fn keeps_days(&self) -> bool {
+        self.custom && self.rule.as_ref().map(|rule| rule.freq) == self.freq
+    }
+
+    // The repeat fields as an RRULE, None if the event doesn't repeat.
+    // Starts from the rule the event had, so parts the form doesn't show stay as they were.
+    fn rrule(&self, start: u64) -> Result<Option<String>, &'static str> {
+        let freq = match self.freq {
+            Some(freq) => freq,
+            None => return Ok(None),
+        };
+        let f = time::fields(start);
+        let mut rule = match &self.rule {
+            Some(old) if old.freq == freq => old.clone(),
+            Some(old) => Rule {
+                week_start: old.week_start,
+                ..Rule::new(freq)
+            },
+            None => Rule {
+                week_start: prefs::get().first_day,
+                ..Rule::new(freq)
+            },
+        };
+        rule.interval = Self::input(&self.intervalref)
+            .trim()
+            .parse()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or("Invalid interval!")?;
+        if !self.keeps_days() {
+            rule.by_day.clear();
+            rule.by_month_day.clear();
+            match freq {
+                Freq::Weekly => {
+                    rule.by_day = (0..7)
+                        .filter(|day| Self::checked(&self.dayrefs[*day as usize]))
+                        .map(|day| (None, day))
+                        .collect();
+                    if rule.by_day.is_empty() {
+                        rule.by_day.push((None, f.weekday));
+                    }
+                }
+                Freq::Monthly if Self::select(&self.monthlyref) == "weekday" => {
+                    rule.by_day = vec![(Some(Self::nth(f.day)), f.weekday)];
+                }
+                // The day of the month the event starts on
+                Freq::Monthly | Freq::Daily => (),
+            }
+        }
+        rule.count = None;
+        rule.until = None;
+        match self.ends {
+            Ends::Never => (),
+            Ends::Count => {
+                let count = Self::input(&self.countref)
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or("Invalid number of times!")?;
+                rule.count = Some(count);
+            }
+            Ends::Until => {
+                // Occurrences on the last day are included
+                let until = time::parse_input(&Self::input(&self.untilref), "23:59")
+                    .ok_or("Invalid end date!")?;
+                rule.until = Some(until + time::MINUTE - 1);
+            }
+        }
+        Ok(Some(rule.to_string()))
+    }
+
+    // Read the form into a request, or say what is wrong with it
+    fn request(&self, event: Option<&CalendarEvent>) -> Result<SaveEventRequest, &'static str> {
+        let title = Self::input(&self.titleref).trim().to_string();
+        if title.is_empty() {
+            return Err("Missing title!");
+        }
+        let start = time::parse_input(
+            &Self::input(&self.startdateref),
+            &Self::input(&self.starttimeref),
+        )
+        .ok_or("Invalid start time!")?;
+        let end = time::parse_input(
+            &Self::input(&self.enddateref),
+            &Self::input(&self.endtimeref),
+        )
+        .ok_or("Invalid end time!")?;
+        if end <= start {
+            return Err("An event has to end after it starts.");
+        }
+        let notes = self
+            .notesref
+            .cast::<HtmlTextAreaElement>()
+            .map(|notes| notes.value())
+            .unwrap_or_default();
+        Ok(SaveEventRequest {
+            id: event.map(|event| event.id),
+            title,
+            start,
+            end,
+            notes,
+            rrule: self.rrule(start)?,
+            exdates: event.map(|event| event.exdates.clone()).unwrap_or_default(),
+        })
+    }
+
+    fn scope(&self) -> Scope {
+        match Self::select(&self.scoperef).as_str() {
+            "following" => Scope::Following,
+            "all" => Scope::All,
+            _ => Scope::This,
+        }
+    }
+
+    // Interval, days and when it ends, shown while the event repeats
+    fn repeat(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let changed = ctx.link().callback(|_: Event| EditorMsg::Changed);
+        let f = time::fields(props.start);
+        let rule = self.rule.as_ref();
+        let hidden = |shown: bool| if shown { "" } else { "display: none;" };
+
+        let unit = match self.freq {
+            Some(Freq::Daily) => "days",
+            Some(Freq::Weekly) => "weeks",
+            _ => "months",
+        };
+        let interval = rule.map_or(1, |rule| rule.interval).to_string();
+
+        // Weekly rules start out on the weekday of the event
+        let first_day = prefs::get().first_day;
+        let days = (0..7).map(|i| {
+            let day = (first_day + i) % 7;
+            let checked = match rule.filter(|rule| rule.freq == Freq::Weekly) {
+                Some(rule) => rule.by_day.iter().any(|(_, weekday)| *weekday == day),
+                None => day == f.weekday,
+            };
+            let name = time::WEEKDAYS[day as usize];
+            html! {
+                <label class="checkbox">
+                    <input type="checkbox" name="weekday" checked={checked} ref={self.dayrefs[day as usize].clone()} />
+                    { &name[..3] }
+                </label>
+            }
+        });
+
+        let by_weekday =
+            rule.is_some_and(|rule| rule.freq == Freq::Monthly && !rule.by_day.is_empty());
+        let nth = match Self::nth(f.day) {
+            -1 => String::from("last"),
+            1 => String::from("1st"),
+            2 => String::from("2nd"),
+            3 => String::from("3rd"),
+            nth => format!("{}th", nth),
+        };
+
+        let count = rule.and_then(|rule| rule.count).unwrap_or(10).to_string();
+        let until = rule
+            .and_then(|rule| rule.until)
+            .unwrap_or_else(|| time::add_months(props.start, 3));
+        html! {
+            <div class="repeatfields" style={hidden(self.freq.is_some())}>
+                <label>{ format!("Every how many {}", unit) }
+                    <input type="number" name="interval" min="1" value={interval} ref={self.intervalref.clone()} />
+                </label>
+                <p class="repeatrule" style={hidden(self.keeps_days())}>
+                    { "Keeps repeating on the days it was imported with." }
+                </p>
+                <div class="weekdays" style={hidden(self.freq == Some(Freq::Weekly) && !self.keeps_days())}>
+                    { for days }
+                </div>
+                <label style={hidden(self.freq == Some(Freq::Monthly) && !self.keeps_days())}>{ "On" }
+                    <select name="monthly" ref={self.monthlyref.clone()}>
+                        <option value="day" selected={!by_weekday}>{ format!("Day {} of the month", f.day) }</option>
+                        <option value="weekday" selected={by_weekday}>
+                            { format!("The {} {} of the month", nth, time::WEEKDAYS[f.weekday as usize]) }
+                        </option>
+                    </select>
+                </label>
+                <label>{ "Stops repeating" }
+                    <select name="ends" onchange={changed} ref={self.endsref.clone()}>
+                        <option value="never" selected={self.ends == Ends::Never}>{ "Never" }</option>
+                        <option value="count" selected={self.ends == Ends::Count}>{ "After a number of times" }</option>
+                        <option value="until" selected={self.ends == Ends::Until}>{ "On a date" }</option>
+                    </select>
+                </label>
+                <input type="number" name="count" min="1" value={count} ref={self.countref.clone()}
+                    style={hidden(self.ends == Ends::Count)} />
+                <input type="date" name="until" value={time::date_input(until)} ref={self.untilref.clone()}
+                    style={hidden(self.ends == Ends::Until)} />
+            </div>
+        }
+    }
+}
+
+impl Component for EventEditor {
+    type Message = EditorMsg;
+    type Properties = EditorProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let rule = ctx
+            .props()
+            .event
+            .as_ref()
+            .and_then(|event| event.rrule.as_deref())
+            .and_then(Rule::parse);
+        let custom = rule
+            .as_ref()
+            .is_some_and(|rule| !Self::fits(rule, ctx.props().start));
+        let ends = match &rule {
+            Some(rule) if rule.count.is_some() => Ends::Count,
+            Some(rule) if rule.until.is_some() => Ends::Until,
+            _ => Ends::Never,
+        };
+        Self {
+            freq: rule.as_ref().map(|rule| rule.freq),
+            rule,
+            custom,
+            ends,
+            failure: None,
+            confirming: false,
+            titleref: NodeRef::default(),
+            startdateref: NodeRef::default(),
+            starttimeref: NodeRef::default(),
+            enddateref: NodeRef::default(),
+            endtimeref: NodeRef::default(),
+            notesref: NodeRef::default(),
+            freqref: NodeRef::default(),
+            intervalref: NodeRef::default(),
+            dayrefs: (0..7).map(|_| NodeRef::default()).collect(),
+            monthlyref: NodeRef::default(),
+            endsref: NodeRef::default(),
+            countref: NodeRef::default(),
+            untilref: NodeRef::default(),
+            scoperef: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            EditorMsg::Changed => {
+                self.freq = match Self::select(&self.freqref).as_str() {
+                    "daily" => Some(Freq::Daily),
+                    "weekly" => Some(Freq::Weekly),
+                    "monthly" => Some(Freq::Monthly),
+                    _ => None,
+                };
+                self.ends = match Self::select(&self.endsref).as_str() {
+                    "count" => Ends::Count,
+                    "until" => Ends::Until,
+                    _ => Ends::Never,
+                };
+                true
+            }
+            EditorMsg::Save => {
+                if ctx.props().saving {
+                    return false;
+                }
+                match self.request(ctx.props().event.as_ref()) {
+                    Ok(req) => {
+                        self.failure = None;
+                        ctx.props().onsave.emit((req, self.scope()));
+                    }
+                    Err(failure) => self.failure = Some(failure),
+                }
+                true
+            }
+            EditorMsg::Delete => {
+                if ctx.props().event.is_none() || ctx.props().saving {
+                    return false;
+                }
+                if !self.confirming {
+                    self.confirming = true;
+                    return true;
+                }
+                self.confirming = false;
+                ctx.props().ondelete.emit(self.scope());
+                true
+            }
+            EditorMsg::Cancel => {
+                ctx.props().oncancel.emit(());
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let save = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            EditorMsg::Save
+        });
+        let delete = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            EditorMsg::Delete
+        });
+        let cancel = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            EditorMsg::Cancel
+        });
+        let changed = ctx.link().callback(|_: Event| EditorMsg::Changed);
+
+        let (heading, title, notes) = match &props.event {
+            Some(event) => ("Edit event", event.title.clone(), event.notes.clone()),
+            None => ("New event", String::new(), String::new()),
+        };
+        let described = self
+            .rule
+            .as_ref()
+            .map(|rule| rule.describe())
+            .unwrap_or_default();
+        let delete_text = if self.confirming {
+            "Really delete?"
+        } else {
+            "Delete"
+        };
+        let failure_text = self.failure.unwrap_or_default();
+        html! {
+            <form class="eventeditor">
+                <h2>{ heading }</h2>
+                <p class="repeatrule" style={if described.is_empty() { "display: none;" } else { "" }}>{ described }</p>
+                <label>{ "Title" }
+                    <input type="text" name="title" value={title} ref={self.titleref.clone()} />
+                </label>
+                <label>{ "Starts" }
+                    <input type="date" name="startdate" value={time::date_input(props.start)} ref={self.startdateref.clone()} />
+                    <input type="time" name="starttime" value={time::time_input(props.start)} ref={self.starttimeref.clone()} />
+                </label>
+                <label>{ "Ends" }
+                    <input type="date" name="enddate" value={time::date_input(props.end)} ref={self.enddateref.clone()} />
+                    <input type="time" name="endtime" value={time::time_input(props.end)} ref={self.endtimeref.clone()} />
+                </label>
+                <label>{ "Repeats" }
+                    <select name="freq" onchange={changed} ref={self.freqref.clone()}>
+                        <option value="" selected={self.freq.is_none()}>{ "Does not repeat" }</option>
+                        <option value="daily" selected={self.freq == Some(Freq::Daily)}>{ "Daily" }</option>
+                        <option value="weekly" selected={self.freq == Some(Freq::Weekly)}>{ "Weekly" }</option>
+                        <option value="monthly" selected={self.freq == Some(Freq::Monthly)}>{ "Monthly" }</option>
+                    </select>
+                </label>
+                { self.repeat(ctx) }
+                <label>{ "Notes" }
+                    <textarea name="notes" value={notes} ref={self.notesref.clone()} />
+                </label>
+                <label style={if props.series { "" } else { "display: none;" }}>{ "Change" }
+                    <select name="scope" ref={self.scoperef.clone()}>
+                        <option value="this" selected=true>{ "This event" }</option>
+                        <option value="following">{ "This and following events" }</option>
+                        <option value="all">{ "All events" }</option>
+                    </select>
+                </label>
+                <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>{ failure_text }</p>
+                <div class="editorbuttons">
+                    <button type="submit" onclick={save} disabled={props.saving}>{ "Save" }</button>
+                    <button onclick={cancel}>{ "Cancel" }</button>
+                    <button class="dangerbutton" onclick={delete} disabled={props.saving}
+                        style={if props.event.is_none() { "display: none;" } else { "" }}>
+                        { delete_text }
+                    </button>
+                </div>
+            </form>
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use super::Item;
+use crate::time;
+use std::rc::Rc;
+use web_sys::MouseEvent;
+use yew::prelude::*;
+
+// Items shown in a day before the rest fold into "+N more"
+const SHOWN: usize = 3;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct MonthProps {
+    // Start of the month shown
+    pub month: u64,
+    pub first_day: u32,
+    pub items: Rc<Vec<Item>>,
+    // Clicking a day number opens that day
+    pub onday: Callback<u64>,
+    // Clicking an empty part of a day adds an event on it
+    pub oncreate: Callback<(u64, u64)>,
+    // Clicking an event opens it, by id and the start of the occurrence clicked
+    pub onopen: Callback<(u64, u64)>,
+}
+
+// Six weeks around a month, so it always fits
+pub struct Month;
+
+impl Month {
+    fn item(props: &MonthProps, item: &Item) -> Html {
+        let onopen = props.onopen.clone();
+        let (event, at) = (item.event, item.start);
+        // Don't let the click reach the day underneath
+        let onclick = Callback::from(move |e: MouseEvent| {
+            e.stop_propagation();
+            if let Some(id) = event {
+                onopen.emit((id, at));
+            }
+        });
+        html! {
+            <li class={item.class()} onclick={onclick}>
+                <span class="caltime">{ time::format_clock(item.start) }</span>
+                { item.title.clone() }
+            </li>
+        }
+    }
+
+    fn day(props: &MonthProps, day: u64) -> Html {
+        let next = time::add_days(day, 1);
+        let items: Vec<&Item> = props
+            .items
+            .iter()
+            .filter(|item| item.overlaps(day, next))
+            .collect();
+        let more = items.len().saturating_sub(SHOWN);
+
+        let mut class = String::from("calday");
+        if time::add_months(day, 0) != props.month {
+            class.push_str(" calothermonth");
+        }
+        if day == time::start_of_day(time::now()) {
+            class.push_str(" caltoday");
+        }
+
+        let oncreate = props.oncreate.clone();
+        let create = Callback::from(move |_: MouseEvent| {
+            // New events start out as nine to ten in the morning
+            oncreate.emit((time::at(day, 9, 0), time::at(day, 10, 0)))
+        });
+        let show = |onday: &Callback<u64>| {
+            let onday = onday.clone();
+            Callback::from(move |e: MouseEvent| {
+                e.stop_propagation();
+                onday.emit(day);
+            })
+        };
+        html! {
+            <div class={class} onclick={create}>
+                <button class="linkbutton caldaynumber" onclick={show(&props.onday)}>
+                    { time::fields(day).day.to_string() }
+                </button>
+                <ul>
+                    { for items.into_iter().take(SHOWN).map(|item| Self::item(props, item)) }
+                </ul>
+                <button class="linkbutton calmore" onclick={show(&props.onday)}
+                    style={if more == 0 { "display: none;" } else { "" }}>
+                    { format!("+{} more", more) }
+                </button>
+            </div>
+        }
+    }
+}
+
+impl Component for Month {
+    type Message = ();
+    type Properties = MonthProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let start = time::start_of_week(props.month, props.first_day);
+        let names = (0..7).map(|i| {
+            let name = time::WEEKDAYS[((props.first_day + i) % 7) as usize];
+            html! { <div class="calweekday">{ &name[..3] }</div> }
+        });
+        html! {
+            <section id="calendar-month" class="calmonth">
+                { for names }
+                { for (0..42).map(|i| Self::day(props, time::add_days(start, i))) }
+            </section>
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use crate::api::{
+    self, ApiResult, Client, EntriesRequest, EntriesResponse, EventsRequest, EventsResponse,
+    ImportEventsRequest, ImportEventsResponse, ImportedEvent,
+};
+use crate::ics::{self, IcsEvent};
+use crate::prefs;
+use crate::recur::Rule;
+use crate::time;
+use crate::toast;
+use crate::util;
+use std::collections::HashSet;
+use web_sys::{Event, HtmlInputElement, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum TransferMsg {
+    Export,
+    Exported(ApiResult<EventsResponse>, ApiResult<EntriesResponse>),
+    // A file was picked
+    Pick,
+    Read(Result<String, String>),
+    // The events in the file, and the ones already in the calendar over the same time
+    Previewed(Vec<IcsEvent>, ApiResult<EventsResponse>),
+    Import,
+    Imported(ApiResult<ImportEventsResponse>),
+    Discard,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Fate {
+    New,
+    // Its UID is in the calendar already
+    Exists,
+    // Its UID came earlier in the same file
+    Repeated,
+}
+
+impl Fate {
+    fn text(&self) -> &'static str {
+        match self {
+            Fate::New => "New",
+            Fate::Exists => "Already in the calendar",
+            Fate::Repeated => "Repeated in the file",
+        }
+    }
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct TransferProps {
+    // Time the calendar shows, exports cover it unless changed
+    pub from: u64,
+    pub to: u64,
+    // Events were added
+    pub onimported: Callback<()>,
+}
+
+// Moving events in and out as iCalendar files
+pub struct Transfer {
+    busy: bool,
+    // Events read from the picked file, with what importing would do with each
+    preview: Option<Vec<(IcsEvent, Fate)>>,
+    message: Option<String>,
+    failure: Option<String>,
+    fromref: NodeRef,
+    toref: NodeRef,
+    eventsref: NodeRef,
+    entriesref: NodeRef,
+    fileref: NodeRef,
+}
+
+impl Transfer {
+    fn input(node: &NodeRef) -> Option<HtmlInputElement> {
+        node.cast::<HtmlInputElement>()
+    }
+
+    fn checked(node: &NodeRef) -> bool {
+        Self::input(node).is_some_and(|input| input.checked())
+    }
+
+    // Range picked for the export, the end date is included
+    fn range(&self) -> Option<(u64, u64)> {
+        let from = time::parse_input(&Self::input(&self.fromref)?.value(), "00:00")?;
+        let to = time::parse_input(&Self::input(&self.toref)?.value(), "00:00")?;
+        Some((from, time::add_days(to, 1))).filter(|(from, to)| from < to)
+    }
+
+    // What importing would do with each event
+    fn fates(events: Vec<IcsEvent>, existing: &[String]) -> Vec<(IcsEvent, Fate)> {
+        let existing: HashSet<&String> = existing.iter().collect();
+        let mut seen = HashSet::new();
+        events
+            .into_iter()
+            .map(|event| {
+                let fate = if existing.contains(&event.uid) {
+                    Fate::Exists
+                } else if !seen.insert(event.uid.clone()) {
+                    Fate::Repeated
+                } else {
+                    Fate::New
+                };
+                (event, fate)
+            })
+            .collect()
+    }
+
+    fn preview(&self, ctx: &Context<Self>) -> Html {
+        let preview = match &self.preview {
+            Some(preview) => preview,
+            None => return html! {},
+        };
+        let new = preview
+            .iter()
+            .filter(|(_, fate)| *fate == Fate::New)
+            .count();
+        let import = ctx.link().callback(|_: MouseEvent| TransferMsg::Import);
+        let discard = ctx.link().callback(|_: MouseEvent| TransferMsg::Discard);
+        let rows = preview.iter().map(|(event, fate)| {
+            let class = if *fate == Fate::New { "icsevent" } else { "icsevent icsskipped" };
+            let repeats = match event.rrule.as_deref().map(Rule::parse) {
+                Some(Some(rule)) => rule.describe(),
+                Some(None) => String::from("Repeats in a way the calendar can't show, only the first time is imported"),
+                None => String::new(),
+            };
+            html! {
+                <li class={class}>
+                    <span class="caltime">
+                        { format!("{} {} - {}", time::date_input(event.start), time::format_clock(event.start), time::format_clock(event.end)) }
+                    </span>
+                    <span class="icstitle">{ event.title.clone() }</span>
+                    <span class="icsfate">{ fate.text() }</span>
+                    <p class="icsnote">{ repeats }</p>
+                </li>
+            }
+        });
+        html! {
+            <div class="icspreview">
+                <p>{ format!("{} events in the file, {} new.", preview.len(), new) }</p>
+                <ul>{ for rows }</ul>
+                <button onclick={import} disabled={self.busy || new == 0}>{ format!("Import {} events", new) }</button>
+                <button onclick={discard}>{ "Cancel" }</button>
+            </div>
+        }
+    }
+}
+
+impl Component for Transfer {
+    type Message = TransferMsg;
+    type Properties = TransferProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            busy: false,
+            preview: None,
+            message: None,
+            failure: None,
+            fromref: NodeRef::default(),
+            toref: NodeRef::default(),
+            eventsref: NodeRef::default(),
+            entriesref: NodeRef::default(),
+            fileref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            TransferMsg::Export => {
+                if self.busy {
+                    return false;
+                }
+                let (from, to) = match self.range() {
+                    Some(range) => range,
+                    None => {
+                        self.failure = Some(String::from("Invalid dates!"));
+                        return true;
+                    }
+                };
+                let events = Self::checked(&self.eventsref);
+                let entries = Self::checked(&self.entriesref);
+                ctx.link().send_future(async move {
+                    let client = Client::new();
+                    let events = if events {
+                        client.events(&EventsRequest { from, to }).await
+                    } else {
+                        Ok(EventsResponse::Success(Vec::new()))
+                    };
+                    let entries = if entries {
+                        client.entries(&EntriesRequest { from, to }).await
+                    } else {
+                        Ok(EntriesResponse::Success(Vec::new()))
+                    };
+                    TransferMsg::Exported(events, entries)
+                });
+                self.busy = true;
+                self.message = None;
+                self.failure = None;
+                true
+            }
+            TransferMsg::Exported(events, entries) => {
+                self.busy = false;
+                let (events, entries) = match (events, entries) {
+                    (
+                        Ok(EventsResponse::Success(events)),
+                        Ok(EntriesResponse::Success(entries)),
+                    ) => (events, entries),
+                    (Err(e), _) | (_, Err(e)) => {
+                        toast::api_error(&e);
+                        return true;
+                    }
+                    _ => {
+                        self.failure = Some(String::from(api::REJECTED));
+                        return true;
+                    }
+                };
+                let zone = prefs::get().time_zone.unwrap_or_else(time::browser_zone);
+                let text = ics::export(&events, &entries, &zone);
+                let name = match self.range() {
+                    Some((from, to)) => format!(
+                        "nittei-{}-{}.ics",
+                        time::date_input(from),
+                        time::date_input(time::add_days(to, -1))
+                    ),
+                    None => String::from("nittei.ics"),
+                };
+                match util::download(&name, "text/calendar", &text) {
+                    Ok(()) => {
+                        self.message =
+                            Some(format!("Exported {} events.", events.len() + entries.len()))
+                    }
+                    Err(_) => self.failure = Some(String::from("Could not save the file.")),
+                }
+                true
+            }
+            TransferMsg::Pick => {
+                let file = Self::input(&self.fileref)
+                    .and_then(|input| input.files())
+                    .and_then(|files| files.get(0));
+                let file = match file {
+                    Some(file) => file,
+                    None => return false,
+                };
+                ctx.link().send_future(async move {
+                    let text = util::read_file(&file)
+                        .await
+                        .map_err(|_| String::from("Could not read the file."));
+                    TransferMsg::Read(text)
+                });
+                self.busy = true;
+                self.preview = None;
+                self.message = None;
+                self.failure = None;
+                true
+            }
+            TransferMsg::Read(text) => {
+                let events =
+                    match text.and_then(|text| ics::parse(&text).map_err(|e| e.to_string())) {
+                        Ok(events) => events,
+                        Err(failure) => {
+                            self.busy = false;
+                            self.failure = Some(failure);
+                            return true;
+                        }
+                    };
+                if events.is_empty() {
+                    self.busy = false;
+                    self.failure = Some(String::from("There are no events in the file."));
+                    return true;
+                }
+                // Look for the same UIDs over the time the file covers
+                let from = events.iter().map(|event| event.start).min().unwrap_or(0);
+                let to = events.iter().map(|event| event.end).max().unwrap_or(from) + 1;
+                ctx.link().send_future(async move {
+                    let resp = Client::new().events(&EventsRequest { from, to }).await;
+                    TransferMsg::Previewed(events, resp)
+                });
+                true
+            }
+            TransferMsg::Previewed(events, resp) => {
+                self.busy = false;
+                match resp {
+                    Ok(EventsResponse::Success(existing)) => {
+                        let uids: Vec<String> =
+                            existing.into_iter().map(|event| event.uid).collect();
+                        self.preview = Some(Self::fates(events, &uids));
+                    }
+                    Ok(EventsResponse::InvalidRequest) => {
+                        self.failure = Some(String::from(api::REJECTED))
+                    }
+                    Err(e) => toast::api_error(&e),
+                }
+                true
+            }
+            TransferMsg::Import => {
+                let events: Vec<ImportedEvent> = match &self.preview {
+                    Some(preview) if !self.busy => preview
+                        .iter()
+                        .filter(|(_, fate)| *fate == Fate::New)
+                        .map(|(event, _)| ImportedEvent {
+                            uid: event.uid.clone(),
+                            title: event.title.clone(),
+                            start: event.start,
+                            end: event.end,
+                            notes: event.notes.clone(),
+                            // Rules we can't follow would show the wrong times
+                            rrule: event
+                                .rrule
+                                .clone()
+                                .filter(|rule| Rule::parse(rule).is_some()),
+                            exdates: event.exdates.clone(),
+                        })
+                        .collect(),
+                    _ => return false,
+                };
+                let req = ImportEventsRequest { events };
+                ctx.link().send_future(async move {
+                    TransferMsg::Imported(Client::new().import_events(&req).await)
+                });
+                self.busy = true;
+                self.failure = None;
+                true
+            }
+            TransferMsg::Imported(resp) => {
+                self.busy = false;
+                match resp {
+                    Ok(ImportEventsResponse::Success(added)) => {
+                        self.preview = None;
+                        self.message = Some(format!("Imported {} events.", added));
+                        ctx.props().onimported.emit(());
+                    }
+                    Ok(ImportEventsResponse::InvalidRequest) => {
+                        self.failure = Some(String::from(api::REJECTED))
+                    }
+                    Err(e) => toast::api_error(&e),
+                }
+                true
+            }
+            TransferMsg::Discard => {
+                self.preview = None;
+                if let Some(input) = Self::input(&self.fileref) {
+                    input.set_value("");
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let export = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            TransferMsg::Export
+        });
+        let pick = ctx.link().callback(|_: Event| TransferMsg::Pick);
+        let message_text = self.message.clone().unwrap_or_default();
+        let failure_text = self.failure.clone().unwrap_or_default();
+        html! {
+            <div class="transfer">
+                <form class="icsexport">
+                    <h2>{ "Export" }</h2>
+                    <label>{ "From" }
+                        <input type="date" name="from" value={time::date_input(props.from)} ref={self.fromref.clone()} />
+                    </label>
+                    <label>{ "To" }
+                        <input type="date" name="to" value={time::date_input(time::add_days(props.to, -1))} ref={self.toref.clone()} />
+                    </label>
+                    <label class="checkbox">
+                        <input type="checkbox" name="events" checked=true ref={self.eventsref.clone()} />
+                        { "Events" }
+                    </label>
+                    <label class="checkbox">
+                        <input type="checkbox" name="entries" checked=true ref={self.entriesref.clone()} />
+                        { "Tracked time" }
+                    </label>
+                    <button type="submit" onclick={export} disabled={self.busy}>{ "Download .ics" }</button>
+                </form>
+                <form class="icsimport">
+                    <h2>{ "Import" }</h2>
+                    <label>{ "iCalendar file" }
+                        <input type="file" name="file" accept=".ics,text/calendar" onchange={pick} disabled={self.busy} ref={self.fileref.clone()} />
+                    </label>
+                </form>
+                { self.preview(ctx) }
+                <p style={if message_text.is_empty() { "display: none;" } else { "" }}>{ message_text }</p>
+                <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>{ failure_text }</p>
+            </div>
+        }
+    }
+}
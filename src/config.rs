@@ -0,0 +1,170 @@
+use crate::api::Codec;
+use crate::consts;
+use std::fmt;
+use std::str::FromStr;
+use web_sys::Url;
+
+// Deploy settings, read once at startup.
+// Each one comes from a <meta name="nittei-..." content="..."> tag in index.html,
+// falling back to the defaults in consts.rs for the cargo profile.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    // Origin of the API, without a trailing slash
+    pub api_url: String,
+    // Renew the session this many seconds before it expires
+    pub renew_margin: u64,
+    // Allow new accounts to sign up
+    pub registration: bool,
+    // Offer the remember me checkbox
+    pub remember_me: bool,
+    // Wire format for API requests, "ron" or "json"
+    pub codec: Codec,
+    // Where "Contact support" goes, hidden if empty
+    pub support_url: String,
+    // Shortest and longest passwords allowed, in characters
+    pub password_min: usize,
+    pub password_max: usize,
+    // Range API for breached passwords, the hash prefix is appended. Empty turns it off.
+    pub breach_url: String,
+    // Refuse breached passwords instead of only warning about them
+    pub breach_block: bool,
+}
+
+// Why the app can not run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    // A meta tag has a value we can't use
+    BadValue { name: String, value: String },
+    // The API can not be reached
+    Unreachable(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::BadValue { name, value } => {
+                write!(f, "Invalid value \"{}\" for setting {}", value, name)
+            }
+            ConfigError::Unreachable(url) => write!(f, "Can not reach the server at {}", url),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            api_url: same_origin(consts::URL),
+            renew_margin: consts::RENEW_MARGIN,
+            registration: consts::REGISTRATION,
+            remember_me: consts::REMEMBER_ME,
+            codec: consts::CODEC,
+            support_url: String::from(consts::SUPPORT_URL),
+            password_min: consts::PASSWORD_MIN,
+            password_max: consts::PASSWORD_MAX,
+            breach_url: String::from(consts::BREACH_URL),
+            breach_block: consts::BREACH_BLOCK,
+        }
+    }
+}
+
+// The origin of the page if url is empty
+fn same_origin(url: &str) -> String {
+    if !url.is_empty() {
+        return String::from(url.trim_end_matches('/'));
+    }
+    web_sys::window()
+        .and_then(|window| window.location().origin().ok())
+        .unwrap_or_default()
+}
+
+// Content of <meta name="nittei-name">, if there is one and it isn't empty
+fn meta(name: &str) -> Option<String> {
+    let document = web_sys::window()?.document()?;
+    let selector = format!("meta[name=\"nittei-{}\"]", name);
+    let content = document
+        .query_selector(&selector)
+        .ok()??
+        .get_attribute("content")?;
+    let content = content.trim();
+    if content.is_empty() {
+        None
+    } else {
+        Some(String::from(content))
+    }
+}
+
+// Parse a meta tag if it is there
+fn parse_meta<T: FromStr>(name: &str, default: T) -> Result<T, ConfigError> {
+    match meta(name) {
+        Some(value) => value.parse().map_err(|_| ConfigError::BadValue {
+            name: String::from(name),
+            value,
+        }),
+        None => Ok(default),
+    }
+}
+
+impl Config {
+    // Seconds before expiry to renew a session that has lifetime seconds left.
+    // At most half of it, so a margin longer than the session can't renew nonstop.
+    pub fn renew_margin(&self, lifetime: u64) -> u64 {
+        self.renew_margin.min(lifetime / 2)
+    }
+
+    fn load() -> Result<Self, ConfigError> {
+        let default = Self::default();
+
+        let api_url = match meta("api-url") {
+            Some(url) => {
+                // Only absolute http(s) URLs are any good
+                let valid = Url::new(&url)
+                    .map(|parsed| parsed.protocol() == "http:" || parsed.protocol() == "https:")
+                    .unwrap_or(false);
+                if !valid {
+                    return Err(ConfigError::BadValue {
+                        name: String::from("api-url"),
+                        value: url,
+                    });
+                }
+                String::from(url.trim_end_matches('/'))
+            }
+            None => default.api_url,
+        };
+
+        let password_min = parse_meta("password-min", default.password_min)?;
+        let password_max = parse_meta("password-max", default.password_max)?;
+        if password_max < password_min {
+            return Err(ConfigError::BadValue {
+                name: String::from("password-max"),
+                value: password_max.to_string(),
+            });
+        }
+
+        Ok(Self {
+            api_url,
+            renew_margin: parse_meta("renew-margin", default.renew_margin)?,
+            registration: parse_meta("registration", default.registration)?,
+            remember_me: parse_meta("remember-me", default.remember_me)?,
+            codec: parse_meta("codec", default.codec)?,
+            support_url: meta("support-url").unwrap_or(default.support_url),
+            password_min,
+            password_max,
+            breach_url: meta("breach-url").unwrap_or(default.breach_url),
+            breach_block: parse_meta("breach-block", default.breach_block)?,
+        })
+    }
+}
+
+thread_local! {
+    static CONFIG: Result<Config, ConfigError> = Config::load();
+}
+
+// The settings in use. Defaults if they could not be loaded.
+pub fn get() -> Config {
+    CONFIG.with(|config| config.clone().unwrap_or_default())
+}
+
+// Why the settings could not be loaded, if they couldn't
+pub fn error() -> Option<ConfigError> {
+    CONFIG.with(|config| config.clone().err())
+}
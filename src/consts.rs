@@ -1 +1,50 @@
+use crate::api::Codec;
+
+// Defaults for the deploy settings in config.rs, picked by cargo feature.
+// Any of them can be overridden with <meta> tags in index.html.
+
+// Where the API lives. Empty means the same origin as the page.
+#[cfg(not(any(feature = "staging", feature = "prod")))]
 pub const URL: &str = "http://localhost:8000";
+#[cfg(any(feature = "staging", feature = "prod"))]
+pub const URL: &str = "";
+
+// Name of the build profile, shown on the configuration error page
+#[cfg(not(any(feature = "staging", feature = "prod")))]
+pub const PROFILE: &str = "dev";
+#[cfg(all(feature = "staging", not(feature = "prod")))]
+pub const PROFILE: &str = "staging";
+#[cfg(feature = "prod")]
+pub const PROFILE: &str = "prod";
+
+// Renew the session this many seconds before it expires
+pub const RENEW_MARGIN: u64 = 60;
+// How often to renew if the expiry time is unknown, in seconds
+pub const RENEW_FALLBACK: u64 = 4 * 60;
+// Shortest wait before a renewal, in milliseconds
+pub const RENEW_DELAY_MIN: u32 = 5 * 1000;
+// Wait between failed renewals, in milliseconds
+pub const RENEW_BACKOFF_MIN: u32 = 2 * 1000;
+pub const RENEW_BACKOFF_MAX: u32 = 2 * 60 * 1000;
+
+// Feature toggles
+pub const REGISTRATION: bool = true;
+pub const REMEMBER_ME: bool = true;
+
+// Wire format for API requests
+pub const CODEC: Codec = Codec::Ron;
+
+// Where "Contact support" links to, empty hides it
+pub const SUPPORT_URL: &str = "";
+
+// Password length limits, in characters. Long enough for passphrases.
+pub const PASSWORD_MIN: usize = 8;
+pub const PASSWORD_MAX: usize = 128;
+
+// Where to look up breached passwords, and whether to refuse them or only warn.
+// Off unless the operator sets one, e.g. "https://api.pwnedpasswords.com/range/".
+pub const BREACH_URL: &str = "";
+pub const BREACH_BLOCK: bool = false;
+
+// How long to hold a form back after a lockout that didn't say how long, in seconds
+pub const LOCKOUT_FALLBACK: u32 = 30;
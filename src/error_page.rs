@@ -0,0 +1,55 @@
+use crate::config::{self, ConfigError};
+use crate::consts;
+use web_sys::MouseEvent;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct ErrorPageProps {
+    pub error: ConfigError,
+    pub onretry: Callback<()>,
+}
+
+// Shown instead of the whole app when it can't talk to the server
+pub struct ErrorPage {}
+
+impl Component for ErrorPage {
+    type Message = ();
+    type Properties = ErrorPageProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {}
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, _msg: Self::Message) -> bool {
+        false
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let error = &ctx.props().error;
+        let hint = match error {
+            ConfigError::BadValue { .. } => "Check the nittei meta tags in index.html.",
+            ConfigError::Unreachable(_) => "The server may be down, or you may be offline.",
+        };
+        // Retrying can't fix a bad setting, only a bad connection
+        let retry = match error {
+            ConfigError::Unreachable(_) => {
+                let onretry = ctx.props().onretry.clone();
+                let cb = Callback::from(move |_: MouseEvent| onretry.emit(()));
+                html! { <button onclick={cb}>{ "Try again" }</button> }
+            }
+            ConfigError::BadValue { .. } => html! {},
+        };
+
+        html! {
+            <main id="errorpage" class="content">
+                <h1>{ "Nittei can't start" }</h1>
+                <p class="failuretext">{ error.to_string() }</p>
+                <p>{ hint }</p>
+                { retry }
+                <p class="errordetails">
+                    { format!("Profile: {}, API: {}", consts::PROFILE, config::get().api_url) }
+                </p>
+            </main>
+        }
+    }
+}
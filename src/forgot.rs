@@ -0,0 +1,152 @@
+use crate::api::{self, ApiResult, Client, ForgotRequest, ForgotResponse, Throttled};
+use crate::app::AppRoute;
+use crate::lockout::{self, Countdown};
+use crate::nav::{Anchor, Nav};
+use crate::toast;
+use email_address_parser::EmailAddress;
+use web_sys::{HtmlInputElement, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum ForgotMsg {
+    Submit,
+    Recieved(ApiResult<Throttled<ForgotResponse>>),
+    // A lockout ran out
+    Unlocked,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ForgotState {
+    New,
+    Sending,
+    Sent,
+    BadEmail,
+    Rejected,
+}
+
+impl From<ForgotState> for String {
+    fn from(state: ForgotState) -> String {
+        match state {
+            ForgotState::New | ForgotState::Sending | ForgotState::Sent => String::new(),
+            ForgotState::BadEmail => String::from("Invalid email address!"),
+            ForgotState::Rejected => String::from(api::REJECTED),
+        }
+    }
+}
+
+// Page to request a password reset email
+pub struct Forgot {
+    state: ForgotState,
+    // Locked out until then, in milliseconds since the epoch
+    locked_until: Option<f64>,
+    emailref: NodeRef,
+}
+
+impl Component for Forgot {
+    type Message = ForgotMsg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            state: ForgotState::New,
+            locked_until: None,
+            emailref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ForgotMsg::Submit => {
+                if self.state == ForgotState::Sending || self.locked_until.is_some() {
+                    return false;
+                }
+                let email = match self.emailref.cast::<HtmlInputElement>() {
+                    Some(emailbox) => emailbox.value(),
+                    None => return false,
+                };
+                if email.is_empty() || !EmailAddress::is_valid(&email, None) {
+                    self.state = ForgotState::BadEmail;
+                    return true;
+                }
+
+                let req = ForgotRequest { email };
+                ctx.link().send_future(async move {
+                    ForgotMsg::Recieved(Client::anonymous().forgot(&req).await)
+                });
+                self.state = ForgotState::Sending;
+                true
+            }
+            ForgotMsg::Recieved(resp) => {
+                self.state = match resp {
+                    Ok(resp) => match resp.body {
+                        ForgotResponse::Success => ForgotState::Sent,
+                        ForgotResponse::InvalidEmail => ForgotState::BadEmail,
+                        ForgotResponse::Lockout => {
+                            self.locked_until = Some(lockout::until(resp.retry_after));
+                            ForgotState::New
+                        }
+                        ForgotResponse::InvalidRequest => ForgotState::Rejected,
+                    },
+                    Err(e) => {
+                        match lockout::from_error(&e) {
+                            Some(until) => self.locked_until = Some(until),
+                            None => toast::api_error(&e),
+                        }
+                        ForgotState::New
+                    }
+                };
+                true
+            }
+            ForgotMsg::Unlocked => {
+                self.locked_until = None;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            ForgotMsg::Submit
+        });
+        let failure_text: String = self.state.into();
+        let countdown = match self.locked_until {
+            Some(until) => {
+                let onelapsed = ctx.link().callback(|_| ForgotMsg::Unlocked);
+                html! { <Countdown until={until} onelapsed={onelapsed} /> }
+            }
+            None => html! {},
+        };
+
+        if self.state == ForgotState::Sent {
+            return html! {
+                <>
+                    <Nav route={AppRoute::Forgot} />
+                    <main id="forgot" class="content">
+                        <p>{ "If that address has an account, a reset link is on its way." }</p>
+                        <p><Anchor route={AppRoute::Login}>{ "Back to login" }</Anchor></p>
+                    </main>
+                </>
+            };
+        }
+
+        html! {
+            <>
+                <Nav route={AppRoute::Forgot} />
+                <main id="forgot" class="content">
+                    <form id="forgotform">
+                        <label for="forgotemail">{ "Email" }</label>
+                        <input type="text" id="forgotemail" name="email" ref={self.emailref.clone()} />
+                        <button id="forgotsubmit" type="submit" onclick={cb} disabled={self.state == ForgotState::Sending || self.locked_until.is_some()}>
+                            { "Send reset link" }
+                        </button>
+                    </form>
+                    { countdown }
+                    <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>
+                        { failure_text }
+                    </p>
+                </main>
+            </>
+        }
+    }
+}
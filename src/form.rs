@@ -0,0 +1,420 @@
+use gloo::timers::future::TimeoutFuture;
+use std::cell::Cell;
+use std::future::Future;
+use std::rc::Rc;
+use web_sys::{FocusEvent, HtmlInputElement, KeyboardEvent, MouseEvent};
+use yew::html::Scope;
+use yew::prelude::*;
+
+// Rules fields are checked against
+mod validator;
+pub use validator::*;
+
+// Something the user did to a field, components wrap these in their own message
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FieldEvent<K> {
+    // A key was typed
+    Input(K),
+    // The user left the field
+    Blur(K),
+}
+
+// Wait this long after the last keystroke before asking the server about a value,
+// in milliseconds
+const DEBOUNCE_MS: u32 = 400;
+
+// What the server thinks of a field's value
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Verdict {
+    // What is wrong with it
+    pub error: Option<String>,
+    // Something good to say about it, i.e. "Available!"
+    pub note: Option<String>,
+    // Something the user should know, but that doesn't stop the form
+    pub warning: Option<String>,
+    // Values the user could pick instead
+    pub suggestions: Vec<String>,
+}
+
+impl Verdict {
+    pub fn ok(note: &str) -> Self {
+        Self {
+            note: Some(String::from(note)),
+            ..Self::default()
+        }
+    }
+
+    pub fn warning(warning: &str) -> Self {
+        Self {
+            warning: Some(String::from(warning)),
+            ..Self::default()
+        }
+    }
+
+    pub fn error(error: &str) -> Self {
+        Self {
+            error: Some(String::from(error)),
+            ..Self::default()
+        }
+    }
+
+    // The server couldn't be asked, it will check again on submit anyway
+    pub fn unknown() -> Self {
+        Self::default()
+    }
+}
+
+// A verdict and the value it is about, kept until the value changes
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Remote {
+    value: String,
+    verdict: Verdict,
+}
+
+// One input of a form
+struct Field<K> {
+    key: K,
+    name: &'static str,
+    label: &'static str,
+    kind: &'static str,
+    validators: Vec<Validator<K>>,
+    node: NodeRef,
+    errors: Vec<String>,
+    remote: Option<Remote>,
+    // The value a server side check is running for
+    checking: Option<String>,
+    // Bumped by every server side check, so older debounced checks can give up
+    generation: Rc<Cell<u32>>,
+    // Left by the user at least once
+    touched: bool,
+    // Typed in at least once
+    dirty: bool,
+}
+
+// State of a form: its fields, their errors and whether it is being sent.
+// Components own one, feed it every FieldEvent and re-render when it says so.
+// K names the fields, usually a small Copy enum.
+pub struct Form<K> {
+    fields: Vec<Field<K>>,
+    submitting: bool,
+}
+
+impl<K: Copy + PartialEq + 'static> Default for Form<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Copy + PartialEq + 'static> Form<K> {
+    pub fn new() -> Self {
+        Self {
+            fields: Vec::new(),
+            submitting: false,
+        }
+    }
+
+    // Add a text box
+    pub fn text(
+        self,
+        key: K,
+        name: &'static str,
+        label: &'static str,
+        validators: Vec<Validator<K>>,
+    ) -> Self {
+        self.add(key, name, label, "text", validators)
+    }
+
+    // Add a password box
+    pub fn password(
+        self,
+        key: K,
+        name: &'static str,
+        label: &'static str,
+        validators: Vec<Validator<K>>,
+    ) -> Self {
+        self.add(key, name, label, "password", validators)
+    }
+
+    fn add(
+        mut self,
+        key: K,
+        name: &'static str,
+        label: &'static str,
+        kind: &'static str,
+        validators: Vec<Validator<K>>,
+    ) -> Self {
+        self.fields.push(Field {
+            key,
+            name,
+            label,
+            kind,
+            validators,
+            node: NodeRef::default(),
+            errors: Vec::new(),
+            remote: None,
+            checking: None,
+            generation: Rc::new(Cell::new(0)),
+            touched: false,
+            dirty: false,
+        });
+        self
+    }
+
+    fn get(&self, key: K) -> &Field<K> {
+        self.fields
+            .iter()
+            .find(|field| field.key == key)
+            .expect("No such form field")
+    }
+
+    fn get_mut(&mut self, key: K) -> &mut Field<K> {
+        self.fields
+            .iter_mut()
+            .find(|field| field.key == key)
+            .expect("No such form field")
+    }
+
+    // What is in the box right now
+    pub fn value(&self, key: K) -> String {
+        self.get(key)
+            .node
+            .cast::<HtmlInputElement>()
+            .map(|input| input.value())
+            .unwrap_or_default()
+    }
+
+    // Re-run every validator.
+    // Fields the user hasn't gotten to yet keep quiet.
+    // Returns true if any error appeared or went away.
+    fn refresh(&mut self) -> bool {
+        let values: Vec<(K, String)> = self
+            .fields
+            .iter()
+            .map(|field| (field.key, self.value(field.key)))
+            .collect();
+        let other = |key: K| {
+            values
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default()
+        };
+
+        let mut changed = false;
+        for (field, (_, value)) in self.fields.iter_mut().zip(values.iter()) {
+            if field.remote.as_ref().map(|remote| &remote.value) != Some(value) {
+                changed |= field.remote.take().is_some();
+            }
+            if field.checking.as_ref() != Some(value) {
+                // Whatever the server says now is about an old value
+                changed |= field.checking.take().is_some();
+            }
+
+            let errors: Vec<String> = if field.touched || field.dirty {
+                field
+                    .validators
+                    .iter()
+                    .filter_map(|validator| validator.check(value, &other))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            changed |= errors != field.errors;
+            field.errors = errors;
+        }
+        changed
+    }
+
+    // Handle a field event, returns true if the form should be re-rendered
+    pub fn event(&mut self, event: FieldEvent<K>) -> bool {
+        match event {
+            FieldEvent::Input(key) => self.get_mut(key).dirty = true,
+            FieldEvent::Blur(key) => self.get_mut(key).touched = true,
+        }
+        self.refresh()
+    }
+
+    // Check every field before submitting, returns true if the form can be sent
+    pub fn validate(&mut self) -> bool {
+        for field in self.fields.iter_mut() {
+            field.touched = true;
+        }
+        self.refresh();
+        self.is_valid()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.fields.iter().all(|field| {
+            let remote_error = field
+                .remote
+                .as_ref()
+                .is_some_and(|remote| remote.verdict.error.is_some());
+            field.errors.is_empty() && !remote_error && field.checking.is_none()
+        })
+    }
+
+    // Everything wrong with a field
+    pub fn errors(&self, key: K) -> Vec<String> {
+        let field = self.get(key);
+        let mut errors = field.errors.clone();
+        if let Some(error) = field
+            .remote
+            .as_ref()
+            .and_then(|remote| remote.verdict.error.clone())
+        {
+            errors.push(error);
+        }
+        errors
+    }
+
+    // Show an error the server found, until the user changes the value
+    pub fn set_error(&mut self, key: K, error: &str) {
+        let value = self.value(key);
+        self.get_mut(key).remote = Some(Remote {
+            value,
+            verdict: Verdict::error(error),
+        });
+    }
+
+    // Ask the server about a field's value, once the user stops typing.
+    // check returns the verdict for the value; the answer comes back as
+    // msg(key, value, verdict) and should be handed to remote_result.
+    // Skipped if the value is empty or already wrong.
+    pub fn check_remote<C, F, Fut>(
+        &mut self,
+        link: &Scope<C>,
+        key: K,
+        check: F,
+        msg: fn(K, String, Verdict) -> C::Message,
+    ) where
+        C: Component,
+        F: FnOnce(String) -> Fut + 'static,
+        Fut: Future<Output = Verdict> + 'static,
+    {
+        let value = self.value(key);
+        let field = self.get(key);
+        let known = field.remote.as_ref().map(|remote| &remote.value) == Some(&value);
+        if value.is_empty()
+            || !field.errors.is_empty()
+            || known
+            || field.checking.as_ref() == Some(&value)
+        {
+            return;
+        }
+
+        let field = self.get_mut(key);
+        field.checking = Some(value.clone());
+        let generation = field.generation.clone();
+        generation.set(generation.get().wrapping_add(1));
+        let started = generation.get();
+        link.send_future_batch(async move {
+            TimeoutFuture::new(DEBOUNCE_MS).await;
+            if generation.get() != started {
+                // The user kept typing, a newer check took over
+                return Vec::new();
+            }
+            let verdict = check(value.clone()).await;
+            vec![msg(key, value, verdict)]
+        });
+    }
+
+    // Answer of a check_remote, returns true if the form should be re-rendered
+    pub fn remote_result(&mut self, key: K, value: String, verdict: Verdict) -> bool {
+        let field = self.get_mut(key);
+        if field.checking.as_ref() != Some(&value) {
+            // The value changed since, a newer check is on its way
+            return false;
+        }
+        field.checking = None;
+        field.remote = Some(Remote { value, verdict });
+        true
+    }
+
+    pub fn submitting(&self) -> bool {
+        self.submitting
+    }
+
+    pub fn set_submitting(&mut self, submitting: bool) {
+        self.submitting = submitting;
+    }
+
+    // Submit buttons are disabled while this is false
+    pub fn can_submit(&self) -> bool {
+        !self.submitting && self.fields.iter().all(|field| field.checking.is_none())
+    }
+
+    // Label, input box and errors for a field.
+    // msg turns the field's events into the component's messages.
+    pub fn view_field<C: Component>(
+        &self,
+        link: &Scope<C>,
+        key: K,
+        id: &'static str,
+        msg: fn(FieldEvent<K>) -> C::Message,
+    ) -> Html {
+        let field = self.get(key);
+        let oninput = link.callback(move |_: KeyboardEvent| msg(FieldEvent::Input(key)));
+        let onblur = link.callback(move |_: FocusEvent| msg(FieldEvent::Blur(key)));
+        let errors = self.errors(key);
+        let class = if errors.is_empty() { "" } else { "invalid" };
+        html! {
+            <>
+                <label for={id}>{ field.label }</label>
+                <input type={field.kind} id={id} name={field.name} class={class}
+                    ref={field.node.clone()} onkeyup={oninput} onblur={onblur} />
+                { for errors.into_iter().map(|error| html! { <p class="failuretext">{ error }</p> }) }
+                { self.remote_view(link, key, msg) }
+            </>
+        }
+    }
+
+    // The server's note and suggestions for a field, if they are about what is in the box
+    fn remote_view<C: Component>(
+        &self,
+        link: &Scope<C>,
+        key: K,
+        msg: fn(FieldEvent<K>) -> C::Message,
+    ) -> Html {
+        let field = self.get(key);
+        if field.checking.is_some() {
+            return html! { <p class="fieldnote">{ "Checking..." }</p> };
+        }
+        let value = self.value(key);
+        let verdict = match &field.remote {
+            Some(remote) if remote.value == value => &remote.verdict,
+            _ => return html! {},
+        };
+
+        let note = match &verdict.note {
+            Some(note) => html! { <p class="fieldnote">{ note.clone() }</p> },
+            None => html! {},
+        };
+        let warning = match &verdict.warning {
+            Some(warning) => html! { <p class="fieldwarning">{ warning.clone() }</p> },
+            None => html! {},
+        };
+        let suggestion = |suggestion: &String| {
+            let node = field.node.clone();
+            let text = suggestion.clone();
+            let cb = link.callback(move |_: MouseEvent| {
+                // Fill the box in, as if the user typed it
+                if let Some(input) = node.cast::<HtmlInputElement>() {
+                    input.set_value(&text);
+                }
+                msg(FieldEvent::Input(key))
+            });
+            html! { <button type="button" class="suggestion" onclick={cb}>{ suggestion.clone() }</button> }
+        };
+        let suggestions = if verdict.suggestions.is_empty() {
+            html! {}
+        } else {
+            html! {
+                <p class="suggestions">
+                    { "Try: " }
+                    { for verdict.suggestions.iter().map(suggestion) }
+                </p>
+            }
+        };
+        html! { <>{ note }{ warning }{ suggestions }</> }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::util::{self, UsernameProblem};
+use email_address_parser::EmailAddress;
+
+// A rule a form field has to follow
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Validator<K> {
+    // Can't be left empty
+    Required,
+    // At least this many characters
+    MinLength(usize),
+    // No more than this many characters
+    MaxLength(usize),
+    // A valid email address
+    Email,
+    // Follows the username rules in util
+    Username,
+    // Long and strong enough for the password rules in util
+    Password,
+    // Has to be the same as another field, with the message to show if not
+    MustMatch(K, &'static str),
+}
+
+impl<K: Copy> Validator<K> {
+    // What is wrong with value, if anything.
+    // other reads the value of another field of the same form.
+    pub fn check(&self, value: &str, other: impl Fn(K) -> String) -> Option<String> {
+        match *self {
+            Validator::Required if value.is_empty() => Some(String::from("Missing field value!")),
+            Validator::MinLength(min) if value.chars().count() < min => {
+                Some(format!("Must be at least {} characters.", min))
+            }
+            Validator::MaxLength(max) if value.chars().count() > max => {
+                Some(format!("Must be no longer than {} characters.", max))
+            }
+            Validator::Email if !value.is_empty() && !EmailAddress::is_valid(value, None) => {
+                Some(String::from("Invalid email address!"))
+            }
+            Validator::Username => match util::check_username(value) {
+                Some(UsernameProblem::Empty) => Some(String::from("Missing field value!")),
+                Some(UsernameProblem::Long) => Some(format!(
+                    "Username must be no longer than {} characters.",
+                    util::USERNAME_MAX
+                )),
+                Some(UsernameProblem::Character(c)) => Some(format!(
+                    "Usernames can't contain '{}', only letters, digits, '_' and '-'.",
+                    c
+                )),
+                None => None,
+            },
+            Validator::Password => util::check_password(value).map(|problem| problem.message()),
+            Validator::MustMatch(key, message) if other(key) != value => {
+                Some(String::from(message))
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::app::{self, AppRoute};
+use crate::nav::Nav;
+use crate::session::{SessionContext, SessionStatus};
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GuardMsg {
+    Session(SessionContext),
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct GuardProps {
+    pub route: AppRoute,
+}
+
+// Shows a route if the session allows it, otherwise redirects:
+// logged out users go to login (coming back afterwards), logged in users skip login.
+pub struct Guard {
+    session: SessionContext,
+    _session_handle: ContextHandle<SessionContext>,
+}
+
+impl Guard {
+    // Where to send the user instead of showing the route, if anywhere
+    fn redirect(&self, route: &AppRoute) -> Option<AppRoute> {
+        match self.session.status {
+            SessionStatus::Anonymous | SessionStatus::Expired if route.needs_auth() => {
+                Some(route.login_return())
+            }
+            SessionStatus::Authenticated if route.anonymous_only() => Some(route.after_login()),
+            _ => None,
+        }
+    }
+
+    fn follow(&self, ctx: &Context<Self>) {
+        if let Some(route) = self.redirect(&ctx.props().route) {
+            yew_router::replace_route(route);
+        }
+    }
+}
+
+impl Component for Guard {
+    type Message = GuardMsg;
+    type Properties = GuardProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (session, handle) = ctx
+            .link()
+            .context::<SessionContext>(ctx.link().callback(GuardMsg::Session))
+            .expect("No session context");
+        let guard = Self {
+            session,
+            _session_handle: handle,
+        };
+        guard.follow(ctx);
+        guard
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            GuardMsg::Session(session) => {
+                // Only re-render the page if it would change what is shown
+                let before = (self.redirect(&ctx.props().route), self.session.status);
+                self.session = session;
+                self.follow(ctx);
+                before != (self.redirect(&ctx.props().route), self.session.status)
+            }
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        self.follow(ctx);
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let route = &ctx.props().route;
+        if self.redirect(route).is_some() {
+            return html! {};
+        }
+
+        // Don't show private pages until we know who the user is
+        if route.needs_auth() && self.session.status == SessionStatus::Authenticating {
+            return html! {
+                <>
+                    <Nav route={route.clone()} />
+                    <main class="content">
+                        <p>{ "Checking session..." }</p>
+                    </main>
+                </>
+            };
+        }
+
+        app::page(route)
+    }
+}
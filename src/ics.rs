@@ -0,0 +1,1022 @@
+use crate::api::{CalendarEvent, Entry};
+use crate::recur::{self, Rule};
+use crate::time::{self, Fields};
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::fmt;
+
+// Reading and writing RFC 5545 files.
+// Written files put every time in the user's zone, described by a VTIMEZONE.
+// Read files may use any zone the browser knows, or one described in the file.
+
+// Longest a line may be before it is folded, in bytes
+const LINE_MAX: usize = 75;
+
+const PRODID: &str = "-//nittei//calendar//EN";
+
+// How far ahead the time zone is described for events that repeat forever
+const REPEAT_YEARS: u64 = 5;
+
+// An event read from a file
+#[derive(Clone, PartialEq, Debug)]
+pub struct IcsEvent {
+    pub uid: String,
+    pub title: String,
+    pub start: u64,
+    pub end: u64,
+    pub notes: String,
+    // RRULE as written in the file, if the event repeats
+    pub rrule: Option<String>,
+    // Occurrences taken out of the series
+    pub exdates: Vec<u64>,
+}
+
+// Why a file could not be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IcsError {
+    // No BEGIN:VCALENDAR
+    NotCalendar,
+    // A component was never closed, or closed out of order
+    Unbalanced(String),
+    // A line without a colon, by line number after unfolding
+    BadLine(usize),
+    BadTime(String),
+}
+
+impl fmt::Display for IcsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IcsError::NotCalendar => write!(f, "This is not an iCalendar file"),
+            IcsError::Unbalanced(name) => write!(f, "The {} component is not closed", name),
+            IcsError::BadLine(line) => write!(f, "Line {} can not be read", line),
+            IcsError::BadTime(value) => write!(f, "Invalid date or time \"{}\"", value),
+        }
+    }
+}
+
+// "NAME;PARAM=value:VALUE" with the name upper cased
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        // The name and parameters end at the first colon outside quotes
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(i, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(i),
+            _ => None,
+        })?;
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = split_unquoted(head, ';').into_iter();
+        let name = parts.next()?.to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((
+                    key.to_ascii_uppercase(),
+                    value.trim_matches('"').to_string(),
+                ))
+            })
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+}
+
+// Split on sep where it is not inside double quotes
+fn split_unquoted(text: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == sep && !quoted {
+            parts.push(&text[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+// Join folded lines back together
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines.retain(|line| !line.trim().is_empty());
+    lines
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => (),
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => (),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Break a line into CRLF separated pieces of at most LINE_MAX bytes,
+// without splitting a character
+fn fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > LINE_MAX {
+            out.push_str("\r\n ");
+            // The space counts towards the new line
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+// Date and time fields as written in a file, "20211004T090000" or "20211004".
+// Dates alone read as midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    year: i32,
+    month: i32,
+    day: i32,
+    hour: i32,
+    minute: i32,
+    second: i32,
+    // Ends in Z
+    utc: bool,
+}
+
+impl Stamp {
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (value, utc) = match value.strip_suffix('Z') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+        let (date, clock) = match value.split_once('T') {
+            Some((date, clock)) => (date, Some(clock)),
+            None => (value, None),
+        };
+        let number = |text: &str, from: usize, to: usize| -> Option<i32> {
+            text.get(from..to)?.parse().ok()
+        };
+        if date.len() != 8 || clock.is_some_and(|clock| clock.len() != 6) {
+            return None;
+        }
+        let clock = clock.unwrap_or("000000");
+        Some(Self {
+            year: number(date, 0, 4)?,
+            month: number(date, 4, 6)?,
+            day: number(date, 6, 8)?,
+            hour: number(clock, 0, 2)?,
+            minute: number(clock, 2, 4)?,
+            second: number(clock, 4, 6)?,
+            utc,
+        })
+    }
+
+    // The wall clock time as if it were UTC, for comparing against VTIMEZONE onsets
+    fn wall(&self) -> i64 {
+        time::from_fields_utc(
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+        ) as i64
+    }
+}
+
+fn write_stamp(f: &Fields) -> String {
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}",
+        f.year, f.month, f.day, f.hour, f.minute, f.second
+    )
+}
+
+// A timestamp in UTC, "20211004T070000Z"
+pub fn utc_stamp(ts: u64) -> String {
+    write_stamp(&time::fields_utc(ts)) + "Z"
+}
+
+// Timestamp of a value without a TZID, like the UNTIL of an RRULE.
+// Floating times and dates are on the user's wall clock.
+pub fn parse_time(value: &str) -> Option<u64> {
+    let s = Stamp::parse(value)?;
+    let read = if s.utc {
+        time::from_fields_utc
+    } else {
+        time::from_fields
+    };
+    Some(read(s.year, s.month, s.day, s.hour, s.minute, s.second))
+}
+
+// "+0130" style offsets, in seconds
+fn parse_offset(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let sign = match value.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let hours: i64 = value.get(1..3)?.parse().ok()?;
+    let minutes: i64 = value.get(3..5)?.parse().ok()?;
+    let seconds: i64 = value.get(5..7).and_then(|s| s.parse().ok()).unwrap_or(0);
+    Some(sign * (hours * 3600 + minutes * 60 + seconds))
+}
+
+fn write_offset(offset: i64) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!("{}{:02}{:02}", sign, offset / 3600, offset % 3600 / 60)
+}
+
+// "P1DT2H30M" style durations, in seconds.
+// Negative ones and ones too long to count come out as None.
+fn parse_duration(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let value = value.strip_prefix('+').unwrap_or(value).strip_prefix('P')?;
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => (),
+            _ => {
+                let n: u64 = number.parse().ok()?;
+                number.clear();
+                let unit = match c {
+                    'W' => 7 * time::DAY,
+                    'D' => time::DAY,
+                    'H' => time::HOUR,
+                    'M' => time::MINUTE,
+                    'S' => 1,
+                    _ => return None,
+                };
+                total = total.checked_add(n.checked_mul(unit)?)?;
+            }
+        }
+    }
+    Some(total)
+}
+
+// One STANDARD or DAYLIGHT part of a VTIMEZONE
+#[derive(Clone, Debug)]
+struct Observance {
+    start: Stamp,
+    from: i64,
+    to: i64,
+    // Yearly onset from an RRULE: month, which week (negative from the end) and weekday
+    yearly: Option<(i32, i32, u32)>,
+}
+
+impl Observance {
+    fn parse(props: &[Property]) -> Option<Self> {
+        let get = |name: &str| props.iter().find(|prop| prop.name == name);
+        let yearly = get("RRULE").and_then(|rule| {
+            let parts = recur::rule_parts(&rule.value);
+            if parts.get("FREQ").map(String::as_str) != Some("YEARLY") {
+                return None;
+            }
+            let month = parts.get("BYMONTH")?.parse().ok()?;
+            let (nth, weekday) = recur::parse_byday(parts.get("BYDAY")?)?;
+            Some((month, nth.unwrap_or(1), weekday))
+        });
+        Some(Self {
+            start: Stamp::parse(&get("DTSTART")?.value)?,
+            from: parse_offset(&get("TZOFFSETFROM")?.value)?,
+            to: parse_offset(&get("TZOFFSETTO")?.value)?,
+            yearly,
+        })
+    }
+
+    // When it last began at or before wall (in its own offset), as a UTC timestamp
+    fn onset_before(&self, wall: i64) -> Option<i64> {
+        let first = self.start.wall() - self.from;
+        let (month, nth, weekday) = match self.yearly {
+            Some(yearly) => yearly,
+            None => return Some(first).filter(|first| *first <= wall - self.to),
+        };
+        let year = time::fields_utc(wall.max(0) as u64).year;
+        (year - 1..=year)
+            .rev()
+            .map(|year| {
+                let day = recur::nth_weekday(year, month, nth, weekday);
+                let s = &self.start;
+                time::from_fields_utc(year, month, day, s.hour, s.minute, s.second) as i64
+                    - self.from
+            })
+            .find(|onset| *onset <= wall - self.to && *onset >= first)
+    }
+}
+
+// A time zone described in the file
+#[derive(Clone, Debug, Default)]
+struct Zone {
+    observances: Vec<Observance>,
+}
+
+impl Zone {
+    // Timestamp of a wall clock time in this zone
+    fn resolve(&self, stamp: &Stamp) -> Option<u64> {
+        let wall = stamp.wall();
+        let observance = self
+            .observances
+            .iter()
+            .filter_map(|observance| Some((observance.onset_before(wall)?, observance)))
+            .max_by_key(|(onset, _)| *onset)
+            .map(|(_, observance)| observance)
+            .or_else(|| self.observances.first())?;
+        Some((wall - observance.to).max(0) as u64)
+    }
+}
+
+// Timestamp of a DTSTART, DTEND or EXDATE value
+fn resolve(prop: &Property, value: &str, zones: &HashMap<String, Zone>) -> Result<u64, IcsError> {
+    let stamp = Stamp::parse(value).ok_or_else(|| IcsError::BadTime(value.to_string()))?;
+    let s = &stamp;
+    if let Some(tzid) = prop.param("TZID").filter(|_| !s.utc) {
+        // Some writers put a slash in front of IANA names
+        let name = tzid.trim_start_matches('/');
+        if let Some(ts) =
+            time::from_fields_in(name, s.year, s.month, s.day, s.hour, s.minute, s.second)
+        {
+            return Ok(ts);
+        }
+        if let Some(ts) = zones.get(tzid).and_then(|zone| zone.resolve(s)) {
+            return Ok(ts);
+        }
+    }
+    parse_time(value).ok_or_else(|| IcsError::BadTime(value.to_string()))
+}
+
+// Read the events out of a file
+pub fn parse(text: &str) -> Result<Vec<IcsEvent>, IcsError> {
+    let mut stack: Vec<String> = Vec::new();
+    let mut seen_calendar = false;
+    // Properties of the component being read, by depth
+    let mut props: Vec<Vec<Property>> = Vec::new();
+    let mut zones: HashMap<String, Zone> = HashMap::new();
+    let mut zone = (String::new(), Zone::default());
+    let mut events: Vec<Vec<Property>> = Vec::new();
+
+    for (number, line) in unfold(text).iter().enumerate() {
+        let prop = Property::parse(line).ok_or(IcsError::BadLine(number + 1))?;
+        match prop.name.as_str() {
+            "BEGIN" => {
+                let name = prop.value.trim().to_ascii_uppercase();
+                if stack.is_empty() && name != "VCALENDAR" {
+                    return Err(IcsError::NotCalendar);
+                }
+                seen_calendar = true;
+                stack.push(name);
+                props.push(Vec::new());
+            }
+            "END" => {
+                let name = prop.value.trim().to_ascii_uppercase();
+                if stack.last() != Some(&name) {
+                    return Err(IcsError::Unbalanced(stack.pop().unwrap_or(name)));
+                }
+                stack.pop();
+                let done = props.pop().unwrap_or_default();
+                match (name.as_str(), stack.last().map(String::as_str)) {
+                    ("VEVENT", _) => events.push(done),
+                    ("STANDARD" | "DAYLIGHT", Some("VTIMEZONE")) => {
+                        if let Some(observance) = Observance::parse(&done) {
+                            zone.1.observances.push(observance);
+                        }
+                    }
+                    ("VTIMEZONE", _) => {
+                        if let Some(tzid) = done.iter().find(|prop| prop.name == "TZID") {
+                            zone.0 = tzid.value.clone();
+                        }
+                        let (tzid, found) = std::mem::take(&mut zone);
+                        zones.insert(tzid, found);
+                    }
+                    _ => (),
+                }
+            }
+            _ => match props.last_mut() {
+                Some(current) => current.push(prop),
+                None => return Err(IcsError::NotCalendar),
+            },
+        }
+    }
+    if !seen_calendar {
+        return Err(IcsError::NotCalendar);
+    }
+    if let Some(open) = stack.pop() {
+        return Err(IcsError::Unbalanced(open));
+    }
+
+    let mut read: Vec<(IcsEvent, Option<u64>)> = events
+        .iter()
+        .filter_map(|props| event(props, &zones).transpose())
+        .collect::<Result<_, _>>()?;
+    // A changed occurrence is its own VEVENT with the UID of the series and a RECURRENCE-ID.
+    // It comes in as an event of its own, taken out of the series.
+    let overrides: Vec<(String, u64)> = read
+        .iter()
+        .filter_map(|(event, recurrence)| Some((event.uid.clone(), (*recurrence)?)))
+        .collect();
+    for (event, recurrence) in read.iter_mut() {
+        match recurrence {
+            Some(at) => event.uid = format!("{}/{}", event.uid, at),
+            None => event.exdates.extend(
+                overrides
+                    .iter()
+                    .filter(|(uid, _)| *uid == event.uid)
+                    .map(|(_, at)| *at),
+            ),
+        }
+    }
+    Ok(read.into_iter().map(|(event, _)| event).collect())
+}
+
+// An event from the properties of a VEVENT with its RECURRENCE-ID.
+// None if it has no start, or ends before it starts.
+fn event(
+    props: &[Property],
+    zones: &HashMap<String, Zone>,
+) -> Result<Option<(IcsEvent, Option<u64>)>, IcsError> {
+    let get = |name: &str| props.iter().find(|prop| prop.name == name);
+    let start_prop = match get("DTSTART") {
+        Some(prop) => prop,
+        None => return Ok(None),
+    };
+    let start = resolve(start_prop, &start_prop.value, zones)?;
+    let date_only = start_prop.param("VALUE") == Some("DATE") || start_prop.value.trim().len() == 8;
+    let end = match (get("DTEND"), get("DURATION")) {
+        (Some(end), _) => resolve(end, &end.value, zones)?,
+        // Only this event is left out, the rest of the file is fine
+        (None, Some(duration)) if duration.value.trim().starts_with('-') => return Ok(None),
+        // Ends past year 9999 could never be written back
+        (None, Some(duration)) => parse_duration(&duration.value)
+            .and_then(|length| start.checked_add(length))
+            .filter(|end| *end <= time::from_fields_utc(9999, 12, 31, 23, 59, 59))
+            .ok_or_else(|| IcsError::BadTime(duration.value.clone()))?,
+        // All day events without an end last the day
+        (None, None) if date_only => time::add_days(start, 1),
+        (None, None) => start,
+    };
+    let text = |name: &str| {
+        get(name)
+            .map(|prop| unescape(&prop.value))
+            .unwrap_or_default()
+    };
+    // Events without a UID get one made up from everything about them,
+    // so importing the same file again finds them but no two different ones clash
+    let uid = match get("UID") {
+        Some(uid) => unescape(&uid.value).trim().to_string(),
+        None => {
+            let rrule = get("RRULE")
+                .map(|rule| rule.value.trim())
+                .unwrap_or_default();
+            let content = [
+                start.to_string(),
+                end.to_string(),
+                text("SUMMARY"),
+                text("DESCRIPTION"),
+                rrule.to_string(),
+            ]
+            .join("\0");
+            format!("{}@import", Sha1::from(content).digest())
+        }
+    };
+    // EXDATE may come once with a list, or many times
+    let mut exdates = Vec::new();
+    for prop in props.iter().filter(|prop| prop.name == "EXDATE") {
+        for value in prop.value.split(',') {
+            exdates.push(resolve(prop, value, zones)?);
+        }
+    }
+    let recurrence = match get("RECURRENCE-ID") {
+        Some(prop) => Some(resolve(prop, &prop.value, zones)?),
+        None => None,
+    };
+    let event = IcsEvent {
+        uid,
+        title: text("SUMMARY"),
+        start,
+        end: end.max(start),
+        notes: text("DESCRIPTION"),
+        rrule: get("RRULE").map(|rule| rule.value.trim().to_string()),
+        exdates,
+    };
+    Ok(Some((event, recurrence)))
+}
+
+// Builds a file line by line
+struct Writer {
+    out: String,
+    zone: String,
+}
+
+impl Writer {
+    fn line(&mut self, line: &str) {
+        fold(line, &mut self.out);
+    }
+
+    // A time on the user's wall clock, in the zone the VTIMEZONE describes
+    fn time(&mut self, name: &str, ts: u64) {
+        let line = format!(
+            "{};TZID={}:{}",
+            name,
+            self.zone,
+            write_stamp(&time::fields(ts))
+        );
+        self.line(&line);
+    }
+
+    // EXDATE with every time on one line, on the user's wall clock
+    fn exdates(&mut self, exdates: &[u64]) {
+        if exdates.is_empty() {
+            return;
+        }
+        let stamps: Vec<String> = exdates
+            .iter()
+            .map(|ts| write_stamp(&time::fields(*ts)))
+            .collect();
+        let line = format!("EXDATE;TZID={}:{}", self.zone, stamps.join(","));
+        self.line(&line);
+    }
+
+    fn text(&mut self, name: &str, text: &str) {
+        if !text.is_empty() {
+            let line = format!("{}:{}", name, escape(text));
+            self.line(&line);
+        }
+    }
+
+    // The user's zone between from and to, as one observance per offset change
+    fn timezone(&mut self, from: u64, to: u64) {
+        let changes = time::transitions(from, to);
+        let initial = time::utc_offset(from);
+        let standard = changes
+            .iter()
+            .map(|(_, _, after)| *after)
+            .fold(initial, i64::min);
+        let zone = self.zone.clone();
+        self.line("BEGIN:VTIMEZONE");
+        self.line(&format!("TZID:{}", zone));
+        // What was in effect before the first event, back to 1970
+        let observances = std::iter::once((0, initial, initial)).chain(changes);
+        for (at, before, after) in observances {
+            let kind = if after == standard {
+                "STANDARD"
+            } else {
+                "DAYLIGHT"
+            };
+            // Onsets are written on the wall clock as it was before the change
+            let onset = time::fields_utc((at as i64 + before).max(0) as u64);
+            self.line(&format!("BEGIN:{}", kind));
+            self.line(&format!("DTSTART:{}", write_stamp(&onset)));
+            self.line(&format!("TZOFFSETFROM:{}", write_offset(before)));
+            self.line(&format!("TZOFFSETTO:{}", write_offset(after)));
+            self.line(&format!("END:{}", kind));
+        }
+        self.line("END:VTIMEZONE");
+    }
+
+    // Opens a VEVENT, the caller adds anything else and closes it
+    fn begin_event(
+        &mut self,
+        uid: &str,
+        title: &str,
+        start: u64,
+        end: u64,
+        notes: &str,
+        stamp: &str,
+    ) {
+        self.line("BEGIN:VEVENT");
+        self.text("UID", uid);
+        self.line(&format!("DTSTAMP:{}", stamp));
+        self.time("DTSTART", start);
+        self.time("DTEND", end);
+        self.text("SUMMARY", title);
+        self.text("DESCRIPTION", notes);
+    }
+}
+
+// A file with events and tracked time, in zone (the user's zone, by name).
+// Running entries are left out, they have no end yet.
+pub fn export(events: &[CalendarEvent], entries: &[Entry], zone: &str) -> String {
+    let mut writer = Writer {
+        out: String::new(),
+        zone: zone.to_string(),
+    };
+    let stamp = utc_stamp(time::now());
+    let entries: Vec<&Entry> = entries.iter().filter(|entry| entry.end.is_some()).collect();
+    let starts = events
+        .iter()
+        .map(|event| event.start)
+        .chain(entries.iter().map(|entry| entry.start));
+    let ends = events
+        .iter()
+        .map(|event| event.end)
+        .chain(entries.iter().filter_map(|entry| entry.end));
+    let from = starts.min().unwrap_or_else(time::now);
+    let mut to = ends.max().unwrap_or(from).max(from);
+    // Repeating events go on past their first end, describe the zone for a while
+    let rules: Vec<Option<Rule>> = events
+        .iter()
+        .map(|event| event.rrule.as_deref().and_then(Rule::parse))
+        .collect();
+    for rule in rules.iter().flatten() {
+        let horizon = time::now() + REPEAT_YEARS * 366 * time::DAY;
+        to = to.max(rule.until.unwrap_or(horizon).min(horizon));
+    }
+
+    writer.line("BEGIN:VCALENDAR");
+    writer.line("VERSION:2.0");
+    writer.line(&format!("PRODID:{}", PRODID));
+    writer.line("CALSCALE:GREGORIAN");
+    writer.timezone(from, to);
+    for (event, rule) in events.iter().zip(rules) {
+        writer.begin_event(
+            &event.uid,
+            &event.title,
+            event.start,
+            event.end,
+            &event.notes,
+            &stamp,
+        );
+        // Rules we can read are written back in a standard form, others as they came
+        let rrule = rule
+            .map(|rule| rule.to_string())
+            .or_else(|| event.rrule.clone());
+        if let Some(rrule) = rrule {
+            writer.line(&format!("RRULE:{}", rrule));
+            writer.exdates(&event.exdates);
+        }
+        writer.line("END:VEVENT");
+    }
+    for entry in entries {
+        let uid = format!("entry-{}@nittei", entry.id);
+        let end = entry.end.unwrap_or(entry.start);
+        writer.begin_event(
+            &uid,
+            &entry.activity,
+            entry.start,
+            end,
+            &entry.notes,
+            &stamp,
+        );
+        writer.line("CATEGORIES:Tracked time");
+        writer.line("END:VEVENT");
+    }
+    writer.line("END:VCALENDAR");
+    writer.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A file with one event, properties one per line
+    fn calendar(props: &[&str]) -> String {
+        let mut text = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\n");
+        for prop in props {
+            text.push_str(prop);
+            text.push_str("\r\n");
+        }
+        text.push_str("END:VEVENT\r\nEND:VCALENDAR\r\n");
+        text
+    }
+
+    #[test]
+    fn escaping() {
+        let text = "Lunch, then; more\\stuff\nsecond line";
+        assert_eq!(escape(text), r"Lunch\, then\; more\\stuff\nsecond line");
+        assert_eq!(unescape(&escape(text)), text);
+        assert_eq!(unescape("a\\Nb\\"), "a\nb");
+        assert_eq!(escape("a\r\nb"), "a\\nb");
+    }
+
+    #[test]
+    fn folding() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(100));
+        let mut out = String::new();
+        fold(&line, &mut out);
+        assert!(out.split("\r\n").all(|piece| piece.len() <= LINE_MAX));
+        assert_eq!(unfold(&out), vec![line]);
+        assert_eq!(
+            unfold("A:1\n\tcontinued\n\nB:2"),
+            vec!["A:1continued", "B:2"]
+        );
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("PT1H30M"), Some(90 * time::MINUTE));
+        assert_eq!(parse_duration("+P1W"), Some(7 * time::DAY));
+        assert_eq!(
+            parse_duration("P1DT2H3M4S"),
+            Some(time::DAY + 2 * time::HOUR + 184)
+        );
+        assert_eq!(parse_duration("-PT1H"), None);
+        assert_eq!(parse_duration("PT1X"), None);
+        assert_eq!(parse_duration("PTH"), None);
+        assert_eq!(parse_duration("1H"), None);
+        assert_eq!(parse_duration("P99999999999999W"), None);
+        assert_eq!(parse_duration("P99999999999999999999D"), None);
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse_offset("+0530"), Some(19800));
+        assert_eq!(parse_offset("-0800"), Some(-28800));
+        assert_eq!(parse_offset("+013015"), Some(5415));
+        assert_eq!(parse_offset("0530"), None);
+        assert_eq!(parse_offset("+05"), None);
+        assert_eq!(parse_offset("+é00"), None);
+        assert_eq!(parse_offset("é+0100"), None);
+        assert_eq!(write_offset(-28800), "-0800");
+        assert_eq!(write_offset(19800), "+0530");
+    }
+
+    #[test]
+    fn stamps() {
+        assert_eq!(
+            parse_time("20211004T090000Z"),
+            Some(time::from_fields_utc(2021, 10, 4, 9, 0, 0))
+        );
+        assert_eq!(
+            utc_stamp(time::from_fields_utc(2021, 10, 4, 9, 0, 0)),
+            "20211004T090000Z"
+        );
+        for bad in [
+            "",
+            "2021",
+            "20211004T0900Z",
+            "2021100éT090000Z",
+            "20211004T09000éZ",
+        ] {
+            assert_eq!(parse_time(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn reads_events() {
+        let start = time::from_fields_utc(2021, 10, 4, 9, 0, 0);
+        let day = |day: u32| start + day as u64 * time::DAY;
+        let text = calendar(&[
+            r"UID:a\,b\;c",
+            "SUMMARY:Lunch\\, maybe",
+            "DESCRIPTION:line one\\nline two",
+            "DTSTART:20211004T090000Z",
+            "DURATION:PT1H30M",
+            "RRULE:FREQ=DAILY;COUNT=10",
+            "EXDATE:20211005T090000Z,20211006T090000Z",
+            "EXDATE:20211007T090000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            r"UID:a\,b\;c",
+            "RECURRENCE-ID:20211008T090000Z",
+            "SUMMARY:Late lunch",
+            "DTSTART:20211008T100000Z",
+            "DTEND:20211008T110000Z",
+        ]);
+        let events = parse(&text).unwrap();
+        assert_eq!(events.len(), 2);
+        let series = &events[0];
+        assert_eq!(series.uid, "a,b;c");
+        assert_eq!(series.title, "Lunch, maybe");
+        assert_eq!(series.notes, "line one\nline two");
+        assert_eq!(
+            (series.start, series.end),
+            (start, start + 90 * time::MINUTE)
+        );
+        assert_eq!(series.rrule.as_deref(), Some("FREQ=DAILY;COUNT=10"));
+        assert_eq!(series.exdates, vec![day(1), day(2), day(3), day(4)]);
+        let moved = &events[1];
+        assert_eq!(moved.uid, format!("a,b;c/{}", day(4)));
+        assert_eq!(moved.start, day(4) + time::HOUR);
+        assert_eq!(moved.rrule, None);
+    }
+
+    #[test]
+    fn made_up_uids() {
+        // Same start and titles as long as each other, still two events
+        let text = calendar(&[
+            "SUMMARY:Call A",
+            "DTSTART:20211004T120000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "SUMMARY:Call B",
+            "DTSTART:20211004T120000Z",
+        ]);
+        let events = parse(&text).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_ne!(events[0].uid, events[1].uid);
+        // The same event reads the same every time
+        assert_eq!(parse(&text).unwrap()[0].uid, events[0].uid);
+    }
+
+    #[test]
+    fn skips_what_it_cant_use() {
+        // No start, and a negative duration, only those events are left out
+        let text = calendar(&[
+            "SUMMARY:No start",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "SUMMARY:Backwards",
+            "DTSTART:20211004T090000Z",
+            "DURATION:-PT1H",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "SUMMARY:Fine",
+            "DTSTART:20211004T090000Z",
+        ]);
+        let events = parse(&text).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Fine");
+        assert_eq!(events[0].end, events[0].start);
+    }
+
+    #[test]
+    fn malformed_files() {
+        assert_eq!(parse(""), Err(IcsError::NotCalendar));
+        assert_eq!(parse("hello:world"), Err(IcsError::NotCalendar));
+        assert_eq!(
+            parse("BEGIN:VEVENT\r\nEND:VEVENT"),
+            Err(IcsError::NotCalendar)
+        );
+        assert_eq!(
+            parse("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VCALENDAR"),
+            Err(IcsError::Unbalanced(String::from("VEVENT")))
+        );
+        assert_eq!(
+            parse("BEGIN:VCALENDAR\r\nBEGIN:VEVENT"),
+            Err(IcsError::Unbalanced(String::from("VEVENT")))
+        );
+        assert_eq!(
+            parse("BEGIN:VCALENDAR\r\nno colon\r\nEND:VCALENDAR"),
+            Err(IcsError::BadLine(2))
+        );
+        assert_eq!(
+            parse(&calendar(&["DTSTART:2021"])),
+            Err(IcsError::BadTime(String::from("2021")))
+        );
+        assert_eq!(
+            parse(&calendar(&[
+                "DTSTART:20211004T090000Z",
+                "DURATION:P99999999999999W"
+            ])),
+            Err(IcsError::BadTime(String::from("P99999999999999W")))
+        );
+        // The last second a file can write, anything ending later is refused
+        assert_eq!(
+            parse(&calendar(&["DTSTART:99991231T235959Z", "DURATION:P1W"])),
+            Err(IcsError::BadTime(String::from("P1W")))
+        );
+        assert!(parse(&calendar(&["DTSTART:99991231T235959Z", "DURATION:PT0S"])).is_ok());
+    }
+
+    #[test]
+    fn malformed_timezone_rules() {
+        // An observance with a rule we can't read is dropped, without panicking
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTIMEZONE",
+            "TZID:Broken",
+            "BEGIN:STANDARD",
+            "DTSTART:19701025T030000",
+            "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=1é",
+            "TZOFFSETFROM:+0200",
+            "TZOFFSETTO:+0100",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:19700329T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=é",
+            "TZOFFSETFROM:+é100",
+            "TZOFFSETTO:+0200",
+            "END:DAYLIGHT",
+            "END:VTIMEZONE",
+            "BEGIN:VEVENT",
+            "DTSTART:20211004T090000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        assert_eq!(parse(&text).unwrap().len(), 1);
+    }
+}
+
+// Times in a zone and exports read the wall clock, which takes the browser's Intl
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn zone_from_the_file() {
+        time::set_zone(Some("UTC"));
+        let text = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTIMEZONE",
+            "TZID:Somewhere",
+            "BEGIN:STANDARD",
+            "DTSTART:19701025T030000",
+            "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+            "TZOFFSETFROM:+0200",
+            "TZOFFSETTO:+0100",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:19700329T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+            "TZOFFSETFROM:+0100",
+            "TZOFFSETTO:+0200",
+            "END:DAYLIGHT",
+            "END:VTIMEZONE",
+            "BEGIN:VEVENT",
+            "DTSTART;TZID=Somewhere:20211004T090000",
+            "DTEND;TZID=Somewhere:20211104T090000",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let events = parse(&text).unwrap();
+        assert_eq!(events[0].start, time::from_fields_utc(2021, 10, 4, 7, 0, 0));
+        assert_eq!(events[0].end, time::from_fields_utc(2021, 11, 4, 8, 0, 0));
+    }
+
+    #[wasm_bindgen_test]
+    fn round_trip() {
+        time::set_zone(Some("UTC"));
+        let start = time::from_fields_utc(2021, 10, 4, 9, 0, 0);
+        let event = CalendarEvent {
+            id: 1,
+            uid: String::from("a,b;c\\d@example.com"),
+            title: String::from("Lunch, maybe; or not"),
+            start,
+            end: start + time::HOUR,
+            notes: format!("{}\nsecond line", "long ".repeat(30)),
+            rrule: Some(String::from("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=6")),
+            exdates: vec![start + 2 * time::DAY],
+        };
+        let text = export(std::slice::from_ref(&event), &[], "UTC");
+        assert!(text.split("\r\n").all(|line| line.len() <= LINE_MAX));
+        let events = parse(&text).unwrap();
+        assert_eq!(events.len(), 1);
+        let read = &events[0];
+        assert_eq!(read.uid, event.uid);
+        assert_eq!(read.title, event.title);
+        assert_eq!(read.notes, event.notes);
+        assert_eq!((read.start, read.end), (event.start, event.end));
+        assert_eq!(read.rrule, event.rrule);
+        assert_eq!(read.exdates, event.exdates);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::api::{ApiError, ErrorKind};
+use crate::consts;
+use gloo::timers::callback::Interval;
+use js_sys::Date;
+use yew::prelude::*;
+
+// When a lockout of secs seconds started now ends, in milliseconds since the epoch.
+// Servers that don't say how long get the fallback.
+pub fn until(secs: Option<u32>) -> f64 {
+    let secs = secs.unwrap_or(consts::LOCKOUT_FALLBACK);
+    Date::now() + secs as f64 * 1000.0
+}
+
+// When a rate limited (429) request can be tried again, None for any other error
+pub fn from_error(err: &ApiError) -> Option<f64> {
+    match err.kind {
+        ErrorKind::RateLimited(secs) => Some(until(secs)),
+        _ => None,
+    }
+}
+
+// Whole seconds left until a lockout ends
+fn remaining(until: f64) -> u32 {
+    ((until - Date::now()) / 1000.0).ceil().max(0.0) as u32
+}
+
+// "1:05", or "42 seconds" under a minute
+fn format_remaining(secs: u32) -> String {
+    if secs < 60 {
+        format!("{} seconds", secs)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct CountdownProps {
+    // When the lockout ends, from until or from_error
+    pub until: f64,
+    // Called once the time is up, so the form can be sent again
+    pub onelapsed: Callback<()>,
+}
+
+pub enum CountdownMsg {
+    Tick,
+}
+
+// "Try again in ..." counting down to the end of a lockout.
+// The form owning it keeps its submit button disabled until onelapsed.
+pub struct Countdown {
+    _ticker: Interval,
+}
+
+impl Component for Countdown {
+    type Message = CountdownMsg;
+    type Properties = CountdownProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let link = ctx.link().clone();
+        Self {
+            _ticker: Interval::new(1000, move || link.send_message(CountdownMsg::Tick)),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            CountdownMsg::Tick => {
+                if remaining(ctx.props().until) == 0 {
+                    ctx.props().onelapsed.emit(());
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let secs = remaining(ctx.props().until);
+        html! {
+            <p class="failuretext lockout">
+                { format!("Too many attempts. Try again in {}.", format_remaining(secs)) }
+            </p>
+        }
+    }
+}
@@ -1,22 +1,46 @@
+use crate::api::{
+    self, ApiError, ApiResult, Client, LoginReply, SecondFactor, SecondFactorRequest,
+    SecondFactorResponse, Throttled,
+};
 use crate::app::AppRoute;
-use crate::consts;
+use crate::config;
+use crate::form::{FieldEvent, Form, Validator};
+use crate::lockout::{self, Countdown};
 use crate::nav::{Anchor, Nav};
+use crate::session;
 use crate::timers;
+use crate::toast;
 use crate::util;
-use gloo::storage::{SessionStorage, Storage};
-use nittei_common::auth::*;
-use reqwasm::http::Request;
-use ron;
+use nittei_common::auth::{AuthClaim, AuthToken, LoginRequest};
 use web_sys::{HtmlInputElement, MouseEvent};
 use yew::prelude::*;
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LoginField {
+    Email,
+    Password,
+}
+
+// The second factor step has its own form
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CodeField {
+    Code,
+}
+
 #[derive(Clone, Debug)]
 pub enum LoginMsg {
     Login,
-    LoginRecieved(LoginResponse),
-    LoginFailed,
-    Disconnected,
-    Missing,
+    Field(FieldEvent<LoginField>),
+    LoginRecieved(Throttled<LoginReply>),
+    Error(ApiError),
+    // A lockout ran out
+    Unlocked,
+    // Second factor step
+    Code(FieldEvent<CodeField>),
+    Verify,
+    ToggleRecovery,
+    Back,
+    Verified(ApiResult<Throttled<SecondFactorResponse>>),
 }
 
 #[derive(Properties, Clone, PartialEq)]
@@ -24,66 +48,132 @@ pub struct LoginProps {
     pub href: Option<AppRoute>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum LoginState {
-    Normal,
-    Disconnected,
-    Failed,
-    Missing,
-    LockedOut,
-    BadEmail,
-    BadPassword,
-}
-
-impl From<LoginState> for String {
-    fn from(state: LoginState) -> String {
-        match state {
-            LoginState::Normal => String::new(),
-            LoginState::Disconnected => String::from("Disconnected from network"),
-            LoginState::Failed => String::from("Internal Server Error"),
-            LoginState::Missing => String::from("Missing field value!"),
-            LoginState::LockedOut => String::from("Too many attempts. Please wait."),
-            LoginState::BadEmail => String::from("Invalid email address!"),
-            LoginState::BadPassword => String::from("Invalid password!"),
-        }
-    }
+// A login waiting on a second factor.
+// The first form is gone by then, so what it said is kept here.
+struct Pending {
+    challenge: u128,
+    email: String,
+    password: String,
+    remember: bool,
 }
 
 pub struct Login {
-    state: LoginState,
+    form: Form<LoginField>,
+    code_form: Form<CodeField>,
+    pending: Option<Pending>,
+    // Typing a recovery code instead of an app code
+    recovery: bool,
+    // Error about the whole form rather than one field
+    failure: Option<String>,
+    // Locked out until then, in milliseconds since the epoch
+    locked_until: Option<f64>,
     href: Option<AppRoute>,
-    emailref: NodeRef,
-    passref: NodeRef,
     rememberref: NodeRef,
 }
 
-// use reqwasm to do a login API call
+// Do a login API call
 async fn login_request(email: String, password: String) -> LoginMsg {
-    let ron_request = LoginRequest { email, password };
-    let ron_request = ron::to_string(&ron_request).expect("Should serialize");
-    let req = Request::post(&format!("{}/auth/login", consts::URL))
-        .header("Content-Type", "application/x-login-request")
-        .body(&ron_request);
-    let resp = req.send().await;
-    if resp.is_err() {
-        return LoginMsg::Disconnected;
+    let req = LoginRequest { email, password };
+    match Client::anonymous().login(&req).await {
+        Ok(resp) => LoginMsg::LoginRecieved(resp),
+        Err(e) => LoginMsg::Error(e),
     }
-    let resp = resp.unwrap();
-    if resp.status() != 200 {
-        return LoginMsg::LoginFailed;
+}
+
+impl Login {
+    fn locked(&self) -> bool {
+        self.locked_until.is_some()
+    }
+
+    // Rate limited requests get a countdown, anything else a toast
+    fn error(&mut self, err: &ApiError) {
+        match lockout::from_error(err) {
+            Some(until) => self.locked_until = Some(until),
+            None => toast::api_error(err),
+        }
     }
 
-    let resp = resp.text().await;
-    if resp.is_err() {
-        return LoginMsg::LoginFailed;
+    fn remember(&self) -> bool {
+        self.rememberref
+            .cast::<HtmlInputElement>()
+            .map(|check| check.checked())
+            .unwrap_or(false)
+    }
+
+    // Got a session one way or another
+    fn logged_in(
+        &mut self,
+        token: AuthToken,
+        claim: AuthClaim,
+        email: String,
+        password: String,
+        remember: bool,
+    ) {
+        if session::login(token, claim).is_err() {
+            self.failure = Some(String::from("Something went wrong. Please try again."));
+            return;
+        }
+        timers::session_refresh();
+        if remember && config::get().remember_me {
+            util::request_persistence(email, password);
+        }
+        yew_router::replace_route(self.href.clone().unwrap_or(AppRoute::Feed));
     }
-    let resp = resp.unwrap();
 
-    let resp = ron::from_str::<LoginResponse>(&resp);
-    if resp.is_err() {
-        LoginMsg::LoginFailed
-    } else {
-        LoginMsg::LoginRecieved(resp.unwrap())
+    fn login_view(&self, ctx: &Context<Self>) -> Html {
+        // When the login button is clicked, don't submit the form, but message this component
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            LoginMsg::Login
+        });
+        html! {
+            <form id="loginform">
+                { self.form.view_field(ctx.link(), LoginField::Email, "emailbox", LoginMsg::Field) }
+                { self.form.view_field(ctx.link(), LoginField::Password, "passbox", LoginMsg::Field) }
+                <div id="login-remember" style={if config::get().remember_me { "" } else { "display: none;" }}>
+                    <input type="checkbox" id="rememberme" name="rememberme" ref={self.rememberref.clone()} />
+                    <label for="rememberme">{ "Remember Me" }</label>
+                </div>
+                <button id="loginsubmit" type="submit" onclick={cb} disabled={!self.form.can_submit() || self.locked()}>
+                    { if self.form.submitting() { "Logging in..." } else { "Log In" } }
+                </button>
+            </form>
+        }
+    }
+
+    fn code_view(&self, ctx: &Context<Self>) -> Html {
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            LoginMsg::Verify
+        });
+        let toggle = ctx
+            .link()
+            .callback(|_: MouseEvent| LoginMsg::ToggleRecovery);
+        let back = ctx.link().callback(|_: MouseEvent| LoginMsg::Back);
+        let (prompt, other) = if self.recovery {
+            (
+                "Enter one of your recovery codes.",
+                "Use the authenticator app instead",
+            )
+        } else {
+            (
+                "Enter the code from your authenticator app.",
+                "Use a recovery code instead",
+            )
+        };
+        html! {
+            <>
+                <form id="loginform">
+                    <p>{ prompt }</p>
+                    { self.code_form.view_field(ctx.link(), CodeField::Code, "codebox", LoginMsg::Code) }
+                    <button id="loginsubmit" type="submit" onclick={cb} disabled={!self.code_form.can_submit() || self.locked()}>
+                        { if self.code_form.submitting() { "Checking..." } else { "Verify" } }
+                    </button>
+                </form>
+                <p><button class="linkbutton" onclick={toggle}>{ other }</button></p>
+                <p><button class="linkbutton" onclick={back}>{ "Start over" }</button></p>
+            </>
+        }
     }
 }
 
@@ -92,112 +182,192 @@ impl Component for Login {
     type Properties = LoginProps;
 
     fn create(ctx: &Context<Self>) -> Self {
+        let form = Form::new()
+            .text(
+                LoginField::Email,
+                "email",
+                "Email",
+                vec![Validator::Required, Validator::Email],
+            )
+            .password(
+                LoginField::Password,
+                "password",
+                "Password",
+                vec![Validator::Required],
+            );
+        let code_form =
+            Form::new().text(CodeField::Code, "code", "Code", vec![Validator::Required]);
         Self {
-            state: LoginState::Normal,
+            form,
+            code_form,
+            pending: None,
+            recovery: false,
+            failure: None,
+            locked_until: None,
             href: ctx.props().href.clone(),
-            emailref: NodeRef::default(),
-            passref: NodeRef::default(),
             rememberref: NodeRef::default(),
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
+            LoginMsg::Field(event) => self.form.event(event),
+            LoginMsg::Code(event) => self.code_form.event(event),
             LoginMsg::Login => {
-                let emailref = self.emailref.clone();
-                let passref = self.passref.clone();
-                ctx.link().send_future(async move {
-                    let userbox = emailref.cast::<HtmlInputElement>();
-                    let passbox = passref.cast::<HtmlInputElement>();
-                    if userbox.is_none() || passbox.is_none() {
-                        return LoginMsg::LoginFailed;
-                    }
-                    let userbox = userbox.unwrap();
-                    let passbox = passbox.unwrap();
-                    let username = userbox.value();
-                    let password = passbox.value();
-                    if username.is_empty() || password.is_empty() {
-                        return LoginMsg::Missing;
-                    }
-                    login_request(username, password).await
-                });
-                false
+                if !self.form.can_submit() || self.locked() {
+                    return false;
+                }
+                self.failure = None;
+                if !self.form.validate() {
+                    return true;
+                }
+
+                let email = self.form.value(LoginField::Email);
+                let password = self.form.value(LoginField::Password);
+                ctx.link()
+                    .send_future(async move { login_request(email, password).await });
+                self.form.set_submitting(true);
+                true
+            }
+            LoginMsg::Error(e) => {
+                self.error(&e);
+                self.form.set_submitting(false);
+                true
             }
-            LoginMsg::Disconnected => {
-                self.state = LoginState::Disconnected;
+            LoginMsg::Unlocked => {
+                self.locked_until = None;
                 true
             }
             LoginMsg::LoginRecieved(resp) => {
-                match resp {
-                    LoginResponse::InvalidRequest => self.state = LoginState::Failed,
-                    LoginResponse::LockedOut => self.state = LoginState::LockedOut,
-                    LoginResponse::PasswordWrong => self.state = LoginState::BadPassword,
-                    LoginResponse::EmailInvalid => self.state = LoginState::BadEmail,
-                    LoginResponse::Success(token, claim) => {
-                        if SessionStorage::set("session_key", token).is_err()
-                            || SessionStorage::set("session", claim).is_err()
-                        {
-                            self.state = LoginState::Failed;
-                        } else {
-                            timers::session_refresh();
-                            let checkbox = self.rememberref.cast::<HtmlInputElement>();
-                            let user = self.emailref.cast::<HtmlInputElement>();
-                            let pass = self.passref.cast::<HtmlInputElement>();
-
-                            if let Some(user) = user {
-                                if let Some(pass) = pass {
-                                    if let Some(check) = checkbox {
-                                        if check.checked() {
-                                            util::request_persistence(user.value(), pass.value());
-                                        }
-                                    }
-                                }
-                            }
-
-                            yew_router::replace_route(self.href.clone().unwrap_or(AppRoute::Home));
-                        }
+                self.form.set_submitting(false);
+                match resp.body {
+                    LoginReply::InvalidRequest => self.failure = Some(String::from(api::REJECTED)),
+                    LoginReply::LockedOut => {
+                        self.locked_until = Some(lockout::until(resp.retry_after))
+                    }
+                    LoginReply::PasswordWrong => self
+                        .form
+                        .set_error(LoginField::Password, "Invalid password!"),
+                    LoginReply::EmailInvalid => self
+                        .form
+                        .set_error(LoginField::Email, "Invalid email address!"),
+                    LoginReply::SecondFactor(challenge) => {
+                        self.pending = Some(Pending {
+                            challenge,
+                            email: self.form.value(LoginField::Email),
+                            password: self.form.value(LoginField::Password),
+                            remember: self.remember(),
+                        });
+                        self.recovery = false;
+                    }
+                    LoginReply::Success(token, claim) => {
+                        let email = self.form.value(LoginField::Email);
+                        let password = self.form.value(LoginField::Password);
+                        let remember = self.remember();
+                        self.logged_in(token, claim, email, password, remember);
                     }
                 };
                 true
             }
-            LoginMsg::LoginFailed => {
-                self.state = LoginState::Failed;
+            LoginMsg::ToggleRecovery => {
+                self.recovery = !self.recovery;
+                self.failure = None;
                 true
             }
-            LoginMsg::Missing => {
-                self.state = LoginState::Missing;
+            LoginMsg::Back => {
+                self.pending = None;
+                self.failure = None;
+                true
+            }
+            LoginMsg::Verify => {
+                let challenge = match &self.pending {
+                    Some(pending) => pending.challenge,
+                    None => return false,
+                };
+                if !self.code_form.can_submit() || self.locked() {
+                    return false;
+                }
+                self.failure = None;
+                if !self.code_form.validate() {
+                    return true;
+                }
+
+                let code = self.code_form.value(CodeField::Code).replace(' ', "");
+                let code = if self.recovery {
+                    SecondFactor::Recovery(code)
+                } else {
+                    SecondFactor::Totp(code)
+                };
+                let req = SecondFactorRequest { challenge, code };
+                ctx.link().send_future(async move {
+                    LoginMsg::Verified(Client::anonymous().second_factor(&req).await)
+                });
+                self.code_form.set_submitting(true);
+                true
+            }
+            LoginMsg::Verified(resp) => {
+                self.code_form.set_submitting(false);
+                match resp.map(|resp| (resp.body, resp.retry_after)) {
+                    Ok((SecondFactorResponse::Success(token, claim), _)) => {
+                        if let Some(pending) = self.pending.take() {
+                            self.logged_in(
+                                token,
+                                claim,
+                                pending.email,
+                                pending.password,
+                                pending.remember,
+                            );
+                        }
+                    }
+                    Ok((SecondFactorResponse::BadCode, _)) => {
+                        self.code_form.set_error(CodeField::Code, "Wrong code!")
+                    }
+                    Ok((SecondFactorResponse::ExpiredChallenge, _)) => {
+                        self.pending = None;
+                        self.failure =
+                            Some(String::from("That took too long, please log in again."));
+                    }
+                    Ok((SecondFactorResponse::LockedOut, retry_after)) => {
+                        self.locked_until = Some(lockout::until(retry_after))
+                    }
+                    Ok((SecondFactorResponse::InvalidRequest, _)) => {
+                        self.failure = Some(String::from(api::REJECTED))
+                    }
+                    Err(e) => self.error(&e),
+                }
                 true
             }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        // When the login button is clicked, don't submit the form, but message this component
-        let cb = ctx.link().callback(|e: MouseEvent| {
-            e.prevent_default();
-            LoginMsg::Login
-        });
-        let failure_text: String = self.state.into(); // Error to display
+        let failure_text = self.failure.clone().unwrap_or_default(); // Error to display
+        let countdown = match self.locked_until {
+            Some(until) => {
+                let onelapsed = ctx.link().callback(|_| LoginMsg::Unlocked);
+                html! { <Countdown until={until} onelapsed={onelapsed} /> }
+            }
+            None => html! {},
+        };
+        let step = if self.pending.is_some() {
+            self.code_view(ctx)
+        } else {
+            self.login_view(ctx)
+        };
         html! {
             <>
                 <Nav route={AppRoute::Login} />
                 <main id="login" class="content">
-                    <form id="loginform">
-                        <label for="emailbox">{ "Email" }</label>
-                        <input type="text" id="emailbox" name="email" ref={self.emailref.clone()} />
-                        <label for="passbox">{ "Password" }</label>
-                        <input type="password" id="passbox" name="password" ref={self.passref.clone()} />
-                        <div id="login-remember">
-                            <input type="checkbox" id="rememberme" name="rememberme" ref={self.rememberref.clone()} />
-                            <label for="rememberme">{ "Remember Me" }</label>
-                        </div>
-                        <button id="loginsubmit" type="submit" onclick={cb}>{ "Log In" }</button>
-                    </form>
+                    { step }
+                    { countdown }
                     // Only display error if text is not empty
                     <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>
                         { failure_text }
                     </p>
                     <p>
+                        <Anchor route={AppRoute::Forgot}>{ "Forgot password?" }</Anchor>
+                    </p>
+                    <p style={if config::get().registration { "" } else { "display: none;" }}>
                         {"Don't have an account?"}
                         <Anchor route={AppRoute::Register}>{ "Sign Up" }</Anchor>
                     </p>
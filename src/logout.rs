@@ -0,0 +1,58 @@
+use crate::app::AppRoute;
+use crate::nav::Nav;
+use crate::util;
+use yew::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogoutMsg {
+    Done,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Properties)]
+pub struct LogoutProps {
+    // Also sign out every other device
+    #[prop_or(false)]
+    pub everywhere: bool,
+}
+
+// Signs the user out as soon as it is shown, then goes home
+pub struct Logout {}
+
+impl Component for Logout {
+    type Message = LogoutMsg;
+    type Properties = LogoutProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let everywhere = ctx.props().everywhere;
+        ctx.link().send_future(async move {
+            util::logout(everywhere).await;
+            LogoutMsg::Done
+        });
+        Self {}
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            LogoutMsg::Done => {
+                yew_router::replace_route(AppRoute::Home);
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let route = if ctx.props().everywhere {
+            AppRoute::LogoutAll
+        } else {
+            AppRoute::Logout
+        };
+        html! {
+            <>
+                <Nav route={route} />
+                <main id="logout" class="content">
+                    <p>{ "Logging out..." }</p>
+                </main>
+            </>
+        }
+    }
+}
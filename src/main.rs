@@ -1,11 +1,57 @@
+pub mod config;
 pub mod consts;
 pub mod timers;
 
 pub mod util;
 
+// Local dates and times, for tracking and scheduling
+pub mod time;
+
+// Repeating events, the RRULE subset the calendar understands
+pub mod recur;
+
+// iCalendar files, for import and export
+pub mod ics;
+
+// Calendar preferences of the user
+pub mod prefs;
+
+// Typed client for the nittei API
+pub mod api;
+
+// The logged in user, shared with every component
+pub mod session;
+
+// Keeps the session the same across tabs
+pub mod sync;
+
 // The overall app, all the pages, etc
 pub mod app;
 
+// Keeps logged out users away from private pages
+pub mod guard;
+
+// Form fields and validation, shared by every form
+pub mod form;
+
+// Countdowns for locked out and rate limited forms
+pub mod lockout;
+
+// Checks new passwords against known data breaches
+pub mod breach;
+
+// QR codes, for setting up authenticator apps
+pub mod qr;
+
+// Password strength meter
+pub mod strength;
+
+// Error toasts and the offline banner
+pub mod toast;
+
+// Shown when the app is misconfigured or the server is unreachable
+pub mod error_page;
+
 // The navigation bar
 pub mod nav;
 
@@ -15,15 +61,33 @@ pub mod home;
 // Tracking page
 pub mod track;
 
+// Stopwatch, countdown and Pomodoro page
+pub mod timer;
+
+// Calendar page
+pub mod calendar;
+
 // Login page
 pub mod login;
 
+// Logout page
+pub mod logout;
+
 // Registration page
 pub mod register;
 
+// Forgotten password page
+pub mod forgot;
+
+// Password reset page
+pub mod reset;
+
 // Email verification page
 pub mod verify;
 
+// Account settings page
+pub mod account;
+
 fn main() {
     yew::start_app::<app::Main>();
 }
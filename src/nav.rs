@@ -1,10 +1,18 @@
 use crate::app::AppRoute;
+use crate::config;
+use crate::session::SessionContext;
+use std::mem::discriminant;
+use url_escape::encode_component;
+use web_sys::MouseEvent;
+use yew::context::ContextHandle;
 use yew::prelude::*;
 use yew_router::components::Link;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum NavMsg {
     Hide,
+    Menu,
+    Session(SessionContext),
 }
 
 #[derive(Clone, PartialEq, Properties)]
@@ -16,6 +24,84 @@ pub struct NavProps {
 pub struct Nav {
     props: NavProps,
     active: bool,
+    menu: bool,
+    session: SessionContext,
+    _session_handle: ContextHandle<SessionContext>,
+}
+
+// Links shown to everybody, in order
+const PAGES: [AppRoute; 7] = [
+    AppRoute::Home,
+    AppRoute::Track,
+    AppRoute::Timer,
+    AppRoute::Calendar,
+    AppRoute::Feed,
+    AppRoute::About,
+    AppRoute::Donate,
+];
+
+impl Nav {
+    // Class for the list item linking to route
+    fn class(&self, route: &AppRoute) -> &'static str {
+        // Compare only which page it is, not the parameters
+        if discriminant(&self.props.route) == discriminant(route) {
+            "navcurrent"
+        } else {
+            ""
+        }
+    }
+
+    fn item(&self, route: AppRoute) -> Html {
+        let class = self.class(&route);
+        let text: String = route.clone().into();
+        html! {
+            <li class={class}><Anchor route={route}>{ text }</Anchor></li>
+        }
+    }
+
+    // Login/Register, or the user menu if logged in
+    fn account(&self, ctx: &Context<Self>) -> Html {
+        if !self.session.is_authenticated() {
+            let register = if config::get().registration {
+                self.item(AppRoute::Register)
+            } else {
+                html! {}
+            };
+            return html! {
+                <>
+                    { self.item(AppRoute::Login) }
+                    { register }
+                </>
+            };
+        }
+
+        let name = self
+            .session
+            .username()
+            .or_else(|| self.session.user_id())
+            .unwrap_or_default();
+        let route = AppRoute::UserPage {
+            username: encode_component(&name).to_string(),
+        };
+        let class = format!(
+            "{} {}",
+            self.class(&route),
+            if self.menu { "menuopen" } else { "" }
+        );
+        let menu_cb = ctx.link().callback(|_: MouseEvent| NavMsg::Menu);
+
+        html! {
+            <li id="navuser" class={class}>
+                <Anchor route={route}>{ name }</Anchor>
+                <button id="navmenubutton" onclick={menu_cb}>{ "▾" }</button>
+                <ul id="navmenu">
+                    { self.item(AppRoute::Account) }
+                    { self.item(AppRoute::Logout) }
+                    { self.item(AppRoute::LogoutAll) }
+                </ul>
+            </li>
+        }
+    }
 }
 
 impl Component for Nav {
@@ -23,9 +109,16 @@ impl Component for Nav {
     type Properties = NavProps;
 
     fn create(ctx: &Context<Self>) -> Self {
+        let (session, handle) = ctx
+            .link()
+            .context::<SessionContext>(ctx.link().callback(NavMsg::Session))
+            .expect("No session context");
         Self {
             props: ctx.props().to_owned(),
             active: false,
+            menu: false,
+            session,
+            _session_handle: handle,
         }
     }
 
@@ -35,48 +128,20 @@ impl Component for Nav {
                 self.active = !self.active;
                 true
             }
+            NavMsg::Menu => {
+                self.menu = !self.menu;
+                true
+            }
+            NavMsg::Session(session) => {
+                self.session = session;
+                self.menu = false;
+                true
+            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let home_class = if self.props.route == AppRoute::Home {
-            "navcurrent"
-        } else {
-            ""
-        };
-        let track_class = if self.props.route == AppRoute::Track {
-            "navcurrent"
-        } else {
-            ""
-        };
-        let feed_class = if self.props.route == AppRoute::Feed {
-            "navcurrent"
-        } else {
-            ""
-        };
-        let about_class = if self.props.route == AppRoute::About {
-            "navcurrent"
-        } else {
-            ""
-        };
-        let donate_class = if self.props.route == AppRoute::Donate {
-            "navcurrent"
-        } else {
-            ""
-        };
-        let login_class = if self.props.route == AppRoute::Login {
-            "navcurrent"
-        } else {
-            ""
-        };
-
         let nav_class = if self.active { "navactive" } else { "" };
-        let home_text: String = AppRoute::Home.into();
-        let track_text: String = AppRoute::Track.into();
-        let feed_text: String = AppRoute::Feed.into();
-        let about_text: String = AppRoute::About.into();
-        let donate_text: String = AppRoute::Donate.into();
-        let login_text: String = AppRoute::Login.into();
 
         html! {
             <nav class={nav_class}>
@@ -84,12 +149,8 @@ impl Component for Nav {
                     <img src="/res/minilogo.svg" alt="Nittei Mini Logo" />
                 </button>
                 <ul>
-                    <li class={home_class}><Anchor route={AppRoute::Home}>{ home_text }</Anchor></li>
-                    <li class={track_class}><Anchor route={AppRoute::Track}>{ track_text }</Anchor></li>
-                    <li class={feed_class}><Anchor route={AppRoute::Feed}>{ feed_text }</Anchor></li>
-                    <li class={about_class}><Anchor route={AppRoute::About}>{ about_text }</Anchor></li>
-                    <li class={donate_class}><Anchor route={AppRoute::Donate}>{ donate_text }</Anchor></li>
-                    <li class={login_class}><Anchor route={AppRoute::Login}>{ login_text }</Anchor></li>
+                    { for PAGES.iter().cloned().map(|route| self.item(route)) }
+                    { self.account(ctx) }
                 </ul>
             </nav>
         }
@@ -0,0 +1,56 @@
+use crate::api::{Client, Preferences, PreferencesResponse};
+use crate::time;
+use gloo::storage::{LocalStorage, Storage};
+use std::cell::RefCell;
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            first_day: time::MONDAY,
+            time_zone: None,
+        }
+    }
+}
+
+thread_local! {
+    // Kept in local storage too, so dates come out right before the server answers
+    static PREFS: RefCell<Preferences> =
+        RefCell::new(LocalStorage::get("prefs").unwrap_or_default());
+}
+
+pub fn get() -> Preferences {
+    PREFS.with(|prefs| prefs.borrow().clone())
+}
+
+// Use new preferences from now on
+pub fn set(prefs: Preferences) {
+    time::set_zone(prefs.time_zone.as_deref());
+    if LocalStorage::set("prefs", &prefs).is_err() {
+        console_web::error!("Could not save preferences");
+    }
+    PREFS.with(|cell| *cell.borrow_mut() = prefs);
+}
+
+// Apply the cached preferences, before anything shows a date
+pub fn init() {
+    time::set_zone(get().time_zone.as_deref());
+}
+
+// Forget the preferences of whoever logged out
+pub fn clear() {
+    LocalStorage::delete("prefs");
+    time::set_zone(None);
+    PREFS.with(|cell| *cell.borrow_mut() = Preferences::default());
+}
+
+// Get the preferences from the server, returns true if they changed
+pub async fn load() -> bool {
+    match Client::new().preferences().await {
+        Ok(PreferencesResponse::Success(prefs)) if prefs != get() => {
+            set(prefs);
+            true
+        }
+        // Keep the cached ones, the page works fine with them
+        _ => false,
+    }
+}
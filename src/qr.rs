@@ -0,0 +1,58 @@
+use qrcode::{Color, QrCode};
+use yew::prelude::*;
+
+// Blank modules around the code, scanners need at least 4
+const QUIET_ZONE: usize = 4;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct QrProps {
+    // What the code says
+    pub data: String,
+}
+
+// QR code drawn as an SVG, built right here so the data never leaves the browser
+pub struct Qr {}
+
+impl Component for Qr {
+    type Message = ();
+    type Properties = QrProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {}
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, _msg: Self::Message) -> bool {
+        false
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let code = match QrCode::new(ctx.props().data.as_bytes()) {
+            Ok(code) => code,
+            Err(_) => return html! { <p class="failuretext">{ "Could not draw QR code." }</p> },
+        };
+
+        let width = code.width();
+        let size = width + 2 * QUIET_ZONE;
+        // One rect per dark module
+        let modules = code
+            .to_colors()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, color)| *color == Color::Dark)
+            .map(|(i, _)| {
+                let x = (i % width + QUIET_ZONE).to_string();
+                let y = (i / width + QUIET_ZONE).to_string();
+                html! { <rect x={x} y={y} width="1" height="1" /> }
+            });
+
+        html! {
+            <svg class="qrcode" viewBox={format!("0 0 {} {}", size, size)}
+                xmlns="http://www.w3.org/2000/svg" shape-rendering="crispEdges">
+                <rect width="100%" height="100%" fill="white" />
+                <g fill="black">
+                    { for modules }
+                </g>
+            </svg>
+        }
+    }
+}
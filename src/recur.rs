@@ -0,0 +1,712 @@
+use crate::ics;
+use crate::time;
+use std::collections::HashMap;
+use std::fmt;
+
+// RRULE recurrence, the daily, weekly and monthly parts of RFC 5545.
+// Occurrences are counted on the user's wall clock, so a 9:00 meeting
+// stays at 9:00 across daylight saving changes.
+
+// Most periods walked through before giving up on a rule
+const PERIODS_MAX: u64 = 100_000;
+
+// Weekdays as RRULEs write them, 0 for Sunday like time::Fields
+const WEEKDAYS: [&str; 7] = ["SU", "MO", "TU", "WE", "TH", "FR", "SA"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Freq {
+    fn name(&self) -> &'static str {
+        match self {
+            Freq::Daily => "DAILY",
+            Freq::Weekly => "WEEKLY",
+            Freq::Monthly => "MONTHLY",
+        }
+    }
+
+    // Longest a period can be, in seconds
+    fn max_length(&self) -> u64 {
+        match self {
+            // Daylight saving can add an hour
+            Freq::Daily => time::DAY + time::HOUR,
+            Freq::Weekly => 7 * time::DAY + time::HOUR,
+            Freq::Monthly => 31 * time::DAY + time::HOUR,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rule {
+    pub freq: Freq,
+    // Every how many periods
+    pub interval: u32,
+    // BYDAY: which one in the month (None for all of them, negative from the end)
+    // and the weekday
+    pub by_day: Vec<(Option<i32>, u32)>,
+    // BYMONTHDAY, negative from the end of the month
+    pub by_month_day: Vec<i32>,
+    pub count: Option<u32>,
+    // Last time an occurrence may start, unix timestamp
+    pub until: Option<u64>,
+    // WKST, the day weeks start on for weekly rules
+    pub week_start: u32,
+}
+
+impl Rule {
+    // A rule repeating every period from the start on
+    pub fn new(freq: Freq) -> Self {
+        Self {
+            freq,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            count: None,
+            until: None,
+            week_start: time::MONDAY,
+        }
+    }
+
+    // None for rules this calendar can't follow, like yearly ones
+    pub fn parse(rule: &str) -> Option<Self> {
+        let parts = rule_parts(rule);
+        let freq = match parts.get("FREQ")?.as_str() {
+            "DAILY" => Freq::Daily,
+            "WEEKLY" => Freq::Weekly,
+            "MONTHLY" => Freq::Monthly,
+            _ => return None,
+        };
+        let known = [
+            "FREQ",
+            "INTERVAL",
+            "BYDAY",
+            "BYMONTHDAY",
+            "COUNT",
+            "UNTIL",
+            "WKST",
+        ];
+        // Leaving out a part we don't know would make more occurrences than the rule has
+        if parts.keys().any(|key| !known.contains(&key.as_str())) {
+            return None;
+        }
+        let list = |key: &str| -> Vec<String> {
+            parts
+                .get(key)
+                .map(|value| value.split(',').map(String::from).collect())
+                .unwrap_or_default()
+        };
+        let by_day = list("BYDAY")
+            .iter()
+            .map(|day| parse_byday(day))
+            .collect::<Option<Vec<_>>>()?;
+        // Which one in the month only means something for monthly rules
+        if freq != Freq::Monthly && by_day.iter().any(|(nth, _)| nth.is_some()) {
+            return None;
+        }
+        let by_month_day = list("BYMONTHDAY")
+            .iter()
+            .map(|day| day.trim().parse().ok().filter(|day: &i32| *day != 0))
+            .collect::<Option<Vec<_>>>()?;
+        // RFC 5545 has no BYMONTHDAY for weekly rules, and we only follow it monthly
+        if freq != Freq::Monthly && !by_month_day.is_empty() {
+            return None;
+        }
+        let interval = match parts.get("INTERVAL") {
+            Some(interval) => interval.parse().ok().filter(|n| *n > 0)?,
+            None => 1,
+        };
+        let count = match parts.get("COUNT") {
+            Some(count) => Some(count.parse().ok()?),
+            None => None,
+        };
+        let until = match parts.get("UNTIL") {
+            Some(until) => Some(ics::parse_time(until)?),
+            None => None,
+        };
+        let week_start = match parts.get("WKST") {
+            Some(day) => match parse_byday(day)? {
+                (None, weekday) => weekday,
+                (Some(_), _) => return None,
+            },
+            None => time::MONDAY,
+        };
+        Some(Self {
+            freq,
+            interval,
+            by_day,
+            by_month_day,
+            count,
+            until,
+            week_start,
+        })
+    }
+
+    // Starts of the occurrences in a period, in order, and when the period begins.
+    // Nothing in it starts before it begins.
+    fn period(&self, start: u64, period: u64) -> (u64, Vec<u64>) {
+        let f = time::fields(start);
+        let step = (period * self.interval as u64) as i32;
+        let at = |year: i32, month: i32, day: i32| {
+            time::from_fields(
+                year,
+                month,
+                day,
+                f.hour as i32,
+                f.minute as i32,
+                f.second as i32,
+            )
+        };
+        let (begins, mut starts) = match self.freq {
+            Freq::Daily => {
+                let ts = at(f.year, f.month as i32, f.day as i32 + step);
+                let d = time::fields(ts);
+                let by_day = self.by_day.is_empty()
+                    || self.by_day.iter().any(|(_, weekday)| *weekday == d.weekday);
+                let starts = if by_day { vec![ts] } else { Vec::new() };
+                (time::start_of_day(ts), starts)
+            }
+            Freq::Weekly => {
+                let week = time::add_days(time::start_of_week(start, self.week_start), 7 * step);
+                let w = time::fields(week);
+                let weekdays: Vec<u32> = if self.by_day.is_empty() {
+                    vec![f.weekday]
+                } else {
+                    self.by_day.iter().map(|(_, weekday)| *weekday).collect()
+                };
+                let starts = weekdays
+                    .into_iter()
+                    .map(|weekday| {
+                        let offset = (weekday + 7 - self.week_start % 7) % 7;
+                        at(w.year, w.month as i32, w.day as i32 + offset as i32)
+                    })
+                    .collect();
+                (week, starts)
+            }
+            Freq::Monthly => {
+                let month = time::from_fields(f.year, f.month as i32 + step, 1, 0, 0, 0);
+                let m = time::fields(month);
+                let (year, number) = (m.year, m.month as i32);
+                let last = last_day(year, number);
+                let weekdays: Vec<i32> = self
+                    .by_day
+                    .iter()
+                    .flat_map(|(nth, weekday)| match nth {
+                        Some(nth) => vec![nth_weekday(year, number, *nth, *weekday)],
+                        // Every one of that weekday in the month
+                        None => (1..=5)
+                            .map(|nth| nth_weekday(year, number, nth, *weekday))
+                            .collect(),
+                    })
+                    .filter(|day| (1..=last).contains(day))
+                    .collect();
+                let days: Vec<i32> = if !self.by_month_day.is_empty() {
+                    // With both, a day has to be in each, like Friday the 13th
+                    self.by_month_day
+                        .iter()
+                        .filter_map(|n| month_day(year, number, *n))
+                        .filter(|day| self.by_day.is_empty() || weekdays.contains(day))
+                        .collect()
+                } else if !self.by_day.is_empty() {
+                    weekdays
+                } else {
+                    // Months without the day are skipped, like RFC 5545 says
+                    month_day(year, number, f.day as i32).into_iter().collect()
+                };
+                let starts = days.into_iter().map(|day| at(year, number, day)).collect();
+                (month, starts)
+            }
+        };
+        starts.sort_unstable();
+        starts.dedup();
+        (begins, starts)
+    }
+
+    // Call visit with each occurrence start of a series starting at start, in order,
+    // until it returns false, the rule ends or the periods begin at horizon.
+    // Rules without COUNT skip ahead to periods that may begin after skip_to.
+    fn each(&self, start: u64, skip_to: u64, horizon: u64, mut visit: impl FnMut(u64) -> bool) {
+        // The start is the first occurrence, whether or not the rule makes it
+        if !visit(start) {
+            return;
+        }
+        let mut seen = 1;
+        let mut first = 0;
+        if self.count.is_none() && skip_to > start {
+            let length = self.freq.max_length() * self.interval as u64;
+            first = ((skip_to - start) / length).saturating_sub(1);
+        }
+        for period in first..first + PERIODS_MAX {
+            let (begins, starts) = self.period(start, period);
+            if begins >= horizon || self.until.is_some_and(|until| begins > until) {
+                return;
+            }
+            for at in starts.into_iter().filter(|at| *at > start) {
+                if self.until.is_some_and(|until| at > until)
+                    || self.count.is_some_and(|count| seen >= count)
+                {
+                    return;
+                }
+                seen += 1;
+                if !visit(at) {
+                    return;
+                }
+            }
+        }
+    }
+
+    // Starts of the occurrences overlapping from to to.
+    // The series starts at start, and each occurrence lasts length seconds.
+    pub fn occurrences(
+        &self,
+        start: u64,
+        length: u64,
+        exdates: &[u64],
+        from: u64,
+        to: u64,
+    ) -> Vec<u64> {
+        let mut found = Vec::new();
+        self.each(start, from.saturating_sub(length), to, |at| {
+            if at >= to {
+                return false;
+            }
+            if at + length > from && !exdates.contains(&at) {
+                found.push(at);
+            }
+            true
+        });
+        found
+    }
+
+    // How many occurrences start before at, for splitting a counted series
+    pub fn count_before(&self, start: u64, at: u64) -> u32 {
+        let mut count = 0;
+        self.each(start, 0, at, |occurrence| {
+            if occurrence >= at {
+                return false;
+            }
+            count += 1;
+            true
+        });
+        count
+    }
+
+    // Said in words, like "Every 2 weeks on Monday, Wednesday, 5 times"
+    pub fn describe(&self) -> String {
+        let unit = match self.freq {
+            Freq::Daily => "day",
+            Freq::Weekly => "week",
+            Freq::Monthly => "month",
+        };
+        let mut text = match self.interval {
+            1 => format!("Every {}", unit),
+            n => format!("Every {} {}s", n, unit),
+        };
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|(nth, weekday)| {
+                    let name = time::WEEKDAYS[*weekday as usize % 7];
+                    match nth {
+                        Some(-1) => format!("the last {}", name),
+                        Some(nth) => format!("the {} {}", ordinal(*nth), name),
+                        None => String::from(name),
+                    }
+                })
+                .collect();
+            text.push_str(&format!(" on {}", days.join(", ")));
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(|n| ordinal(*n)).collect();
+            text.push_str(&format!(" on the {}", days.join(", ")));
+        }
+        if let Some(count) = self.count {
+            text.push_str(&format!(", {} times", count));
+        }
+        if let Some(until) = self.until {
+            text.push_str(&format!(", until {}", time::format_day(until)));
+        }
+        text
+    }
+}
+
+// Written as an RRULE value, UNTIL in UTC
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq.name())?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|(nth, weekday)| {
+                    let nth = nth.map(|nth| nth.to_string()).unwrap_or_default();
+                    format!("{}{}", nth, WEEKDAYS[*weekday as usize % 7])
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(i32::to_string).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", ics::utc_stamp(until))?;
+        }
+        if self.week_start != time::MONDAY {
+            write!(f, ";WKST={}", WEEKDAYS[self.week_start as usize % 7])?;
+        }
+        Ok(())
+    }
+}
+
+// "1st", "2nd", "-1" reads "last"
+fn ordinal(n: i32) -> String {
+    if n == -1 {
+        return String::from("last");
+    }
+    if n < 0 {
+        return format!("{} from last", ordinal(-n));
+    }
+    let suffix = match (n % 10, n % 100) {
+        (1, 11) | (2, 12) | (3, 13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+// Days in a month
+fn last_day(year: i32, month: i32) -> i32 {
+    // Day 0 of the next month is the last day of this one
+    time::fields_utc(time::from_fields_utc(year, month + 1, 0, 0, 0, 0)).day as i32
+}
+
+// A BYMONTHDAY in a month, None if the month is too short
+fn month_day(year: i32, month: i32, n: i32) -> Option<i32> {
+    let last = last_day(year, month);
+    let day = if n > 0 { n } else { last + n + 1 };
+    Some(day).filter(|day| (1..=last).contains(day))
+}
+
+// Day of the month of the nth weekday, negative nth counts from the end.
+// May be outside the month when there is no such weekday.
+pub fn nth_weekday(year: i32, month: i32, nth: i32, weekday: u32) -> i32 {
+    if nth > 0 {
+        let first = time::fields_utc(time::from_fields_utc(year, month, 1, 0, 0, 0)).weekday;
+        1 + ((weekday + 7 - first) % 7) as i32 + (nth - 1) * 7
+    } else {
+        let last = last_day(year, month);
+        let last_weekday =
+            time::fields_utc(time::from_fields_utc(year, month, last, 0, 0, 0)).weekday;
+        last - ((last_weekday + 7 - weekday) % 7) as i32 + (nth + 1) * 7
+    }
+}
+
+// "FREQ=WEEKLY;COUNT=3" into its parts, keys upper cased
+pub fn rule_parts(rule: &str) -> HashMap<String, String> {
+    rule.split(';')
+        .filter_map(|part| part.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_uppercase(), value.trim().to_string()))
+        .collect()
+}
+
+// "-1SU" into which one (None for every one) and the weekday, 0 for Sunday.
+// Which one is 1 to 5 from the start of the month, or from the end when negative.
+pub fn parse_byday(value: &str) -> Option<(Option<i32>, u32)> {
+    let value = value.trim();
+    let split = value.len().checked_sub(2)?;
+    // get is None when split isn't on a char boundary, like in "1é"
+    let (nth, day) = (value.get(..split)?, value.get(split..)?);
+    let weekday = WEEKDAYS
+        .iter()
+        .position(|weekday| day.eq_ignore_ascii_case(weekday))? as u32;
+    let nth = if nth.is_empty() {
+        None
+    } else {
+        let (sign, digits) = match nth.strip_prefix('-') {
+            Some(digits) => (-1, digits),
+            None => (1, nth.strip_prefix('+').unwrap_or(nth)),
+        };
+        // parse would take another sign after the one stripped
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let n: i32 = digits.parse().ok().filter(|n| (1..=5).contains(n))?;
+        Some(sign * n)
+    };
+    Some((nth, weekday))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_write() {
+        for text in [
+            "FREQ=DAILY",
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=5",
+            "FREQ=MONTHLY;BYDAY=-1FR",
+            "FREQ=MONTHLY;BYDAY=FR;BYMONTHDAY=13",
+            "FREQ=MONTHLY;BYMONTHDAY=15,-1;UNTIL=20211231T235959Z",
+            "FREQ=WEEKLY;BYDAY=SU;WKST=SU",
+        ] {
+            let rule = Rule::parse(text).unwrap();
+            assert_eq!(rule.to_string(), text);
+        }
+        let rule = Rule::parse("freq=MONTHLY; byday=+2mo ;UNTIL=20211006T090000Z").unwrap();
+        assert_eq!(rule.by_day, vec![(Some(2), 1)]);
+        assert_eq!(
+            rule.until,
+            Some(time::from_fields_utc(2021, 10, 6, 9, 0, 0))
+        );
+    }
+
+    #[test]
+    fn parse_refuses() {
+        for text in [
+            "",
+            "INTERVAL=2",
+            "FREQ=YEARLY",
+            "FREQ=DAILY;BYHOUR=9",
+            "FREQ=DAILY;BYMONTHDAY=1",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=x",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=-1",
+            "FREQ=DAILY;UNTIL=2021",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=WEEKLY;BYDAY=+2MO",
+            "FREQ=DAILY;BYDAY=-1FR",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=MONTHLY;BYDAY=9MO",
+            "FREQ=MONTHLY;BYDAY=-7MO",
+            "FREQ=MONTHLY;BYDAY=+-2MO",
+            "FREQ=WEEKLY;WKST=1MO",
+            "FREQ=WEEKLY;BYDAY=1é",
+            "FREQ=WEEKLY;BYDAY=é",
+            "FREQ=WEEKLY;WKST=éé",
+        ] {
+            assert_eq!(Rule::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn byday() {
+        assert_eq!(parse_byday("-1SU"), Some((Some(-1), 0)));
+        assert_eq!(parse_byday("+2mo"), Some((Some(2), 1)));
+        assert_eq!(parse_byday(" FR "), Some((None, 5)));
+        assert_eq!(parse_byday("5SA"), Some((Some(5), 6)));
+        assert_eq!(parse_byday("-5SA"), Some((Some(-5), 6)));
+        for bad in [
+            "0MO", "6MO", "9MO", "-7MO", "+-2MO", "-+2MO", "--1MO", "++1MO", "+MO",
+        ] {
+            assert_eq!(parse_byday(bad), None, "{}", bad);
+        }
+        assert_eq!(parse_byday("SU1"), None);
+        assert_eq!(parse_byday("xSU"), None);
+        assert_eq!(parse_byday("S"), None);
+        assert_eq!(parse_byday(""), None);
+        assert_eq!(parse_byday("1é"), None);
+        assert_eq!(parse_byday("éSU"), None);
+    }
+
+    #[test]
+    fn month_days() {
+        assert_eq!(last_day(2021, 2), 28);
+        assert_eq!(last_day(2024, 2), 29);
+        assert_eq!(last_day(2021, 12), 31);
+        assert_eq!(month_day(2021, 2, 30), None);
+        assert_eq!(month_day(2021, 2, -1), Some(28));
+        assert_eq!(month_day(2021, 4, -30), Some(1));
+        assert_eq!(month_day(2021, 4, -31), None);
+        // October 2021 starts on a Friday and ends on a Sunday
+        assert_eq!(nth_weekday(2021, 10, 1, 5), 1);
+        assert_eq!(nth_weekday(2021, 10, 1, 1), 4);
+        assert_eq!(nth_weekday(2021, 10, -1, 0), 31);
+        assert_eq!(nth_weekday(2021, 10, -1, 5), 29);
+        // There is no 5th Monday
+        assert_eq!(nth_weekday(2021, 10, 5, 1), 32);
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(ordinal(1), "1st");
+        assert_eq!(ordinal(2), "2nd");
+        assert_eq!(ordinal(3), "3rd");
+        assert_eq!(ordinal(11), "11th");
+        assert_eq!(ordinal(12), "12th");
+        assert_eq!(ordinal(13), "13th");
+        assert_eq!(ordinal(22), "22nd");
+        assert_eq!(ordinal(-1), "last");
+        assert_eq!(ordinal(-2), "2nd from last");
+        let rule = Rule::parse("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=5").unwrap();
+        assert_eq!(
+            rule.describe(),
+            "Every 2 weeks on Monday, Wednesday, 5 times"
+        );
+        let rule = Rule::parse("FREQ=MONTHLY;BYDAY=-1FR").unwrap();
+        assert_eq!(rule.describe(), "Every month on the last Friday");
+    }
+}
+
+// Expanding rules reads the wall clock, which takes the browser's Intl
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn utc(year: i32, month: i32, day: i32, hour: i32) -> u64 {
+        time::from_fields_utc(year, month, day, hour, 0, 0)
+    }
+
+    // Every occurrence of rule starting at start, in a year from it
+    fn expand(rule: &str, start: u64) -> Vec<u64> {
+        time::set_zone(Some("UTC"));
+        let rule = Rule::parse(rule).unwrap();
+        rule.occurrences(start, time::HOUR, &[], start, start + 366 * time::DAY)
+    }
+
+    #[wasm_bindgen_test]
+    fn weekly_byday_count() {
+        // A Monday
+        let start = utc(2021, 10, 4, 9);
+        assert_eq!(
+            expand("FREQ=WEEKLY;BYDAY=MO,WE,FR;COUNT=5", start),
+            vec![
+                start,
+                utc(2021, 10, 6, 9),
+                utc(2021, 10, 8, 9),
+                utc(2021, 10, 11, 9),
+                utc(2021, 10, 13, 9),
+            ]
+        );
+        assert_eq!(
+            expand("FREQ=WEEKLY;INTERVAL=2;COUNT=3", start),
+            vec![start, utc(2021, 10, 18, 9), utc(2021, 11, 1, 9)]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn until_is_inclusive() {
+        let start = utc(2021, 10, 4, 9);
+        assert_eq!(
+            expand("FREQ=DAILY;UNTIL=20211006T090000Z", start),
+            vec![start, utc(2021, 10, 5, 9), utc(2021, 10, 6, 9)]
+        );
+        assert_eq!(
+            expand("FREQ=DAILY;UNTIL=20211006T085959Z", start),
+            vec![start, utc(2021, 10, 5, 9)]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn monthly() {
+        // Months without a 31st are skipped
+        assert_eq!(
+            expand("FREQ=MONTHLY;COUNT=4", utc(2021, 1, 31, 9)),
+            vec![
+                utc(2021, 1, 31, 9),
+                utc(2021, 3, 31, 9),
+                utc(2021, 5, 31, 9),
+                utc(2021, 7, 31, 9),
+            ]
+        );
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3", utc(2021, 10, 29, 9)),
+            vec![
+                utc(2021, 10, 29, 9),
+                utc(2021, 11, 26, 9),
+                utc(2021, 12, 31, 9),
+            ]
+        );
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYMONTHDAY=1,-1;COUNT=4", utc(2021, 1, 1, 9)),
+            vec![
+                utc(2021, 1, 1, 9),
+                utc(2021, 1, 31, 9),
+                utc(2021, 2, 1, 9),
+                utc(2021, 2, 28, 9),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn byday_and_bymonthday_intersect() {
+        // Friday the 13th
+        let start = utc(2021, 8, 13, 9);
+        time::set_zone(Some("UTC"));
+        let rule = Rule::parse("FREQ=MONTHLY;BYDAY=FR;BYMONTHDAY=13").unwrap();
+        assert_eq!(
+            rule.occurrences(start, time::HOUR, &[], start, utc(2023, 2, 1, 0)),
+            vec![start, utc(2022, 5, 13, 9), utc(2023, 1, 13, 9)]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn range_and_exdates() {
+        time::set_zone(Some("UTC"));
+        let start = utc(2021, 10, 4, 9);
+        let rule = Rule::parse("FREQ=DAILY").unwrap();
+        // Two hour events overlap a range starting an hour into them
+        let found = rule.occurrences(
+            start,
+            2 * time::HOUR,
+            &[utc(2021, 10, 6, 9)],
+            utc(2021, 10, 5, 10),
+            utc(2021, 10, 8, 0),
+        );
+        assert_eq!(found, vec![utc(2021, 10, 5, 9), utc(2021, 10, 7, 9)]);
+        // Far from the start, without walking every day in between
+        let found = rule.occurrences(
+            start,
+            time::HOUR,
+            &[],
+            utc(2031, 1, 1, 0),
+            utc(2031, 1, 3, 0),
+        );
+        assert_eq!(found, vec![utc(2031, 1, 1, 9), utc(2031, 1, 2, 9)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn counts_before() {
+        time::set_zone(Some("UTC"));
+        let start = utc(2021, 10, 4, 9);
+        let rule = Rule::parse("FREQ=WEEKLY;BYDAY=MO,WE,FR;COUNT=10").unwrap();
+        assert_eq!(rule.count_before(start, start), 0);
+        assert_eq!(rule.count_before(start, utc(2021, 10, 13, 9)), 4);
+        assert_eq!(rule.count_before(start, utc(2030, 1, 1, 0)), 10);
+    }
+
+    #[wasm_bindgen_test]
+    fn keeps_wall_clock_time() {
+        // Daylight saving ends on November 7th 2021 in New York
+        time::set_zone(Some("America/New_York"));
+        let start = utc(2021, 11, 5, 13);
+        let rule = Rule::parse("FREQ=DAILY").unwrap();
+        let found = rule.occurrences(start, time::HOUR, &[], start, start + 4 * time::DAY);
+        time::set_zone(None);
+        assert_eq!(
+            found,
+            vec![
+                start,
+                utc(2021, 11, 6, 13),
+                utc(2021, 11, 7, 14),
+                utc(2021, 11, 8, 14),
+            ]
+        );
+    }
+}
@@ -1,79 +1,126 @@
+use crate::api::{self, ApiError, Availability, Client, EmailRequest, Throttled, UsernameRequest};
 use crate::app::AppRoute;
-use crate::consts;
+use crate::breach;
+use crate::config;
+use crate::form::{FieldEvent, Form, Validator, Verdict};
+use crate::lockout::{self, Countdown};
 use crate::nav::Nav;
+use crate::session;
+use crate::strength::StrengthMeter;
+use crate::timers;
+use crate::toast;
 use crate::util;
-use email_address_parser::EmailAddress;
-use gloo::storage::{SessionStorage, Storage};
+use js_sys::Math;
 use nittei_common::auth::{RegisterRequest, RegisterResponse};
-use passwords::{analyzer, scorer};
-use reqwasm::http::Request;
-use web_sys::{HtmlInputElement, KeyboardEvent, MouseEvent};
+use web_sys::{HtmlInputElement, MouseEvent};
 use yew::prelude::*;
 
-#[derive(Clone)]
-pub enum RegisterMsg {
-    Register,
-    EmailTyped,
-    UserTyped,
-    PasswordTyped,
-    Password2Typed,
-    Disconnected,
-    Failed,
-    RegisterRecieved(RegisterResponse),
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegisterField {
+    Email,
+    Username,
+    Password,
+    Password2,
 }
 
-#[derive(Default)]
-struct RegisterState {
-    bademail: bool,
-    baduser: bool,
-    shortpassword: bool,
-    longpassword: bool,
-    badpassword: bool,
-    mismatched_password: bool,
-    disconnected: bool,
-    server_error: bool,
-    user_taken: bool,
-    email_taken: bool,
+#[derive(Clone, Debug)]
+pub enum RegisterMsg {
+    Register,
+    Field(FieldEvent<RegisterField>),
+    Checked(RegisterField, String, Verdict),
+    Error(ApiError),
+    // A lockout ran out
+    Unlocked,
+    RegisterRecieved(Throttled<RegisterResponse>),
 }
 
 pub struct Register {
-    emailref: NodeRef,
-    userref: NodeRef,
-    passref: NodeRef,
-    pass2ref: NodeRef,
+    form: Form<RegisterField>,
+    // Error about the whole form rather than one field
+    failure: Option<String>,
+    // Locked out until then, in milliseconds since the epoch
+    locked_until: Option<f64>,
     rememberref: NodeRef,
-    state: RegisterState,
 }
 
 async fn register_request(email: String, username: String, password: String) -> RegisterMsg {
-    let ron_request = RegisterRequest {
+    let req = RegisterRequest {
         email,
         username,
         password,
     };
-    let ron_request = ron::to_string(&ron_request).expect("Should serialize");
-    let req = Request::post(&format!("{}/auth/register", consts::URL))
-        .header("Content-Type", "application/x-register-request")
-        .body(&ron_request);
-    let resp = req.send().await;
-    if resp.is_err() {
-        return RegisterMsg::Disconnected;
+    match Client::anonymous().register(&req).await {
+        Ok(resp) => RegisterMsg::RegisterRecieved(resp),
+        Err(e) => RegisterMsg::Error(e),
+    }
+}
+
+// How many other usernames to suggest when one is taken
+const SUGGESTIONS: usize = 3;
+
+async fn username_available(username: &str) -> Option<Availability> {
+    let req = UsernameRequest {
+        username: String::from(username),
+    };
+    Client::anonymous().username_available(&req).await.ok()
+}
+
+// Ask the server if a username is free, with some that are if it isn't
+async fn username_verdict(username: String) -> Verdict {
+    match username_available(&username).await {
+        Some(Availability::Available) => Verdict::ok("Available!"),
+        Some(Availability::Taken) => Verdict {
+            suggestions: suggest_usernames(&username).await,
+            ..Verdict::error("Username taken!")
+        },
+        Some(Availability::Invalid) => Verdict::error("Invalid username!"),
+        // Registering checks again anyway
+        None => Verdict::unknown(),
     }
-    let resp = resp.unwrap();
-    if resp.status() != 200 {
-        return RegisterMsg::Failed;
+}
+
+// Variations on a taken username that are still free
+async fn suggest_usernames(username: &str) -> Vec<String> {
+    // Leave room for the suffix
+    let base: String = username.chars().take(util::USERNAME_MAX - 4).collect();
+    let number = |max: f64| (Math::random() * max) as u32 + 1;
+    let candidates = [
+        format!("{}{}", base, number(99.0)),
+        format!("{}_{}", base, number(99.0)),
+        format!("{}{}", base, number(999.0)),
+        format!("{}-{}", base, number(999.0)),
+        format!("{}{}", base, number(9999.0)),
+    ];
+
+    let mut suggestions = Vec::new();
+    for candidate in candidates {
+        if suggestions.len() == SUGGESTIONS {
+            break;
+        }
+        if username_available(&candidate).await == Some(Availability::Available) {
+            suggestions.push(candidate);
+        }
     }
+    suggestions
+}
 
-    let resp = resp.text().await;
-    if resp.is_err() {
-        return RegisterMsg::Failed;
+// Ask the server if an email address already has an account
+async fn email_verdict(email: String) -> Verdict {
+    let req = EmailRequest { email };
+    match Client::anonymous().email_available(&req).await {
+        Ok(Availability::Available) => Verdict::unknown(),
+        Ok(Availability::Taken) => Verdict::error("Email taken!"),
+        Ok(Availability::Invalid) => Verdict::error("Invalid email address!"),
+        Err(_) => Verdict::unknown(),
     }
+}
 
-    let resp = ron::from_str::<RegisterResponse>(&resp.unwrap());
-    if resp.is_err() {
-        RegisterMsg::Failed
-    } else {
-        RegisterMsg::RegisterRecieved(resp.unwrap())
+// Look the password up in known breaches
+async fn password_verdict(password: String) -> Verdict {
+    match breach::check(&password).await.message() {
+        Some(message) if config::get().breach_block => Verdict::error(&message),
+        Some(message) => Verdict::warning(&message),
+        None => Verdict::unknown(),
     }
 }
 
@@ -82,279 +129,156 @@ impl Component for Register {
     type Properties = ();
 
     fn create(_ctx: &Context<Self>) -> Self {
+        let form = Form::new()
+            .text(
+                RegisterField::Email,
+                "email",
+                "Email",
+                vec![Validator::Required, Validator::Email],
+            )
+            .text(
+                RegisterField::Username,
+                "username",
+                "Username",
+                vec![Validator::Username],
+            )
+            .password(
+                RegisterField::Password,
+                "password",
+                "Password",
+                vec![Validator::Password],
+            )
+            .password(
+                RegisterField::Password2,
+                "password2",
+                "Re-enter password",
+                vec![Validator::MustMatch(
+                    RegisterField::Password,
+                    "Mismatched passwords!",
+                )],
+            );
         Self {
-            emailref: NodeRef::default(),
-            userref: NodeRef::default(),
-            passref: NodeRef::default(),
-            pass2ref: NodeRef::default(),
+            form,
+            failure: None,
+            locked_until: None,
             rememberref: NodeRef::default(),
-            state: RegisterState::default(),
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            RegisterMsg::Register => {
-                if self.state.bademail
-                    || self.state.baduser
-                    || self.state.badpassword
-                    || self.state.mismatched_password
-                    || self.state.shortpassword
-                {
-                    return false;
-                }
-
-                let emailbox = self.emailref.cast::<HtmlInputElement>();
-                let userbox = self.userref.cast::<HtmlInputElement>();
-                let passbox = self.passref.cast::<HtmlInputElement>();
-                let pass2box = self.pass2ref.cast::<HtmlInputElement>();
-                if emailbox.is_none()
-                    || userbox.is_none()
-                    || passbox.is_none()
-                    || pass2box.is_none()
-                {
-                    return false;
-                }
-
-                let mut fail = false;
-
-                let email = emailbox.unwrap().value();
-                if email.is_empty() || !EmailAddress::is_valid(&email, None) {
-                    self.state.bademail = true;
-                    fail = true;
-                }
-
-                let user = userbox.unwrap().value();
-                if user.is_empty() || user.len() > 30 {
-                    self.state.baduser = true;
-                    fail = true;
-                }
-
-                let password = passbox.unwrap().value();
-                if password.len() < 8 {
-                    self.state.shortpassword = true;
-                    fail = true;
-                }
-
-                let analyzed = analyzer::analyze(&password);
-                let scored = scorer::score(&analyzed);
-
-                if scored < 70.0 {
-                    self.state.badpassword = true;
-                    fail = true;
-                }
-
-                let password2 = pass2box.unwrap().value();
-                if password2 != password {
-                    self.state.mismatched_password = true;
-                    fail = true;
-                }
-
-                if fail {
-                    return fail;
-                }
-
-                ctx.link()
-                    .send_future(async move { register_request(email, user, password).await });
-
-                false
-            }
-            RegisterMsg::EmailTyped => {
-                let emailbox = self.emailref.cast::<HtmlInputElement>();
-                if emailbox.is_none() {
-                    return false;
-                }
-                let email = emailbox.unwrap().value();
-                if email.is_empty() || !EmailAddress::is_valid(&email, None) {
-                    let laststate = self.state.bademail;
-                    self.state.bademail = true;
-                    !laststate
-                } else {
-                    let laststate = self.state.bademail;
-                    self.state.bademail = false;
-                    laststate
+            RegisterMsg::Field(event) => {
+                let changed = self.form.event(event);
+                // Look up availability as the user types
+                match event {
+                    FieldEvent::Input(RegisterField::Username)
+                    | FieldEvent::Blur(RegisterField::Username) => self.form.check_remote(
+                        ctx.link(),
+                        RegisterField::Username,
+                        username_verdict,
+                        RegisterMsg::Checked,
+                    ),
+                    FieldEvent::Input(RegisterField::Email)
+                    | FieldEvent::Blur(RegisterField::Email) => self.form.check_remote(
+                        ctx.link(),
+                        RegisterField::Email,
+                        email_verdict,
+                        RegisterMsg::Checked,
+                    ),
+                    // The strength meter follows every keystroke
+                    FieldEvent::Input(RegisterField::Password) => {
+                        if !config::get().breach_url.is_empty() {
+                            self.form.check_remote(
+                                ctx.link(),
+                                RegisterField::Password,
+                                password_verdict,
+                                RegisterMsg::Checked,
+                            );
+                        }
+                    }
+                    _ => return changed,
                 }
+                true
             }
-            RegisterMsg::UserTyped => {
-                let userbox = self.userref.cast::<HtmlInputElement>();
-                if userbox.is_none() {
-                    return false;
-                }
-                let userbox = userbox.unwrap();
-                let username = userbox.value();
-                if username.is_empty() || username.len() > 30 {
-                    let laststate = self.state.baduser;
-                    self.state.baduser = true;
-                    !laststate
-                } else {
-                    let laststate = self.state.baduser;
-                    self.state.baduser = false;
-                    laststate
-                }
+            RegisterMsg::Checked(field, value, verdict) => {
+                self.form.remote_result(field, value, verdict)
             }
-            RegisterMsg::PasswordTyped => {
-                let passbox = self.passref.cast::<HtmlInputElement>();
-                let pass2box = self.pass2ref.cast::<HtmlInputElement>();
-                if passbox.is_none() || pass2box.is_none() {
+            RegisterMsg::Register => {
+                if !self.form.can_submit() || self.locked_until.is_some() {
                     return false;
                 }
-                let password = passbox.unwrap().value();
-                let password2 = pass2box.unwrap().value();
-
-                let mut mismatched_render = false;
-                if password != password2 {
-                    let laststate = self.state.mismatched_password;
-                    self.state.mismatched_password = true;
-                    if !laststate {
-                        mismatched_render = true;
-                    }
-                } else {
-                    let laststate = self.state.mismatched_password;
-                    self.state.mismatched_password = false;
-                    if laststate {
-                        mismatched_render = true;
-                    }
-                }
-
-                if password.len() < 8 {
-                    let laststate = self.state.shortpassword;
-                    self.state.shortpassword = true;
-                    self.state.longpassword = false;
-                    return !laststate;
-                } else {
-                    let laststate = self.state.shortpassword;
-                    self.state.shortpassword = false;
-                    if laststate {
-                        mismatched_render = true;
-                    }
-                }
-
-                if password.len() > 30 {
-                    let laststate = self.state.longpassword;
-                    self.state.longpassword = true;
-                    if !laststate {
-                        return true;
-                    } else {
-                        return mismatched_render;
-                    }
-                } else {
-                    let laststate = self.state.longpassword;
-                    self.state.longpassword = false;
-                    if laststate {
-                        mismatched_render = true;
-                    }
+                self.failure = None;
+                if !self.form.validate() {
+                    return true;
                 }
 
-                let analyzed = analyzer::analyze(&password);
-                let scored = scorer::score(&analyzed);
-
-                if scored < 70.0 {
-                    let laststate = self.state.badpassword;
-                    self.state.badpassword = true;
-                    if !laststate {
-                        true
-                    } else {
-                        mismatched_render
-                    }
-                } else {
-                    let laststate = self.state.badpassword;
-                    self.state.badpassword = false;
-                    if laststate {
-                        true
-                    } else {
-                        mismatched_render
-                    }
-                }
+                let email = self.form.value(RegisterField::Email);
+                let user = self.form.value(RegisterField::Username);
+                let password = self.form.value(RegisterField::Password);
+                ctx.link()
+                    .send_future(async move { register_request(email, user, password).await });
+                self.form.set_submitting(true);
+                true
             }
-            RegisterMsg::Password2Typed => {
-                let passbox = self.passref.cast::<HtmlInputElement>();
-                let pass2box = self.pass2ref.cast::<HtmlInputElement>();
-                if passbox.is_none() || pass2box.is_none() {
-                    return false;
+            RegisterMsg::Error(e) => {
+                match lockout::from_error(&e) {
+                    Some(until) => self.locked_until = Some(until),
+                    None => toast::api_error(&e),
                 }
-                let password = passbox.unwrap().value();
-                let password2 = pass2box.unwrap().value();
-
-                if password != password2 {
-                    let laststate = self.state.mismatched_password;
-                    self.state.mismatched_password = true;
-                    !laststate
-                } else {
-                    let laststate = self.state.mismatched_password;
-                    self.state.mismatched_password = false;
-                    laststate
-                }
-            }
-            RegisterMsg::Failed => {
-                self.state.server_error = true;
+                self.form.set_submitting(false);
                 true
             }
-            RegisterMsg::Disconnected => {
-                self.state.disconnected = true;
+            RegisterMsg::Unlocked => {
+                self.locked_until = None;
                 true
             }
             RegisterMsg::RegisterRecieved(resp) => {
-                self.state.server_error = false;
-                self.state.disconnected = false;
-                self.state.email_taken = false;
-                self.state.user_taken = false;
-                self.state.baduser = false;
-                self.state.badpassword = false;
-                self.state.bademail = false;
-
-                match resp {
-                    RegisterResponse::UsernameTaken => {
-                        self.state.user_taken = true;
-                        true
-                    }
+                self.form.set_submitting(false);
+                match resp.body {
+                    RegisterResponse::UsernameTaken => self
+                        .form
+                        .set_error(RegisterField::Username, "Username taken!"),
                     RegisterResponse::EmailTaken => {
-                        self.state.email_taken = true;
-                        true
+                        self.form.set_error(RegisterField::Email, "Email taken!")
                     }
                     RegisterResponse::Lockout => {
-                        self.state.server_error = true;
-                        true
+                        self.locked_until = Some(lockout::until(resp.retry_after))
                     }
                     RegisterResponse::InvalidRequest => {
-                        self.state.server_error = true;
-                        true
-                    }
-                    RegisterResponse::InvalidUsername => {
-                        self.state.baduser = true;
-                        true
-                    }
-                    RegisterResponse::WeakPassword => {
-                        self.state.badpassword = true;
-                        true
-                    }
-                    RegisterResponse::InvalidEmail => {
-                        self.state.bademail = true;
-                        true
+                        self.failure = Some(String::from(api::REJECTED))
                     }
+                    RegisterResponse::InvalidUsername => self
+                        .form
+                        .set_error(RegisterField::Username, "Invalid username!"),
+                    RegisterResponse::WeakPassword => self
+                        .form
+                        .set_error(RegisterField::Password, "Password too weak!"),
+                    RegisterResponse::InvalidEmail => self
+                        .form
+                        .set_error(RegisterField::Email, "Invalid email address!"),
                     RegisterResponse::Success(token, claim) => {
-                        if SessionStorage::set("session_key", token).is_err()
-                            || SessionStorage::set("session", claim).is_err()
-                        {
-                            self.state.server_error = true;
-                        } else {
-                            let checkbox = self.rememberref.cast::<HtmlInputElement>();
-                            let user = self.userref.cast::<HtmlInputElement>();
-                            let pass = self.passref.cast::<HtmlInputElement>();
-
-                            if let Some(user) = user {
-                                if let Some(pass) = pass {
-                                    if let Some(check) = checkbox {
-                                        if check.checked() {
-                                            util::request_persistence(user.value(), pass.value());
-                                        }
-                                    }
-                                }
-                            }
-                            yew_router::replace_route(AppRoute::Home);
+                        if session::login(token, claim).is_err() {
+                            self.failure =
+                                Some(String::from("Something went wrong. Please try again."));
+                            return true;
+                        }
+                        timers::session_refresh();
+                        let remember = self
+                            .rememberref
+                            .cast::<HtmlInputElement>()
+                            .map(|check| check.checked())
+                            .unwrap_or(false);
+                        if remember && config::get().remember_me {
+                            util::request_persistence(
+                                self.form.value(RegisterField::Email),
+                                self.form.value(RegisterField::Password),
+                            );
                         }
-                        false
+                        yew_router::replace_route(AppRoute::Feed);
+                        return false;
                     }
                 }
+                true
             }
         }
     }
@@ -364,54 +288,37 @@ impl Component for Register {
             e.prevent_default();
             RegisterMsg::Register
         });
-
-        let email_cb = ctx
-            .link()
-            .callback(|_: KeyboardEvent| RegisterMsg::EmailTyped);
-        let user_cb = ctx
-            .link()
-            .callback(|_: KeyboardEvent| RegisterMsg::UserTyped);
-        let pass_cb = ctx
-            .link()
-            .callback(|_: KeyboardEvent| RegisterMsg::PasswordTyped);
-        let pass2_cb = ctx
-            .link()
-            .callback(|_: KeyboardEvent| RegisterMsg::Password2Typed);
-
-        let failure_text = if self.state.disconnected {
-            String::from("Disconnected from network")
-        } else if self.state.server_error {
-            String::from("Internal Server Error")
-        } else {
-            String::new()
+        let field = |key, id| {
+            self.form
+                .view_field(ctx.link(), key, id, RegisterMsg::Field)
+        };
+        let failure_text = self.failure.clone().unwrap_or_default();
+        let countdown = match self.locked_until {
+            Some(until) => {
+                let onelapsed = ctx.link().callback(|_| RegisterMsg::Unlocked);
+                html! { <Countdown until={until} onelapsed={onelapsed} /> }
+            }
+            None => html! {},
         };
         html! {
             <>
                 <Nav route={AppRoute::Register} />
                 <main id="register" class="content">
                     <form id="registerform">
-                        <label for="emailbox">{ "Email" }</label>
-                        <input type="text" id="emailbox" name="email" ref={self.emailref.clone()} onkeyup={email_cb} />
-                        <p class="failuretext" style={if self.state.bademail { "" } else { "display: none;"}}>{ "Invaild email address!" }</p>
-                        <p class="failuretext" style={if self.state.email_taken { "" } else { "display: none;" }}>{ "Email taken!" }</p>
-                        <label for="unamebox">{ "Username" }</label>
-                        <input type="text" id="unamebox" name="username" ref={self.userref.clone()} onkeyup={user_cb} />
-                        <p class="failuretext" style={if self.state.baduser { "" } else { "display: none;" }}>{ "Invalid username!" }</p>
-                        <p class="failuretext" style={if self.state.user_taken { "" } else { "display: none;" }}>{ "Username taken!" }</p>
-                        <label for="passbox">{ "Password" }</label>
-                        <input type="password" id="passbox" name="password" ref={self.passref.clone()} onkeyup={pass_cb} />
-                        <p class="failuretext" style={if self.state.shortpassword { "" } else { "display: none;" }}>{ "Password must be at least 8 characters." }</p>
-                        <p class="failuretext" style={if self.state.longpassword { "" } else { "display: none;" }}>{ "Password must be no longer than 30 characters." }</p>
-                        <p class="failuretext" style={if self.state.badpassword { "" } else { "display: none;" }}>{ "Password too weak!" }</p>
-                        <label for="passbox2">{ "Re-enter password" }</label>
-                        <input type="password" id="passbox2" name="password2" ref={self.pass2ref.clone()} onkeyup={pass2_cb} />
-                        <p class="failuretext" style={if self.state.mismatched_password { "" } else { "display: none;" }}>{ "Mismatched passwords!" }</p>
-                        <div id="register-remember">
+                        { field(RegisterField::Email, "emailbox") }
+                        { field(RegisterField::Username, "unamebox") }
+                        { field(RegisterField::Password, "passbox") }
+                        <StrengthMeter password={self.form.value(RegisterField::Password)} />
+                        { field(RegisterField::Password2, "passbox2") }
+                        <div id="register-remember" style={if config::get().remember_me { "" } else { "display: none;" }}>
                             <input type="checkbox" id="rememberme-register" name="rememberme" ref={self.rememberref.clone()} />
                             <label for="rememberme-register">{ "Remember Me" }</label>
                         </div>
-                        <button id="registersubmit" type="submit" onclick={cb}>{ "Register" }</button>
+                        <button id="registersubmit" type="submit" onclick={cb} disabled={!self.form.can_submit() || self.locked_until.is_some()}>
+                            { if self.form.submitting() { "Registering..." } else { "Register" } }
+                        </button>
                     </form>
+                    { countdown }
                     <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>{ failure_text }</p>
                 </main>
             </>
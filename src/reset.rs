@@ -0,0 +1,227 @@
+use crate::api::{self, ApiResult, Client, ResetRequest, ResetResponse};
+use crate::app::AppRoute;
+use crate::lockout::{self, Countdown};
+use crate::nav::{Anchor, Nav};
+use crate::strength::StrengthMeter;
+use crate::toast;
+use crate::util::{self, PasswordProblem};
+use web_sys::{HtmlInputElement, KeyboardEvent, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum ResetMsg {
+    Submit,
+    PasswordTyped,
+    Recieved(ApiResult<ResetResponse>),
+    // A lockout ran out
+    Unlocked,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ResetState {
+    New,
+    Resetting,
+    ExpiredCode,
+    BadCode,
+    UsedCode,
+    Rejected,
+    Done,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Properties)]
+pub struct ResetProps {
+    pub code: u128,
+}
+
+// Page to set a new password from a reset email
+pub struct Reset {
+    state: ResetState,
+    code: u128,
+    problem: Option<PasswordProblem>,
+    mismatched_password: bool,
+    // Rate limited until then, in milliseconds since the epoch
+    locked_until: Option<f64>,
+    passref: NodeRef,
+    pass2ref: NodeRef,
+}
+
+impl Reset {
+    // Read both password boxes
+    fn passwords(&self) -> Option<(String, String)> {
+        let passbox = self.passref.cast::<HtmlInputElement>()?;
+        let pass2box = self.pass2ref.cast::<HtmlInputElement>()?;
+        Some((passbox.value(), pass2box.value()))
+    }
+
+    fn form(&self, ctx: &Context<Self>) -> Html {
+        let cb = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            ResetMsg::Submit
+        });
+        let pass_cb = ctx
+            .link()
+            .callback(|_: KeyboardEvent| ResetMsg::PasswordTyped);
+        let pass2_cb = ctx
+            .link()
+            .callback(|_: KeyboardEvent| ResetMsg::PasswordTyped);
+
+        let password = self
+            .passwords()
+            .map(|(password, _)| password)
+            .unwrap_or_default();
+        let problem_text = self
+            .problem
+            .map(|problem| problem.message())
+            .unwrap_or_default();
+        let failure_text = match self.state {
+            ResetState::Rejected => api::REJECTED,
+            _ => "",
+        };
+        let countdown = match self.locked_until {
+            Some(until) => {
+                let onelapsed = ctx.link().callback(|_| ResetMsg::Unlocked);
+                html! { <Countdown until={until} onelapsed={onelapsed} /> }
+            }
+            None => html! {},
+        };
+
+        html! {
+            <>
+                <form id="resetform">
+                    <label for="resetpass">{ "New password" }</label>
+                    <input type="password" id="resetpass" name="password" ref={self.passref.clone()} onkeyup={pass_cb} />
+                    <p class="failuretext" style={if problem_text.is_empty() { "display: none;" } else { "" }}>{ problem_text }</p>
+                    <StrengthMeter password={password} />
+                    <label for="resetpass2">{ "Re-enter password" }</label>
+                    <input type="password" id="resetpass2" name="password2" ref={self.pass2ref.clone()} onkeyup={pass2_cb} />
+                    <p class="failuretext" style={if self.mismatched_password { "" } else { "display: none;" }}>{ "Mismatched passwords!" }</p>
+                    <button id="resetsubmit" type="submit" onclick={cb} disabled={self.state == ResetState::Resetting || self.locked_until.is_some()}>
+                        { "Reset password" }
+                    </button>
+                </form>
+                { countdown }
+                <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>{ failure_text }</p>
+            </>
+        }
+    }
+}
+
+impl Component for Reset {
+    type Message = ResetMsg;
+    type Properties = ResetProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            state: ResetState::New,
+            code: ctx.props().code,
+            problem: None,
+            mismatched_password: false,
+            locked_until: None,
+            passref: NodeRef::default(),
+            pass2ref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ResetMsg::PasswordTyped => {
+                let (password, password2) = match self.passwords() {
+                    Some(passwords) => passwords,
+                    None => return false,
+                };
+                self.problem = util::check_password(&password);
+                self.mismatched_password = password != password2;
+                // The strength meter follows every keystroke
+                true
+            }
+            ResetMsg::Submit => {
+                if self.state == ResetState::Resetting || self.locked_until.is_some() {
+                    return false;
+                }
+                let (password, password2) = match self.passwords() {
+                    Some(passwords) => passwords,
+                    None => return false,
+                };
+                self.problem = util::check_password(&password);
+                self.mismatched_password = password != password2;
+                if self.problem.is_some() || self.mismatched_password {
+                    return true;
+                }
+
+                let req = ResetRequest {
+                    code: self.code,
+                    password,
+                };
+                ctx.link().send_future(async move {
+                    ResetMsg::Recieved(Client::anonymous().reset(&req).await)
+                });
+                self.state = ResetState::Resetting;
+                true
+            }
+            ResetMsg::Recieved(resp) => {
+                self.state = match resp {
+                    Ok(ResetResponse::Success) => ResetState::Done,
+                    Ok(ResetResponse::ExpiredCode) => ResetState::ExpiredCode,
+                    Ok(ResetResponse::BadCode) => ResetState::BadCode,
+                    Ok(ResetResponse::UsedCode) => ResetState::UsedCode,
+                    Ok(ResetResponse::WeakPassword) => {
+                        self.problem = Some(PasswordProblem::Weak);
+                        ResetState::New
+                    }
+                    Ok(ResetResponse::InvalidRequest) => ResetState::Rejected,
+                    Err(e) => {
+                        match lockout::from_error(&e) {
+                            Some(until) => self.locked_until = Some(until),
+                            None => toast::api_error(&e),
+                        }
+                        ResetState::New
+                    }
+                };
+                true
+            }
+            ResetMsg::Unlocked => {
+                self.locked_until = None;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <>
+                <Nav route={AppRoute::Reset { code: self.code }} />
+                <main id="reset" class="content">
+                    {
+                        match self.state {
+                            ResetState::New | ResetState::Resetting | ResetState::Rejected => self.form(ctx),
+                            ResetState::ExpiredCode => html! {
+                                <>
+                                    <p class="failuretext">{ "Reset link expired!" }</p>
+                                    <Anchor route={AppRoute::Forgot}>{ "Send a new link" }</Anchor>
+                                </>
+                            },
+                            ResetState::BadCode => html! {
+                                <>
+                                    <p class="failuretext">{ "Invalid reset link!" }</p>
+                                    <Anchor route={AppRoute::Forgot}>{ "Send a new link" }</Anchor>
+                                </>
+                            },
+                            ResetState::UsedCode => html! {
+                                <>
+                                    <p class="failuretext">{ "This reset link was already used!" }</p>
+                                    <Anchor route={AppRoute::Forgot}>{ "Send a new link" }</Anchor>
+                                </>
+                            },
+                            ResetState::Done => html! {
+                                <>
+                                    <p>{ "Password changed!" }</p>
+                                    <Anchor route={AppRoute::Login}>{ "Log In" }</Anchor>
+                                </>
+                            },
+                        }
+                    }
+                </main>
+            </>
+        }
+    }
+}
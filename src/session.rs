@@ -0,0 +1,214 @@
+use crate::sync::{self, SyncMsg};
+use crate::util;
+use gloo::storage::{self, SessionStorage, Storage};
+use nittei_common::auth::{AuthClaim, AuthToken};
+use std::cell::RefCell;
+use std::rc::Rc;
+use yew::Callback;
+
+// Where the user is in the login process
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SessionStatus {
+    // Nobody is logged in
+    Anonymous,
+    // Trying to get a session (i.e. with a remember me token)
+    Authenticating,
+    // Have a valid session token
+    Authenticated,
+    // Had a session but the server stopped accepting it
+    Expired,
+}
+
+// The current session, as seen by the whole app
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub status: SessionStatus,
+    pub token: Option<AuthToken>,
+    pub claim: Option<AuthClaim>,
+    // Bumped on every change so components can cheaply tell sessions apart
+    version: u32,
+}
+
+// What gets handed down through the yew context
+pub type SessionContext = Rc<Session>;
+
+impl PartialEq for Session {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version && self.status == other.status
+    }
+}
+
+impl Eq for Session {}
+
+impl Session {
+    // Build the session from whatever is in storage
+    fn load() -> Self {
+        let token: storage::Result<AuthToken> = SessionStorage::get("session_key");
+        let claim: storage::Result<AuthClaim> = SessionStorage::get("session");
+        let status = if token.is_ok() {
+            SessionStatus::Authenticated
+        } else if util::remembered() {
+            SessionStatus::Authenticating
+        } else {
+            SessionStatus::Anonymous
+        };
+
+        Self {
+            status,
+            token: token.ok(),
+            claim: claim.ok(),
+            version: 0,
+        }
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.status == SessionStatus::Authenticated
+    }
+
+    pub fn user_id(&self) -> Option<String> {
+        self.claim.as_ref().map(|claim| claim.id.to_string())
+    }
+
+    pub fn username(&self) -> Option<String> {
+        self.claim.as_ref().map(|claim| claim.username.clone())
+    }
+
+    // When the session token expires, in seconds since the epoch
+    pub fn expires(&self) -> Option<u64> {
+        self.claim.as_ref().map(|claim| claim.exp as u64)
+    }
+
+    pub fn verified(&self) -> bool {
+        self.claim
+            .as_ref()
+            .map(|claim| claim.verified)
+            .unwrap_or(false)
+    }
+}
+
+thread_local! {
+    static SESSION: RefCell<Session> = RefCell::new(Session::load());
+    static LISTENER: RefCell<Option<Callback<SessionContext>>> = RefCell::new(None);
+}
+
+// Get a copy of the current session
+pub fn current() -> Session {
+    SESSION.with(|session| session.borrow().clone())
+}
+
+// Register the callback told about every session change.
+// Only app::Main should call this, everything else uses the context it provides.
+pub fn subscribe(callback: Callback<SessionContext>) {
+    LISTENER.with(|listener| *listener.borrow_mut() = Some(callback));
+}
+
+// Change the session and tell the listener about it
+fn update(f: impl FnOnce(&mut Session)) {
+    let session = SESSION.with(|session| {
+        let mut session = session.borrow_mut();
+        f(&mut session);
+        session.version = session.version.wrapping_add(1);
+        session.clone()
+    });
+
+    LISTENER.with(|listener| {
+        if let Some(listener) = listener.borrow().as_ref() {
+            listener.emit(Rc::new(session));
+        }
+    });
+}
+
+// We are trying to get a session
+pub fn authenticating() {
+    update(|session| session.status = SessionStatus::Authenticating);
+}
+
+// Got a brand new session from the server
+pub fn login(token: AuthToken, claim: AuthClaim) -> storage::Result<()> {
+    adopt_login(token.clone(), claim.clone())?;
+    sync::announce(SyncMsg::Login(token, claim));
+    Ok(())
+}
+
+// The session token was renewed
+pub fn renewed(token: AuthToken) -> storage::Result<()> {
+    adopt_renewed(token.clone())?;
+    sync::announce(SyncMsg::Renewed(token));
+    Ok(())
+}
+
+// The server no longer accepts our session
+pub fn expire() {
+    adopt_expired();
+    sync::announce(SyncMsg::Expired);
+}
+
+// Nobody is logged in
+pub fn clear() {
+    adopt_logout();
+    sync::announce(SyncMsg::Logout);
+}
+
+// The adopt_ functions change the session without telling the other tabs,
+// for changes that came from another tab in the first place,
+// or that only this tab has to know about.
+
+pub fn adopt_login(token: AuthToken, claim: AuthClaim) -> storage::Result<()> {
+    SessionStorage::set("session_key", &token)?;
+    SessionStorage::set("session", &claim)?;
+    update(|session| {
+        session.status = SessionStatus::Authenticated;
+        session.token = Some(token);
+        session.claim = Some(claim);
+    });
+    Ok(())
+}
+
+// Read the claim out of a JWT, it is the middle part
+fn decode_claim(token: &AuthToken) -> Option<AuthClaim> {
+    let jwt = token.to_string();
+    let payload = jwt.split('.').nth(1)?;
+    let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    serde_json::from_slice(&payload).ok()
+}
+
+pub fn adopt_renewed(token: AuthToken) -> storage::Result<()> {
+    // The renewed token carries a new expiry time
+    let claim = decode_claim(&token);
+    SessionStorage::set("session_key", &token)?;
+    if let Some(claim) = &claim {
+        SessionStorage::set("session", claim)?;
+    }
+    update(|session| {
+        session.status = SessionStatus::Authenticated;
+        session.token = Some(token);
+        if claim.is_some() {
+            session.claim = claim;
+        }
+    });
+    Ok(())
+}
+
+pub fn adopt_expired() {
+    forget();
+    update(|session| {
+        session.status = SessionStatus::Expired;
+        session.token = None;
+        session.claim = None;
+    });
+}
+
+pub fn adopt_logout() {
+    forget();
+    update(|session| {
+        session.status = SessionStatus::Anonymous;
+        session.token = None;
+        session.claim = None;
+    });
+}
+
+// Remove the session from storage
+fn forget() {
+    SessionStorage::delete("session_key");
+    SessionStorage::delete("session");
+}
@@ -0,0 +1,169 @@
+use crate::config;
+use crate::util;
+use passwords::{analyzer, scorer};
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct StrengthProps {
+    // The password being typed
+    pub password: String,
+}
+
+// What the analyzer found out about a password
+struct Breakdown {
+    score: f64,
+    length: usize,
+    lowercase: bool,
+    uppercase: bool,
+    numbers: bool,
+    symbols: bool,
+    common: bool,
+    // Runs of the same character, like "aaa"
+    repeats: usize,
+    // Runs like "abc" or "123"
+    sequences: usize,
+}
+
+impl Breakdown {
+    fn new(password: &str) -> Self {
+        let analyzed = analyzer::analyze(password);
+        Self {
+            score: scorer::score(&analyzed),
+            length: password.chars().count(),
+            lowercase: analyzed.lowercase_letters_count() > 0,
+            uppercase: analyzed.uppercase_letters_count() > 0,
+            numbers: analyzed.numbers_count() > 0,
+            symbols: analyzed.symbols_count() + analyzed.spaces_count() > 0,
+            common: analyzed.is_common(),
+            repeats: analyzed.consecutive_count(),
+            sequences: analyzed.progressive_count(),
+        }
+    }
+
+    fn classes(&self) -> usize {
+        [self.lowercase, self.uppercase, self.numbers, self.symbols]
+            .iter()
+            .filter(|class| **class)
+            .count()
+    }
+
+    // Word for the score, and the class the meter gets
+    fn rating(&self) -> (&'static str, &'static str) {
+        match self.score {
+            score if score < 40.0 => ("Very weak", "veryweak"),
+            score if score < util::PASSWORD_SCORE => ("Weak", "weak"),
+            score if score < 80.0 => ("Okay", "okay"),
+            score if score < 90.0 => ("Strong", "strong"),
+            _ => ("Very strong", "verystrong"),
+        }
+    }
+
+    // Concrete things to do about it
+    fn suggestions(&self) -> Vec<String> {
+        let config = config::get();
+        let mut suggestions = Vec::new();
+        if self.common {
+            suggestions.push(String::from(
+                "This is one of the most common passwords, pick something else.",
+            ));
+        }
+        if self.length < config.password_min {
+            suggestions.push(format!("Use at least {} characters.", config.password_min));
+        } else if self.length > config.password_max {
+            suggestions.push(format!(
+                "Use no more than {} characters.",
+                config.password_max
+            ));
+        }
+        if self.length < 16 && self.score < 80.0 {
+            suggestions.push(String::from(
+                "Longer is stronger: try a passphrase of a few unrelated words.",
+            ));
+        }
+        if self.classes() < 3 {
+            suggestions.push(String::from("Mix in capital letters, digits or symbols."));
+        }
+        if self.repeats > 0 {
+            suggestions.push(String::from(
+                "Avoid repeating the same character, like \"aaa\".",
+            ));
+        }
+        if self.sequences > 0 {
+            suggestions.push(String::from("Avoid sequences like \"abc\" or \"123\"."));
+        }
+        suggestions
+    }
+}
+
+// Mark a breakdown line good or bad
+fn check(good: bool) -> &'static str {
+    if good {
+        "good"
+    } else {
+        "bad"
+    }
+}
+
+// Meter showing how strong a new password is, what went into that and how to do better.
+// Used everywhere a password is set.
+pub struct StrengthMeter {}
+
+impl Component for StrengthMeter {
+    type Message = ();
+    type Properties = StrengthProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {}
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, _msg: Self::Message) -> bool {
+        false
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let password = &ctx.props().password;
+        if password.is_empty() {
+            return html! {};
+        }
+
+        let config = config::get();
+        let breakdown = Breakdown::new(password);
+        let (rating, class) = breakdown.rating();
+        let length_ok =
+            breakdown.length >= config.password_min && breakdown.length <= config.password_max;
+        let classes = [
+            (breakdown.lowercase, "lowercase"),
+            (breakdown.uppercase, "uppercase"),
+            (breakdown.numbers, "digits"),
+            (breakdown.symbols, "symbols"),
+        ]
+        .iter()
+        .filter(|(found, _)| *found)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+        html! {
+            <div class="strength">
+                <div class="strengthbar">
+                    <div class={format!("strengthfill {}", class)} style={format!("width: {}%;", breakdown.score.min(100.0))}></div>
+                </div>
+                <p class="strengthrating">{ format!("{} ({:.0}/100)", rating, breakdown.score) }</p>
+                <ul class="strengthbreakdown">
+                    <li class={check(length_ok)}>{ format!("Length: {} characters", breakdown.length) }</li>
+                    <li class={check(breakdown.classes() >= 3)}>
+                        { format!("Character types: {} of 4 ({})", breakdown.classes(), classes) }
+                    </li>
+                    <li class={check(!breakdown.common)}>
+                        { if breakdown.common { "Common password: yes" } else { "Common password: no" } }
+                    </li>
+                    <li class={check(breakdown.repeats == 0)}>{ format!("Repeated characters: {}", breakdown.repeats) }</li>
+                    <li class={check(breakdown.sequences == 0)}>{ format!("Sequences: {}", breakdown.sequences) }</li>
+                </ul>
+                <ul class="strengthsuggestions">
+                    { for breakdown.suggestions().into_iter().map(|suggestion| html! { <li>{ suggestion }</li> }) }
+                </ul>
+            </div>
+        }
+    }
+}
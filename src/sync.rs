@@ -0,0 +1,190 @@
+use crate::session;
+use crate::timers;
+use gloo::events::EventListener;
+use gloo::storage::{self, LocalStorage, Storage};
+use gloo::timers::callback::Interval;
+use gloo::timers::future::TimeoutFuture;
+use js_sys::{Date, Math};
+use nittei_common::auth::{AuthClaim, AuthToken};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{BroadcastChannel, MessageEvent};
+
+// Keeps every open tab on the same session.
+// Session changes are sent to the other tabs over a BroadcastChannel, and a lease in
+// LocalStorage picks the one tab that renews the session for everybody.
+
+const CHANNEL: &str = "nittei-session";
+const LEASE_KEY: &str = "renew_leader";
+// How long a lease lasts without being refreshed, in milliseconds
+pub const LEASE_MS: u32 = 30 * 1000;
+// How often the leader refreshes its lease, in milliseconds
+const HEARTBEAT_MS: u32 = 10 * 1000;
+// How long a new tab waits for another tab to share its session, in milliseconds
+const HELLO_WAIT_MS: u32 = 250;
+
+// What tabs tell each other
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum SyncMsg {
+    // A new tab asking for the current session
+    Hello,
+    // Someone logged in
+    Login(AuthToken, AuthClaim),
+    // Someone renewed the session
+    Renewed(AuthToken),
+    // The server stopped accepting the session
+    Expired,
+    // Someone logged out
+    Logout,
+}
+
+// Which tab holds the renewal lease, and until when
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+struct Lease {
+    tab: u64,
+    until: f64,
+}
+
+struct TabSync {
+    channel: BroadcastChannel,
+    _listener: EventListener,
+    _heartbeat: Interval,
+    _unload: EventListener,
+}
+
+thread_local! {
+    static SYNC: RefCell<Option<TabSync>> = RefCell::new(None);
+    static TAB: u64 = (Math::random() * u32::MAX as f64) as u64 ^ Date::now() as u64;
+}
+
+fn tab() -> u64 {
+    TAB.with(|tab| *tab)
+}
+
+// Start listening to the other tabs
+pub fn start() {
+    let channel = match BroadcastChannel::new(CHANNEL) {
+        Ok(channel) => channel,
+        Err(_) => {
+            // Not supported, every tab is on its own
+            console_web::error!("Sync: BroadcastChannel not supported.");
+            return;
+        }
+    };
+
+    let listener = EventListener::new(&channel, "message", |event| {
+        let data = event
+            .dyn_ref::<MessageEvent>()
+            .and_then(|event| event.data().as_string());
+        if let Some(msg) = data.and_then(|data| ron::from_str::<SyncMsg>(&data).ok()) {
+            receive(msg);
+        }
+    });
+
+    // Keep the lease while the tab is open, and give it up when it closes
+    let heartbeat = Interval::new(HEARTBEAT_MS, || {
+        is_leader();
+    });
+    let window = web_sys::window().expect("No window");
+    let unload = EventListener::new(&window, "pagehide", |_| release());
+
+    SYNC.with(|sync| {
+        *sync.borrow_mut() = Some(TabSync {
+            channel,
+            _listener: listener,
+            _heartbeat: heartbeat,
+            _unload: unload,
+        })
+    });
+    is_leader();
+}
+
+// Tell the other tabs something
+pub fn announce(msg: SyncMsg) {
+    let msg = match ron::to_string(&msg) {
+        Ok(msg) => msg,
+        Err(_) => return,
+    };
+    SYNC.with(|sync| {
+        if let Some(sync) = sync.borrow().as_ref() {
+            if sync.channel.post_message(&JsValue::from_str(&msg)).is_err() {
+                console_web::error!("Sync: Failed to post message.");
+            }
+        }
+    });
+}
+
+fn receive(msg: SyncMsg) {
+    match msg {
+        SyncMsg::Hello => {
+            // Share our session with the new tab
+            let current = session::current();
+            if let (Some(token), Some(claim)) = (current.token, current.claim) {
+                announce(SyncMsg::Login(token, claim));
+            }
+        }
+        SyncMsg::Login(token, claim) => {
+            let had_session = session::current().token.is_some();
+            if session::adopt_login(token, claim).is_ok() && !had_session {
+                timers::session_refresh();
+            }
+        }
+        SyncMsg::Renewed(token) => {
+            if session::adopt_renewed(token).is_err() {
+                console_web::error!("Sync: Failed to store renewed session.");
+            }
+        }
+        SyncMsg::Expired => {
+            timers::cancel_session_refresh();
+            session::adopt_expired();
+        }
+        SyncMsg::Logout => {
+            timers::cancel_session_refresh();
+            session::adopt_logout();
+        }
+    }
+}
+
+// Ask the other tabs for their session.
+// Returns true if one of them shared it.
+pub async fn ask_for_session() -> bool {
+    let enabled = SYNC.with(|sync| sync.borrow().is_some());
+    if !enabled {
+        return false;
+    }
+    announce(SyncMsg::Hello);
+    TimeoutFuture::new(HELLO_WAIT_MS).await;
+    session::current().token.is_some()
+}
+
+// Check if this tab should renew the session, taking the lease if nobody holds it
+pub fn is_leader() -> bool {
+    let enabled = SYNC.with(|sync| sync.borrow().is_some());
+    if !enabled {
+        return true;
+    }
+
+    let now = Date::now();
+    let lease: storage::Result<Lease> = LocalStorage::get(LEASE_KEY);
+    match lease {
+        Ok(lease) if lease.tab != tab() && lease.until > now => false,
+        _ => {
+            let lease = Lease {
+                tab: tab(),
+                until: now + LEASE_MS as f64,
+            };
+            LocalStorage::set(LEASE_KEY, lease).is_ok()
+        }
+    }
+}
+
+// Give up the lease so another tab can take over right away
+fn release() {
+    let lease: storage::Result<Lease> = LocalStorage::get(LEASE_KEY);
+    if let Ok(lease) = lease {
+        if lease.tab == tab() {
+            LocalStorage::delete(LEASE_KEY);
+        }
+    }
+}
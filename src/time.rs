@@ -0,0 +1,396 @@
+use js_sys::{Array, Date, Intl, Object, Reflect};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+// Timestamps are unix seconds like everything from the API.
+// Days and weeks are in the user's time zone: the browser's,
+// unless they picked another one (see set_zone).
+
+pub const MINUTE: u64 = 60;
+pub const HOUR: u64 = 60 * MINUTE;
+pub const DAY: u64 = 24 * HOUR;
+
+// Day numbers as Fields::weekday gives them
+pub const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+pub const MONDAY: u32 = 1;
+
+pub const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[wasm_bindgen]
+extern "C" {
+    // Intl.DateTimeFormat throws on time zones it doesn't know, this catches that
+    #[wasm_bindgen(catch, js_namespace = Intl, js_name = DateTimeFormat)]
+    fn date_time_format(locales: &JsValue, options: &JsValue) -> Result<JsValue, JsValue>;
+}
+
+thread_local! {
+    // Formatter reading wall clock times in the chosen time zone, None for the browser's
+    static ZONE: RefCell<Option<Intl::DateTimeFormat>> = RefCell::new(None);
+}
+
+// Formatter for a time zone, None if the browser doesn't know it
+fn zone_format(zone: &str) -> Option<Intl::DateTimeFormat> {
+    let options = Object::new();
+    let set = |key: &str, value: &str| {
+        let _ = Reflect::set(&options, &JsValue::from_str(key), &JsValue::from_str(value));
+    };
+    set("timeZone", zone);
+    set("hourCycle", "h23");
+    for key in ["year", "month", "day", "hour", "minute", "second"] {
+        set(key, "numeric");
+    }
+    date_time_format(&JsValue::from_str("en-US"), &options)
+        .ok()?
+        .dyn_into()
+        .ok()
+}
+
+// Whether the browser knows a time zone, i.e. "Europe/Paris"
+pub fn valid_zone(zone: &str) -> bool {
+    zone_format(zone).is_some()
+}
+
+// Time zone the browser is in
+pub fn browser_zone() -> String {
+    let options = Intl::DateTimeFormat::new(&Array::new(), &Object::new()).resolved_options();
+    Reflect::get(&options, &JsValue::from_str("timeZone"))
+        .ok()
+        .and_then(|zone| zone.as_string())
+        .unwrap_or_default()
+}
+
+// Use a time zone from now on, None or an unknown zone goes back to the browser's
+pub fn set_zone(zone: Option<&str>) {
+    let format = zone.and_then(zone_format);
+    ZONE.with(|cell| *cell.borrow_mut() = format);
+}
+
+pub fn now() -> u64 {
+    (Date::now() / 1000.0) as u64
+}
+
+// A timestamp on the wall clock
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fields {
+    pub year: i32,
+    // 1 to 12
+    pub month: u32,
+    // 1 to 31
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    // 0 for Sunday
+    pub weekday: u32,
+}
+
+// Days since 1970-01-01 of a date, month may be out of range and rolls over the year
+fn days_from_civil(year: i32, month: i32, day: i32) -> i64 {
+    let year = year as i64 + (month as i64 - 1).div_euclid(12);
+    let month = (month as i64 - 1).rem_euclid(12) + 1;
+    // Howard Hinnant's algorithm, years start in March
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468 + day as i64 - 1
+}
+
+// Inverse of days_from_civil, with day always in range
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as i32, month, day)
+}
+
+// A wall clock time as if it were UTC, in seconds
+fn wall_seconds(year: i32, month: i32, day: i32, hour: i32, minute: i32, second: i32) -> i64 {
+    days_from_civil(year, month, day) * DAY as i64
+        + hour as i64 * HOUR as i64
+        + minute as i64 * MINUTE as i64
+        + second as i64
+}
+
+// Seconds the wall clock is ahead of UTC at ts, in the zone format reads.
+// None is the browser's zone.
+fn offset_in(format: Option<&Intl::DateTimeFormat>, ts: i64) -> i64 {
+    let date = Date::new(&JsValue::from_f64(ts as f64 * 1000.0));
+    match format {
+        Some(format) => {
+            let part = |parts: &Array, kind: &str| -> i32 {
+                parts
+                    .iter()
+                    .find(|part| {
+                        Reflect::get(part, &JsValue::from_str("type"))
+                            .ok()
+                            .and_then(|t| t.as_string())
+                            .as_deref()
+                            == Some(kind)
+                    })
+                    .and_then(|part| Reflect::get(&part, &JsValue::from_str("value")).ok())
+                    .and_then(|value| value.as_string())
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(0)
+            };
+            let parts = format.format_to_parts(&date);
+            let wall = wall_seconds(
+                part(&parts, "year"),
+                part(&parts, "month"),
+                part(&parts, "day"),
+                part(&parts, "hour"),
+                part(&parts, "minute"),
+                part(&parts, "second"),
+            );
+            wall - ts
+        }
+        None => -(date.get_timezone_offset() as i64) * MINUTE as i64,
+    }
+}
+
+// Seconds the wall clock is ahead of UTC at ts, in the user's zone
+fn offset(ts: i64) -> i64 {
+    ZONE.with(|cell| offset_in(cell.borrow().as_ref(), ts))
+}
+
+// Seconds the user's wall clock is ahead of UTC at ts
+pub fn utc_offset(ts: u64) -> i64 {
+    offset(ts as i64)
+}
+
+// Daylight saving changes in the user's zone between from and to,
+// as when it happened and the offsets before and after
+pub fn transitions(from: u64, to: u64) -> Vec<(u64, i64, i64)> {
+    let mut found = Vec::new();
+    let mut day = from as i64;
+    while day < to as i64 {
+        let next = day + DAY as i64;
+        let (before, after) = (offset(day), offset(next));
+        if before != after {
+            // First second with the new offset
+            let (mut low, mut high) = (day, next);
+            while high - low > 1 {
+                let mid = (low + high) / 2;
+                if offset(mid) == before {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            found.push((high as u64, before, after));
+        }
+        day = next;
+    }
+    found
+}
+
+// Timestamp of a wall clock time, given how to find the offset of the zone.
+// Times skipped by daylight saving land on one side of the gap.
+fn from_wall(wall: i64, offset: impl Fn(i64) -> i64) -> u64 {
+    let guess = wall - offset(wall);
+    // The offset may be different on the other side of a daylight saving change
+    let ts = wall - offset(guess);
+    ts.max(0) as u64
+}
+
+// Read a timestamp off the wall clock
+pub fn fields(ts: u64) -> Fields {
+    wall_fields(ts as i64 + offset(ts as i64))
+}
+
+// Read a timestamp in UTC
+pub fn fields_utc(ts: u64) -> Fields {
+    wall_fields(ts as i64)
+}
+
+// Split seconds on a wall clock into fields
+fn wall_fields(wall: i64) -> Fields {
+    let days = wall.div_euclid(DAY as i64);
+    let secs = wall.rem_euclid(DAY as i64) as u64;
+    let (year, month, day) = civil_from_days(days);
+    Fields {
+        year,
+        month,
+        day,
+        hour: (secs / HOUR) as u32,
+        minute: (secs % HOUR / MINUTE) as u32,
+        second: (secs % MINUTE) as u32,
+        // 1970-01-01 was a Thursday
+        weekday: (days + 4).rem_euclid(7) as u32,
+    }
+}
+
+// Timestamp of a wall clock time. Out of range months, days and hours roll over.
+pub fn from_fields(year: i32, month: i32, day: i32, hour: i32, minute: i32, second: i32) -> u64 {
+    from_wall(wall_seconds(year, month, day, hour, minute, second), offset)
+}
+
+// Timestamp of a wall clock time in another time zone, None if the browser doesn't know it
+pub fn from_fields_in(
+    zone: &str,
+    year: i32,
+    month: i32,
+    day: i32,
+    hour: i32,
+    minute: i32,
+    second: i32,
+) -> Option<u64> {
+    let format = zone_format(zone)?;
+    let wall = wall_seconds(year, month, day, hour, minute, second);
+    Some(from_wall(wall, |ts| offset_in(Some(&format), ts)))
+}
+
+// Timestamp of a UTC time, out of range fields roll over like from_fields
+pub fn from_fields_utc(
+    year: i32,
+    month: i32,
+    day: i32,
+    hour: i32,
+    minute: i32,
+    second: i32,
+) -> u64 {
+    wall_seconds(year, month, day, hour, minute, second).max(0) as u64
+}
+
+// Midnight at the start of the day ts is in
+pub fn start_of_day(ts: u64) -> u64 {
+    let f = fields(ts);
+    from_fields(f.year, f.month as i32, f.day as i32, 0, 0, 0)
+}
+
+// A time of day on the day ts is in, minutes past 59 roll over into hours
+pub fn at(ts: u64, hour: u32, minute: u32) -> u64 {
+    let f = fields(ts);
+    from_fields(
+        f.year,
+        f.month as i32,
+        f.day as i32,
+        hour as i32,
+        minute as i32,
+        0,
+    )
+}
+
+// The same time of day, days later (or earlier).
+// Not days * DAY, days around daylight saving changes are shorter or longer.
+pub fn add_days(ts: u64, days: i32) -> u64 {
+    let f = fields(ts);
+    from_fields(
+        f.year,
+        f.month as i32,
+        f.day as i32 + days,
+        f.hour as i32,
+        f.minute as i32,
+        f.second as i32,
+    )
+}
+
+// Midnight at the start of the month ts is in, months later (or earlier)
+pub fn add_months(ts: u64, months: i32) -> u64 {
+    let f = fields(ts);
+    from_fields(f.year, f.month as i32 + months, 1, 0, 0, 0)
+}
+
+// Midnight at the start of the week ts is in.
+// first_day is the day weeks start on, 0 for Sunday.
+pub fn start_of_week(ts: u64, first_day: u32) -> u64 {
+    let back = (weekday(ts) + 7 - first_day % 7) % 7;
+    start_of_day(add_days(ts, -(back as i32)))
+}
+
+// 0 for Sunday
+pub fn weekday(ts: u64) -> u32 {
+    fields(ts).weekday
+}
+
+// "1:02:03"
+pub fn format_duration(secs: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        secs / HOUR,
+        secs % HOUR / MINUTE,
+        secs % MINUTE
+    )
+}
+
+// "09:05", 24 hour time
+pub fn format_clock(ts: u64) -> String {
+    let f = fields(ts);
+    format!("{:02}:{:02}", f.hour, f.minute)
+}
+
+// "Monday 4 October 2021"
+pub fn format_day(ts: u64) -> String {
+    let f = fields(ts);
+    format!(
+        "{} {} {} {}",
+        WEEKDAYS[f.weekday as usize],
+        f.day,
+        MONTHS[f.month as usize - 1],
+        f.year
+    )
+}
+
+// "October 2021"
+pub fn format_month(ts: u64) -> String {
+    let f = fields(ts);
+    format!("{} {}", MONTHS[f.month as usize - 1], f.year)
+}
+
+// Value for an <input type="date">
+pub fn date_input(ts: u64) -> String {
+    let f = fields(ts);
+    format!("{:04}-{:02}-{:02}", f.year, f.month, f.day)
+}
+
+// Value for an <input type="time">
+pub fn time_input(ts: u64) -> String {
+    format_clock(ts)
+}
+
+// Read an <input type="date"> and an <input type="time"> back into a timestamp
+pub fn parse_input(date: &str, time: &str) -> Option<u64> {
+    let mut ymd = date.split('-').map(|part| part.parse::<i32>().ok());
+    let (year, month, day) = (ymd.next()??, ymd.next()??, ymd.next()??);
+    let mut hm = time.split(':').map(|part| part.parse::<i32>().ok());
+    let (hour, minute) = (hm.next()??, hm.next()??);
+    if year < 1970
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || !(0..24).contains(&hour)
+        || !(0..60).contains(&minute)
+    {
+        return None;
+    }
+    Some(from_fields(year, month, day, hour, minute, 0))
+}
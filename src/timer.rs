@@ -0,0 +1,456 @@
+use crate::api::{ApiResult, Client, EntryResponse, SaveEntryRequest};
+use crate::app::AppRoute;
+use crate::nav::Nav;
+use crate::session;
+use crate::time;
+use crate::toast::{self, Level};
+use gloo::storage::{LocalStorage, Storage};
+use gloo::timers::callback::Interval;
+use js_sys::Date;
+use serde::{Deserialize, Serialize};
+use web_sys::{Event, HtmlInputElement, MouseEvent};
+use yew::prelude::*;
+
+// Wall clock stopwatch and Pomodoro phases
+mod clock;
+use clock::*;
+
+// How often to redraw while running, in milliseconds.
+// Only for the display, the time itself comes from the wall clock.
+const TICK_MS: u32 = 250;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Stopwatch,
+    Countdown,
+    Pomodoro,
+}
+
+impl Mode {
+    fn name(&self) -> &'static str {
+        match self {
+            Mode::Stopwatch => "Stopwatch",
+            Mode::Countdown => "Countdown",
+            Mode::Pomodoro => "Pomodoro",
+        }
+    }
+}
+
+// Everything about the timer, kept in local storage so it survives reloads
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+struct TimerState {
+    mode: Mode,
+    clock: Clock,
+    // Countdown length, in minutes
+    countdown: u32,
+    pomodoro: Pomodoro,
+    phase: Phase,
+    // Work intervals done so far
+    done: u32,
+    // Log finished intervals as tracked time
+    log: bool,
+    activity: String,
+}
+
+impl Default for TimerState {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Pomodoro,
+            clock: Clock::default(),
+            countdown: 10,
+            pomodoro: Pomodoro::default(),
+            phase: Phase::Work,
+            done: 0,
+            log: false,
+            activity: String::from("Focus"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TimerMsg {
+    Mode(Mode),
+    // Start or pause
+    Toggle,
+    Reset,
+    // Go on to the next Pomodoro phase early
+    Skip,
+    Tick,
+    Settings,
+    Logged(ApiResult<EntryResponse>),
+}
+
+// Stopwatch, countdown and Pomodoro timer
+pub struct Timer {
+    state: TimerState,
+    ticker: Option<Interval>,
+    // When finished intervals were last looked for, in milliseconds since the epoch
+    checked: f64,
+    countdownref: NodeRef,
+    workref: NodeRef,
+    shortref: NodeRef,
+    longref: NodeRef,
+    cyclesref: NodeRef,
+    logref: NodeRef,
+    activityref: NodeRef,
+}
+
+// Read a number box, keeping it between min and max
+fn number(node: &NodeRef, min: u32, max: u32, fallback: u32) -> u32 {
+    node.cast::<HtmlInputElement>()
+        .and_then(|input| input.value().parse().ok())
+        .unwrap_or(fallback)
+        .clamp(min, max)
+}
+
+impl Timer {
+    fn save(&self) {
+        if let Err(e) = LocalStorage::set("timer", &self.state) {
+            console_web::error!(format!("Could not save timer: {:?}", e));
+        }
+    }
+
+    // Tick while running, so the display keeps up
+    fn set_ticking(&mut self, ctx: &Context<Self>) {
+        if !self.state.clock.running() {
+            self.ticker = None;
+        } else if self.ticker.is_none() {
+            let link = ctx.link().clone();
+            self.ticker = Some(Interval::new(TICK_MS, move || {
+                link.send_message(TimerMsg::Tick)
+            }));
+        }
+    }
+
+    // How long the current interval is, in milliseconds. None for the stopwatch.
+    fn length(&self) -> Option<f64> {
+        match self.state.mode {
+            Mode::Stopwatch => None,
+            Mode::Countdown => Some(self.state.countdown as f64 * 60.0 * 1000.0),
+            Mode::Pomodoro => Some(self.state.pomodoro.length(self.state.phase)),
+        }
+    }
+
+    // Log start to end (in milliseconds) as tracked time, if the user wants that
+    fn log(&self, ctx: &Context<Self>, start: f64, end: f64, notes: &str) {
+        if !self.state.log || !session::current().is_authenticated() {
+            return;
+        }
+        let req = SaveEntryRequest {
+            id: None,
+            activity: self.state.activity.clone(),
+            start: (start / 1000.0) as u64,
+            end: (end / 1000.0) as u64,
+            notes: String::from(notes),
+        };
+        ctx.link()
+            .send_future(async move { TimerMsg::Logged(Client::new().save_entry(&req).await) });
+    }
+
+    // Finish every interval whose time is up, returns true if any was.
+    // Works out when each one really ended, so a throttled or closed tab
+    // catches up correctly. Only the interval that was running at the last check
+    // is logged, the ones after it went by with nobody watching.
+    fn catch_up(&mut self, ctx: &Context<Self>) -> bool {
+        let checked = std::mem::replace(&mut self.checked, Date::now());
+        let mut finished = false;
+        // Whole Pomodoro cycles missed are skipped, instead of going through each phase
+        if let (Mode::Pomodoro, Some(length)) = (self.state.mode, self.length()) {
+            let cycle = self.state.pomodoro.cycle();
+            let over = self.state.clock.elapsed() - length;
+            if self.state.clock.running() && cycle > 0.0 && over > cycle {
+                let skipped = (over / cycle).floor();
+                self.state.clock.skip(skipped * cycle);
+                let done = (skipped as u32).saturating_mul(self.state.pomodoro.cycles.max(1));
+                self.state.done = self.state.done.saturating_add(done);
+                finished = true;
+            }
+        }
+        while let Some(length) = self.length() {
+            let elapsed = self.state.clock.elapsed();
+            if !self.state.clock.running() || elapsed < length {
+                break;
+            }
+            finished = true;
+            // A zero length from storage would never catch up
+            if length <= 0.0 || !elapsed.is_finite() {
+                self.state.clock.reset();
+                break;
+            }
+            let end = Date::now() - (elapsed - length);
+            let watched = end - length <= checked && end > checked;
+            match self.state.mode {
+                Mode::Pomodoro => {
+                    if self.state.phase == Phase::Work {
+                        self.state.done += 1;
+                        if watched {
+                            self.log(ctx, end - length, end, "Pomodoro");
+                        }
+                    }
+                    self.state.phase = self.state.pomodoro.next(self.state.phase, self.state.done);
+                    self.state.clock.restart_at(end);
+                }
+                _ => {
+                    if watched {
+                        self.log(ctx, end - length, end, "Countdown");
+                    }
+                    self.state.clock.reset();
+                }
+            }
+        }
+        if finished {
+            let text = match self.state.mode {
+                Mode::Pomodoro => format!("Time for: {}", self.state.phase.name()),
+                _ => String::from("Time's up!"),
+            };
+            toast::show(Level::Info, text, None, Vec::new());
+            self.set_ticking(ctx);
+            self.save();
+        }
+        finished
+    }
+
+    // What the clock face shows, in whole seconds
+    fn display(&self) -> u64 {
+        let elapsed = self.state.clock.elapsed();
+        match self.length() {
+            Some(length) => ((length - elapsed).max(0.0) / 1000.0).ceil() as u64,
+            None => (elapsed / 1000.0) as u64,
+        }
+    }
+
+    fn modes(&self, ctx: &Context<Self>) -> Html {
+        let running = self.state.clock.running();
+        let button = |mode: Mode| {
+            let cb = ctx
+                .link()
+                .callback(move |_: MouseEvent| TimerMsg::Mode(mode));
+            let class = if self.state.mode == mode {
+                "selected"
+            } else {
+                ""
+            };
+            html! { <button class={class} onclick={cb} disabled={running}>{ mode.name() }</button> }
+        };
+        html! {
+            <section id="timer-modes">
+                { button(Mode::Stopwatch) }
+                { button(Mode::Countdown) }
+                { button(Mode::Pomodoro) }
+            </section>
+        }
+    }
+
+    fn face(&self, ctx: &Context<Self>) -> Html {
+        let toggle = ctx.link().callback(|_: MouseEvent| TimerMsg::Toggle);
+        let reset = ctx.link().callback(|_: MouseEvent| TimerMsg::Reset);
+        let skip = ctx.link().callback(|_: MouseEvent| TimerMsg::Skip);
+        let running = self.state.clock.running();
+        let phase = match self.state.mode {
+            Mode::Pomodoro => format!(
+                "{} - {} of {} done",
+                self.state.phase.name(),
+                self.state.done % self.state.pomodoro.cycles.max(1),
+                self.state.pomodoro.cycles
+            ),
+            mode => String::from(mode.name()),
+        };
+        let pomodoro = self.state.mode == Mode::Pomodoro;
+        html! {
+            <section id="timer-clock">
+                <p class="phase">{ phase }</p>
+                <p class="clockface">{ time::format_duration(self.display()) }</p>
+                <button onclick={toggle}>{ if running { "Pause" } else { "Start" } }</button>
+                <button onclick={reset}>{ "Reset" }</button>
+                <button onclick={skip} style={if pomodoro { "" } else { "display: none;" }}>{ "Skip" }</button>
+            </section>
+        }
+    }
+
+    fn settings(&self, ctx: &Context<Self>) -> Html {
+        let onchange = ctx.link().callback(|_: Event| TimerMsg::Settings);
+        let running = self.state.clock.running();
+        let minutes = |id: &'static str, label: &'static str, node: &NodeRef, value: u32| {
+            html! {
+                <>
+                    <label for={id}>{ label }</label>
+                    <input type="number" id={id} min="1" value={value.to_string()} disabled={running}
+                        ref={node.clone()} onchange={onchange.clone()} />
+                </>
+            }
+        };
+        let fields = match self.state.mode {
+            Mode::Stopwatch => html! {},
+            Mode::Countdown => minutes(
+                "timer-countdown",
+                "Minutes",
+                &self.countdownref,
+                self.state.countdown,
+            ),
+            Mode::Pomodoro => {
+                let pomodoro = self.state.pomodoro;
+                html! {
+                    <>
+                        { minutes("timer-work", "Work minutes", &self.workref, pomodoro.work) }
+                        { minutes("timer-short", "Short break minutes", &self.shortref, pomodoro.short_break) }
+                        { minutes("timer-long", "Long break minutes", &self.longref, pomodoro.long_break) }
+                        { minutes("timer-cycles", "Work intervals before a long break", &self.cyclesref, pomodoro.cycles) }
+                    </>
+                }
+            }
+        };
+        let logging = if session::current().is_authenticated() {
+            html! {
+                <>
+                    <div class="timerlog">
+                        <input type="checkbox" id="timer-log" checked={self.state.log}
+                            ref={self.logref.clone()} onchange={onchange.clone()} />
+                        <label for="timer-log">{ "Log finished intervals as tracked time" }</label>
+                    </div>
+                    <label for="timer-activity">{ "Activity" }</label>
+                    <input type="text" id="timer-activity" value={self.state.activity.clone()}
+                        ref={self.activityref.clone()} onchange={onchange} />
+                </>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <section id="timer-settings">
+                <form>
+                    { fields }
+                    { logging }
+                </form>
+            </section>
+        }
+    }
+}
+
+impl Component for Timer {
+    type Message = TimerMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut timer = Self {
+            state: LocalStorage::get("timer").unwrap_or_default(),
+            ticker: None,
+            checked: Date::now(),
+            countdownref: NodeRef::default(),
+            workref: NodeRef::default(),
+            shortref: NodeRef::default(),
+            longref: NodeRef::default(),
+            cyclesref: NodeRef::default(),
+            logref: NodeRef::default(),
+            activityref: NodeRef::default(),
+        };
+        // Whatever ended while the page was closed, none of it is logged
+        timer.catch_up(ctx);
+        timer.set_ticking(ctx);
+        timer
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            TimerMsg::Mode(mode) => {
+                if self.state.clock.running() || self.state.mode == mode {
+                    return false;
+                }
+                self.state.mode = mode;
+                self.state.clock.reset();
+                self.save();
+                true
+            }
+            TimerMsg::Toggle => {
+                if self.state.clock.running() {
+                    self.state.clock.pause();
+                } else {
+                    // Time spent paused wasn't missed, the next interval is watched
+                    self.checked = Date::now();
+                    self.state.clock.start();
+                }
+                self.set_ticking(ctx);
+                self.save();
+                true
+            }
+            TimerMsg::Reset => {
+                let elapsed = self.state.clock.elapsed();
+                // A stopwatch is done whenever the user says so
+                if self.state.mode == Mode::Stopwatch && elapsed >= 60.0 * 1000.0 {
+                    let now = Date::now();
+                    self.log(ctx, now - elapsed, now, "Stopwatch");
+                }
+                self.state.clock.reset();
+                if self.state.mode == Mode::Pomodoro {
+                    self.state.phase = Phase::Work;
+                    self.state.done = 0;
+                }
+                self.set_ticking(ctx);
+                self.save();
+                true
+            }
+            TimerMsg::Skip => {
+                if self.state.mode != Mode::Pomodoro {
+                    return false;
+                }
+                let running = self.state.clock.running();
+                // A skipped interval isn't logged, but still counts towards the long break
+                if self.state.phase == Phase::Work {
+                    self.state.done += 1;
+                }
+                self.state.phase = self.state.pomodoro.next(self.state.phase, self.state.done);
+                self.state.clock.reset();
+                if running {
+                    self.state.clock.start();
+                }
+                self.save();
+                true
+            }
+            TimerMsg::Tick => {
+                self.catch_up(ctx);
+                true
+            }
+            TimerMsg::Settings => {
+                let state = &mut self.state;
+                state.countdown = number(&self.countdownref, 1, 24 * 60, state.countdown);
+                state.pomodoro = Pomodoro {
+                    work: number(&self.workref, 1, 600, state.pomodoro.work),
+                    short_break: number(&self.shortref, 1, 600, state.pomodoro.short_break),
+                    long_break: number(&self.longref, 1, 600, state.pomodoro.long_break),
+                    cycles: number(&self.cyclesref, 1, 12, state.pomodoro.cycles),
+                };
+                if let Some(log) = self.logref.cast::<HtmlInputElement>() {
+                    state.log = log.checked();
+                }
+                if let Some(activity) = self.activityref.cast::<HtmlInputElement>() {
+                    let activity = activity.value().trim().to_string();
+                    if !activity.is_empty() {
+                        state.activity = activity;
+                    }
+                }
+                self.save();
+                true
+            }
+            TimerMsg::Logged(resp) => {
+                match resp {
+                    Ok(EntryResponse::Success(_)) => (),
+                    Ok(resp) => console_web::error!(format!("/track/entry: {:?}", resp)),
+                    Err(e) => toast::api_error(&e),
+                }
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <>
+                <Nav route={AppRoute::Timer} />
+                <main class="content" id="timer">
+                    { self.modes(ctx) }
+                    { self.face(ctx) }
+                    { self.settings(ctx) }
+                </main>
+            </>
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use js_sys::Date;
+use serde::{Deserialize, Serialize};
+
+// A stopwatch that only remembers timestamps.
+// Browsers slow down timers in background tabs, so time is never counted
+// by ticks, only read off the wall clock when needed.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub struct Clock {
+    // When it was last started, in milliseconds since the epoch
+    started: Option<f64>,
+    // Milliseconds counted before that
+    banked: f64,
+}
+
+impl Clock {
+    pub fn running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    // Milliseconds counted so far
+    pub fn elapsed(&self) -> f64 {
+        self.banked + self.started.map_or(0.0, |started| Date::now() - started)
+    }
+
+    pub fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Date::now());
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.banked = self.elapsed();
+        self.started = None;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Take some milliseconds off the time counted, for passing over whole Pomodoro cycles
+    pub fn skip(&mut self, ms: f64) {
+        self.banked -= ms;
+    }
+
+    // Start over from a moment in the past, so time past the end of one
+    // interval counts towards the next
+    pub fn restart_at(&mut self, at: f64) {
+        self.banked = 0.0;
+        self.started = Some(at);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Work => "Work",
+            Phase::ShortBreak => "Short break",
+            Phase::LongBreak => "Long break",
+        }
+    }
+}
+
+// Pomodoro lengths in minutes, and how many work intervals come before a long break
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pomodoro {
+    pub work: u32,
+    pub short_break: u32,
+    pub long_break: u32,
+    pub cycles: u32,
+}
+
+impl Default for Pomodoro {
+    fn default() -> Self {
+        Self {
+            work: 25,
+            short_break: 5,
+            long_break: 15,
+            cycles: 4,
+        }
+    }
+}
+
+impl Pomodoro {
+    // Length of a phase, in milliseconds
+    pub fn length(&self, phase: Phase) -> f64 {
+        let minutes = match phase {
+            Phase::Work => self.work,
+            Phase::ShortBreak => self.short_break,
+            Phase::LongBreak => self.long_break,
+        };
+        minutes as f64 * 60.0 * 1000.0
+    }
+
+    // A round of work intervals with their breaks, ending with the long break, in milliseconds
+    pub fn cycle(&self) -> f64 {
+        let cycles = self.cycles.max(1) as f64;
+        (self.work as f64 * cycles
+            + self.short_break as f64 * (cycles - 1.0)
+            + self.long_break as f64)
+            * 60.0
+            * 1000.0
+    }
+
+    // What comes after phase, given how many work intervals are done including it
+    pub fn next(&self, phase: Phase, done: u32) -> Phase {
+        match phase {
+            Phase::Work if done % self.cycles.max(1) == 0 => Phase::LongBreak,
+            Phase::Work => Phase::ShortBreak,
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        }
+    }
+}
@@ -1,73 +1,128 @@
+use crate::api::Client;
+use crate::config;
 use crate::consts;
-use gloo::storage::{SessionStorage, Storage};
+use crate::session;
+use crate::sync;
+use crate::toast;
+use crate::util;
 use gloo::timers::future::TimeoutFuture;
-use nittei_common::auth::{AuthToken, RenewResponse};
-use reqwasm::http::Request;
+use js_sys::{Date, Math};
+use nittei_common::auth::RenewResponse;
+use std::cell::Cell;
 use wasm_bindgen_futures::spawn_local;
 
-// Start a timer to refresh the session
+thread_local! {
+    // Bumped whenever a renewal loop starts or is cancelled.
+    // A loop quits as soon as it sees the generation it started with is gone,
+    // so there is only ever one loop doing anything.
+    static GENERATION: Cell<u32> = Cell::new(0);
+}
+
+fn generation() -> u32 {
+    GENERATION.with(|gen| gen.get())
+}
+
+fn next_generation() -> u32 {
+    GENERATION.with(|gen| {
+        gen.set(gen.get().wrapping_add(1));
+        gen.get()
+    })
+}
+
+// Start renewing the session shortly before it expires.
 // Should be called just after a new token is gotten!
 pub fn session_refresh() {
-    // Renew the session in 4 minutes
-    spawn_local(async move {
-        TimeoutFuture::new(1000 * 4 * 60).await;
-        session_refresh_loop().await;
-    });
+    let gen = next_generation();
+    spawn_local(session_refresh_loop(gen, false));
 }
 
-// Loop to renew session keys.
-async fn session_refresh_loop() {
-    let key: gloo::storage::Result<AuthToken> = SessionStorage::get("session_key");
-    if key.is_err() {
-        return;
-    }
-    let key = key.unwrap();
-    let jwt = key.to_string();
+// Renew the session right away, then keep it renewed
+pub fn session_renew() {
+    let gen = next_generation();
+    spawn_local(session_refresh_loop(gen, true));
+}
 
-    let req = Request::get(&format!("{}/auth/renew", consts::URL))
-        .header("Content-Type", "application/x-renew-request")
-        .header("Authorization", &format!("Bearer {}", jwt));
-    let resp = req.send().await;
-    if resp.is_err() {
-        // No connection, try again in 10 seconds
-        spawn_local(async {
-            TimeoutFuture::new(1000 * 10).await;
-            session_refresh_loop().await;
-        });
-        return;
-    }
-    let resp = resp.unwrap();
-    // Since we use the presence of a session as proof of login, delete it so we won't get confused
-    if resp.status() == 401 {
-        SessionStorage::delete("session-key");
-        SessionStorage::delete("session");
-    }
-    if resp.status() != 200 {
-        return;
-    }
-    let resp = resp.text().await;
-    if resp.is_err() {
-        return;
-    }
-    let resp = resp.unwrap();
+// Stop renewing the session (i.e. on logout)
+pub fn cancel_session_refresh() {
+    next_generation();
+}
 
-    let resp = ron::from_str::<RenewResponse>(&resp);
-    if resp.is_err() {
-        return;
-    }
-    let resp = resp.unwrap();
+// Milliseconds until the session should be renewed
+fn renew_delay() -> u32 {
+    let now = (Date::now() / 1000.0) as u64;
+    let secs = match session::current().expires() {
+        Some(exp) => {
+            let left = exp.saturating_sub(now);
+            left - config::get().renew_margin(left)
+        }
+        None => consts::RENEW_FALLBACK,
+    };
+    (secs.saturating_mul(1000))
+        .min(u32::MAX as u64)
+        .max(consts::RENEW_DELAY_MIN as u64) as u32
+}
+
+// Milliseconds to wait after failures failed renewals in a row.
+// Doubles every time, with jitter so many clients don't retry all at once.
+fn backoff(failures: u32) -> u32 {
+    let delay = consts::RENEW_BACKOFF_MIN
+        .saturating_mul(1 << failures.min(16))
+        .min(consts::RENEW_BACKOFF_MAX);
+    let jitter = 0.5 + Math::random() / 2.0;
+    (delay as f64 * jitter) as u32
+}
+
+// Loop to renew session keys.
+async fn session_refresh_loop(gen: u32, immediate: bool) {
+    let mut failures = 0;
+    let mut delay = if immediate { 0 } else { renew_delay() };
 
-    if let RenewResponse::Success(jwt) = resp {
-        if SessionStorage::set("session_key", jwt).is_err() {
+    loop {
+        TimeoutFuture::new(delay).await;
+        if gen != generation() {
             return;
         }
-    } else {
-        return;
-    }
+        let key = match session::current().token {
+            Some(key) => key,
+            None => return,
+        };
+        if !sync::is_leader() {
+            // Another tab renews for us and shares the new token.
+            // Check back later in case that tab goes away.
+            delay = renew_delay().max(sync::LEASE_MS);
+            continue;
+        }
 
-    // Renew succeeded, do it again in 4 minutes
-    spawn_local(async {
-        TimeoutFuture::new(1000 * 60 * 4).await;
-        session_refresh_loop().await;
-    });
+        let resp = Client::with_token(key).renew().await;
+        if gen != generation() {
+            return;
+        }
+        match resp {
+            Ok(RenewResponse::Success(jwt)) => {
+                if session::renewed(jwt).is_err() {
+                    return;
+                }
+                failures = 0;
+                delay = renew_delay();
+            }
+            Err(e) if !e.is_unauthorized() => {
+                // No connection or a server error, try again later.
+                // Not worth a toast, only the offline banner.
+                if e.is_offline() {
+                    toast::set_offline(true);
+                } else {
+                    console_web::error!(e.to_string());
+                }
+                delay = backoff(failures);
+                failures += 1;
+            }
+            _ => {
+                // The server stopped accepting our session.
+                // Forget it, but a remember me token can still get a new one.
+                session::expire();
+                spawn_local(util::try_login_persist());
+                return;
+            }
+        }
+    }
 }
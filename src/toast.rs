@@ -0,0 +1,281 @@
+use crate::api::{ApiError, ErrorKind};
+use crate::app::AppRoute;
+use crate::config;
+use crate::session;
+use gloo::events::EventListener;
+use gloo::timers::future::TimeoutFuture;
+use std::cell::{Cell, RefCell};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::MouseEvent;
+use yew::prelude::*;
+
+// How long toasts without any actions stay up, in milliseconds
+const TOAST_MS: u32 = 6 * 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+// Something the user can do about a toast
+#[derive(Clone, PartialEq, Debug)]
+pub enum Action {
+    // Try whatever failed again
+    Retry(Callback<()>),
+    // Go log in again
+    Login,
+    // Reload the page to get a client matching the server
+    Reload,
+    // Tell us about it
+    Support,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Toast {
+    id: u32,
+    level: Level,
+    text: String,
+    // Technical details for bug reports
+    detail: Option<String>,
+    actions: Vec<Action>,
+}
+
+// Everything shown by the Notices component
+#[derive(Clone, PartialEq, Debug, Default)]
+struct State {
+    toasts: Vec<Toast>,
+    offline: bool,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+    static LISTENER: RefCell<Option<Callback<()>>> = RefCell::new(None);
+    static NEXT_ID: Cell<u32> = Cell::new(0);
+}
+
+fn state() -> State {
+    STATE.with(|state| state.borrow().clone())
+}
+
+// Change what is shown and tell the Notices component
+fn update(f: impl FnOnce(&mut State)) {
+    STATE.with(|state| f(&mut state.borrow_mut()));
+    LISTENER.with(|listener| {
+        if let Some(listener) = listener.borrow().as_ref() {
+            listener.emit(());
+        }
+    });
+}
+
+// Show a toast. Ones with nothing to do go away by themselves.
+pub fn show(level: Level, text: String, detail: Option<String>, actions: Vec<Action>) {
+    let id = NEXT_ID.with(|next| {
+        next.set(next.get().wrapping_add(1));
+        next.get()
+    });
+    let sticky = !actions.is_empty();
+    update(|state| {
+        // Don't stack the same message over and over
+        state.toasts.retain(|toast| toast.text != text);
+        state.toasts.push(Toast {
+            id,
+            level,
+            text,
+            detail,
+            actions,
+        });
+    });
+
+    if !sticky {
+        spawn_local(async move {
+            TimeoutFuture::new(TOAST_MS).await;
+            dismiss(id);
+        });
+    }
+}
+
+pub fn dismiss(id: u32) {
+    update(|state| state.toasts.retain(|toast| toast.id != id));
+}
+
+// Show the offline banner, or take it down
+pub fn set_offline(offline: bool) {
+    if state().offline != offline {
+        update(|state| state.offline = offline);
+    }
+}
+
+// Tell the user about a failed API call
+pub fn api_error(err: &ApiError) {
+    api_error_with_retry(err, None);
+}
+
+// Tell the user about a failed API call that can be tried again
+pub fn api_error_with_retry(err: &ApiError, retry: Option<Callback<()>>) {
+    console_web::error!(err.to_string());
+
+    let support = if config::get().support_url.is_empty() {
+        None
+    } else {
+        Some(Action::Support)
+    };
+    let retry = retry.map(Action::Retry);
+    let (level, actions) = match err.kind {
+        ErrorKind::Offline => {
+            // The banner says it all
+            set_offline(true);
+            return;
+        }
+        ErrorKind::Unauthorized => {
+            // Private pages send the user to login by themselves
+            session::expire();
+            (Level::Warning, vec![Action::Login])
+        }
+        ErrorKind::RateLimited(_) => (Level::Warning, Vec::new()),
+        ErrorKind::Server(_) | ErrorKind::Status(_) => {
+            (Level::Error, retry.into_iter().chain(support).collect())
+        }
+        ErrorKind::Encode(_) => (Level::Error, support.into_iter().collect()),
+        ErrorKind::Decode(_) => (
+            Level::Error,
+            Some(Action::Reload).into_iter().chain(support).collect(),
+        ),
+    };
+    show(level, err.message(), Some(err.to_string()), actions);
+}
+
+#[derive(Clone, Debug)]
+pub enum NoticesMsg {
+    Changed,
+    Online(bool),
+    Dismiss(u32),
+}
+
+// Toasts and the offline banner, drawn over every page
+pub struct Notices {
+    state: State,
+    _online: EventListener,
+    _offline: EventListener,
+}
+
+impl Notices {
+    fn action(&self, ctx: &Context<Self>, toast: &Toast, action: &Action) -> Html {
+        let id = toast.id;
+        match action {
+            Action::Retry(retry) => {
+                let retry = retry.clone();
+                let dismiss = ctx.link().callback(move |_: MouseEvent| {
+                    retry.emit(());
+                    NoticesMsg::Dismiss(id)
+                });
+                html! { <button onclick={dismiss}>{ "Try again" }</button> }
+            }
+            Action::Login => {
+                let cb = ctx.link().callback(move |_: MouseEvent| {
+                    yew_router::push_route(AppRoute::Login);
+                    NoticesMsg::Dismiss(id)
+                });
+                html! { <button onclick={cb}>{ "Log in" }</button> }
+            }
+            Action::Reload => {
+                let cb = Callback::from(|_: MouseEvent| {
+                    if let Some(window) = web_sys::window() {
+                        let _ = window.location().reload();
+                    }
+                });
+                html! { <button onclick={cb}>{ "Reload" }</button> }
+            }
+            Action::Support => {
+                let href = config::get().support_url;
+                html! { <a href={href} target="_blank">{ "Contact support" }</a> }
+            }
+        }
+    }
+
+    fn toast(&self, ctx: &Context<Self>, toast: &Toast) -> Html {
+        let id = toast.id;
+        let close = ctx
+            .link()
+            .callback(move |_: MouseEvent| NoticesMsg::Dismiss(id));
+        let class = match toast.level {
+            Level::Info => "toast info",
+            Level::Warning => "toast warning",
+            Level::Error => "toast error",
+        };
+        html! {
+            <div class={class} role="alert">
+                <button class="toastclose" onclick={close}>{ "×" }</button>
+                <p>{ toast.text.clone() }</p>
+                {
+                    match &toast.detail {
+                        Some(detail) => html! { <p class="toastdetail">{ detail.clone() }</p> },
+                        None => html! {},
+                    }
+                }
+                <div class="toastactions">
+                    { for toast.actions.iter().map(|action| self.action(ctx, toast, action)) }
+                </div>
+            </div>
+        }
+    }
+}
+
+impl Component for Notices {
+    type Message = NoticesMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        LISTENER.with(|listener| {
+            *listener.borrow_mut() = Some(ctx.link().callback(|_| NoticesMsg::Changed))
+        });
+
+        let window = web_sys::window().expect("No window");
+        let online = ctx.link().callback(|_| NoticesMsg::Online(true));
+        let offline = ctx.link().callback(|_| NoticesMsg::Online(false));
+        set_offline(!window.navigator().on_line());
+        Self {
+            state: state(),
+            _online: EventListener::new(&window, "online", move |_| online.emit(())),
+            _offline: EventListener::new(&window, "offline", move |_| offline.emit(())),
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            NoticesMsg::Changed => {
+                let state = state();
+                let changed = self.state != state;
+                self.state = state;
+                changed
+            }
+            NoticesMsg::Online(online) => {
+                set_offline(!online);
+                false
+            }
+            NoticesMsg::Dismiss(id) => {
+                dismiss(id);
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let banner = if self.state.offline {
+            html! {
+                <div id="offlinebanner" role="status">
+                    { "You are offline. Nothing will be saved until the connection is back." }
+                </div>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <div id="notices">
+                { banner }
+                { for self.state.toasts.iter().map(|toast| self.toast(ctx, toast)) }
+            </div>
+        }
+    }
+}
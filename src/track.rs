@@ -1,29 +1,440 @@
+use crate::api::{
+    self, ApiResult, Client, DeleteEntryRequest, DeleteEntryResponse, EntriesRequest,
+    EntriesResponse, Entry, EntryResponse, RunningResponse, SaveEntryRequest, StartTimerRequest,
+    StopTimerRequest,
+};
 use crate::app::AppRoute;
 use crate::nav::Nav;
+use crate::prefs;
+use crate::time;
+use crate::toast;
+use gloo::timers::callback::Interval;
+use web_sys::{HtmlInputElement, MouseEvent};
 use yew::prelude::*;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum TrackMsg {}
+// Form for adding and changing entries
+mod editor;
+use editor::*;
 
-pub struct Track {}
+#[derive(Clone, Debug)]
+pub enum TrackMsg {
+    Load,
+    Loaded(ApiResult<EntriesResponse>),
+    LoadedRunning(ApiResult<RunningResponse>),
+    Start,
+    Stop,
+    // Answer to starting, stopping or saving an entry
+    Saved(ApiResult<EntryResponse>),
+    // Redraw the running timer
+    Tick,
+    Add,
+    Edit(u64),
+    Cancel,
+    Save(SaveEntryRequest),
+    Delete(u64),
+    Deleted(u64, ApiResult<DeleteEntryResponse>),
+}
+
+// Which entry the editor is open for
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Editing {
+    New,
+    Entry(u64),
+}
+
+// Time tracking: a timer to start and stop, and this week's entries
+pub struct Track {
+    // This week's entries, newest first
+    entries: Vec<Entry>,
+    loading: bool,
+    // The running timer, it lives on the server so it survives reloads
+    running: Option<Entry>,
+    editing: Option<Editing>,
+    // Waiting on the server to start, stop or save something
+    saving: bool,
+    // Asked once whether to delete this entry
+    confirming: Option<u64>,
+    failure: Option<String>,
+    // Start of the week shown, unix timestamp
+    week: u64,
+    // Ticks while the timer runs
+    ticker: Option<Interval>,
+    activityref: NodeRef,
+}
+
+impl Track {
+    // Seconds spent on an entry, running ones count up to now
+    fn duration(entry: &Entry) -> u64 {
+        entry
+            .end
+            .unwrap_or_else(time::now)
+            .saturating_sub(entry.start)
+    }
+
+    // Seconds of the entries that fall between from and to
+    fn total(&self, from: u64, to: u64) -> u64 {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let start = entry.start.max(from);
+                let end = entry.end.unwrap_or_else(time::now).min(to);
+                end.saturating_sub(start)
+            })
+            .sum()
+    }
+
+    fn set_running(&mut self, ctx: &Context<Self>, running: Option<Entry>) {
+        self.ticker = running.as_ref().map(|_| {
+            let link = ctx.link().clone();
+            Interval::new(1000, move || link.send_message(TrackMsg::Tick))
+        });
+        self.running = running;
+    }
+
+    // Put a saved entry in the list, or take it out if it is not in this week anymore
+    fn upsert(&mut self, entry: Entry) {
+        self.entries.retain(|other| other.id != entry.id);
+        let next_week = time::add_days(self.week, 7);
+        if entry.start < next_week && entry.end.is_none_or(|end| end >= self.week) {
+            self.entries.push(entry);
+            self.entries.sort_by(|a, b| b.start.cmp(&a.start));
+        }
+    }
+
+    fn error(&mut self, resp: EntryResponse) {
+        self.failure = Some(String::from(match resp {
+            EntryResponse::NotFound => "That entry does not exist anymore.",
+            EntryResponse::AlreadyRunning(_) => "A timer is already running.",
+            EntryResponse::BadTimes => "An entry has to end after it starts.",
+            _ => api::REJECTED,
+        }));
+    }
+
+    fn timer(&self, ctx: &Context<Self>) -> Html {
+        match &self.running {
+            Some(entry) => {
+                let stop = ctx.link().callback(|_: MouseEvent| TrackMsg::Stop);
+                html! {
+                    <section id="track-timer" class="running">
+                        <p class="activity">{ entry.activity.clone() }</p>
+                        <p class="elapsed">{ time::format_duration(Self::duration(entry)) }</p>
+                        <p>{ format!("Since {}", time::format_clock(entry.start)) }</p>
+                        <button onclick={stop} disabled={self.saving}>{ "Stop" }</button>
+                    </section>
+                }
+            }
+            None => {
+                let start = ctx.link().callback(|e: MouseEvent| {
+                    e.prevent_default();
+                    TrackMsg::Start
+                });
+                html! {
+                    <section id="track-timer">
+                        <form>
+                            <label for="track-activity">{ "What are you working on?" }</label>
+                            <input type="text" id="track-activity" name="activity" ref={self.activityref.clone()} />
+                            <button type="submit" onclick={start} disabled={self.saving}>{ "Start" }</button>
+                        </form>
+                    </section>
+                }
+            }
+        }
+    }
+
+    fn entry(&self, ctx: &Context<Self>, entry: &Entry) -> Html {
+        if self.editing == Some(Editing::Entry(entry.id)) {
+            return self.editor(ctx, Some(entry.clone()));
+        }
+        let id = entry.id;
+        let edit = ctx.link().callback(move |_: MouseEvent| TrackMsg::Edit(id));
+        let delete = ctx
+            .link()
+            .callback(move |_: MouseEvent| TrackMsg::Delete(id));
+        let end = match entry.end {
+            Some(end) => time::format_clock(end),
+            None => String::from("now"),
+        };
+        let delete_text = if self.confirming == Some(id) {
+            "Really delete?"
+        } else {
+            "Delete"
+        };
+        html! {
+            <li class="entry">
+                <span class="entrytime">{ format!("{} - {}", time::format_clock(entry.start), end) }</span>
+                <span class="entryactivity">{ entry.activity.clone() }</span>
+                <span class="entryduration">{ time::format_duration(Self::duration(entry)) }</span>
+                <p class="entrynotes">{ entry.notes.clone() }</p>
+                <button onclick={edit} disabled={entry.end.is_none()}>{ "Edit" }</button>
+                <button class="dangerbutton" onclick={delete} disabled={self.saving}>{ delete_text }</button>
+            </li>
+        }
+    }
+
+    fn editor(&self, ctx: &Context<Self>, entry: Option<Entry>) -> Html {
+        let onsave = ctx.link().callback(TrackMsg::Save);
+        let oncancel = ctx.link().callback(|_| TrackMsg::Cancel);
+        html! {
+            <li class="entry">
+                <EntryEditor entry={entry} saving={self.saving} onsave={onsave} oncancel={oncancel} />
+            </li>
+        }
+    }
+
+    // Entries of one day, with how long they add up to
+    fn day(&self, ctx: &Context<Self>, day: u64) -> Html {
+        let next = time::add_days(day, 1);
+        let entries: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.start >= day && entry.start < next)
+            .collect();
+        if entries.is_empty() {
+            return html! {};
+        }
+        let title = if day == time::start_of_day(time::now()) {
+            String::from("Today")
+        } else {
+            time::format_day(day)
+        };
+        html! {
+            <div class="trackday">
+                <h3>
+                    { title }
+                    <span class="daytotal">{ time::format_duration(self.total(day, next)) }</span>
+                </h3>
+                <ul>
+                    { for entries.into_iter().map(|entry| self.entry(ctx, entry)) }
+                </ul>
+            </div>
+        }
+    }
+
+    fn week_view(&self, ctx: &Context<Self>) -> Html {
+        if self.loading {
+            return html! { <p>{ "Loading..." }</p> };
+        }
+        let add = ctx.link().callback(|_: MouseEvent| TrackMsg::Add);
+        let new = if self.editing == Some(Editing::New) {
+            html! { <ul>{ self.editor(ctx, None) }</ul> }
+        } else {
+            html! { <button onclick={add}>{ "Add entry" }</button> }
+        };
+        // Newest day first, stopping at today
+        let today = time::start_of_day(time::now());
+        let days: Vec<u64> = (0..7)
+            .map(|day| time::add_days(self.week, day))
+            .filter(|day| *day <= today)
+            .rev()
+            .collect();
+        html! {
+            <section id="track-entries">
+                <h2>{ "This week" }</h2>
+                { new }
+                { for days.into_iter().map(|day| self.day(ctx, day)) }
+            </section>
+        }
+    }
+}
 
 impl Component for Track {
     type Message = TrackMsg;
     type Properties = ();
 
-    fn create(_ctx: &Context<Self>) -> Self {
-        Self {}
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(TrackMsg::Load);
+        Self {
+            entries: Vec::new(),
+            loading: true,
+            running: None,
+            editing: None,
+            saving: false,
+            confirming: None,
+            failure: None,
+            week: time::start_of_week(time::now(), prefs::get().first_day),
+            ticker: None,
+            activityref: NodeRef::default(),
+        }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, _msg: Self::Message) -> bool {
-        false
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            TrackMsg::Load => {
+                let req = EntriesRequest {
+                    from: self.week,
+                    to: time::add_days(self.week, 7),
+                };
+                ctx.link().send_future(async move {
+                    TrackMsg::Loaded(Client::new().entries(&req).await)
+                });
+                ctx.link()
+                    .send_future(async { TrackMsg::LoadedRunning(Client::new().running().await) });
+                self.loading = true;
+                true
+            }
+            TrackMsg::Loaded(resp) => {
+                self.loading = false;
+                match resp {
+                    Ok(EntriesResponse::Success(mut entries)) => {
+                        entries.sort_by(|a, b| b.start.cmp(&a.start));
+                        self.entries = entries;
+                    }
+                    Ok(EntriesResponse::InvalidRequest) => {
+                        self.failure = Some(String::from(api::REJECTED))
+                    }
+                    Err(e) => {
+                        let retry = ctx.link().callback(|_| TrackMsg::Load);
+                        toast::api_error_with_retry(&e, Some(retry));
+                    }
+                }
+                true
+            }
+            TrackMsg::LoadedRunning(resp) => {
+                match resp {
+                    Ok(RunningResponse::Running(entry)) => {
+                        self.set_running(ctx, Some(entry.clone()));
+                        self.upsert(entry);
+                    }
+                    Ok(RunningResponse::Stopped) => self.set_running(ctx, None),
+                    Ok(RunningResponse::InvalidRequest) => {
+                        self.failure = Some(String::from(api::REJECTED))
+                    }
+                    // Loaded already toasts about the same problem
+                    Err(_) => (),
+                }
+                true
+            }
+            TrackMsg::Start => {
+                if self.saving || self.running.is_some() {
+                    return false;
+                }
+                let activity = match self.activityref.cast::<HtmlInputElement>() {
+                    Some(input) => input.value().trim().to_string(),
+                    None => return false,
+                };
+                if activity.is_empty() {
+                    self.failure = Some(String::from("Missing activity!"));
+                    return true;
+                }
+                let req = StartTimerRequest { activity };
+                ctx.link().send_future(async move {
+                    TrackMsg::Saved(Client::new().start_timer(&req).await)
+                });
+                self.saving = true;
+                self.failure = None;
+                true
+            }
+            TrackMsg::Stop => {
+                let id = match &self.running {
+                    Some(entry) if !self.saving => entry.id,
+                    _ => return false,
+                };
+                let req = StopTimerRequest { id };
+                ctx.link().send_future(async move {
+                    TrackMsg::Saved(Client::new().stop_timer(&req).await)
+                });
+                self.saving = true;
+                self.failure = None;
+                true
+            }
+            TrackMsg::Saved(resp) => {
+                self.saving = false;
+                match resp {
+                    Ok(EntryResponse::Success(entry)) => {
+                        let running = self.running.as_ref().map(|running| running.id);
+                        if entry.end.is_none() {
+                            self.set_running(ctx, Some(entry.clone()));
+                        } else if running == Some(entry.id) {
+                            self.set_running(ctx, None);
+                        }
+                        self.editing = None;
+                        self.upsert(entry);
+                    }
+                    // Started in another tab or on another device
+                    Ok(EntryResponse::AlreadyRunning(entry)) => {
+                        self.set_running(ctx, Some(entry.clone()));
+                        self.upsert(entry);
+                    }
+                    Ok(resp) => self.error(resp),
+                    Err(e) => toast::api_error(&e),
+                }
+                true
+            }
+            TrackMsg::Tick => self.running.is_some(),
+            TrackMsg::Add => {
+                self.editing = Some(Editing::New);
+                self.failure = None;
+                true
+            }
+            TrackMsg::Edit(id) => {
+                self.editing = Some(Editing::Entry(id));
+                self.failure = None;
+                true
+            }
+            TrackMsg::Cancel => {
+                self.editing = None;
+                true
+            }
+            TrackMsg::Save(req) => {
+                if self.saving {
+                    return false;
+                }
+                ctx.link().send_future(async move {
+                    TrackMsg::Saved(Client::new().save_entry(&req).await)
+                });
+                self.saving = true;
+                self.failure = None;
+                true
+            }
+            TrackMsg::Delete(id) => {
+                if self.saving {
+                    return false;
+                }
+                if self.confirming != Some(id) {
+                    self.confirming = Some(id);
+                    return true;
+                }
+                let req = DeleteEntryRequest { id };
+                ctx.link().send_future(async move {
+                    TrackMsg::Deleted(id, Client::new().delete_entry(&req).await)
+                });
+                self.saving = true;
+                self.confirming = None;
+                true
+            }
+            TrackMsg::Deleted(id, resp) => {
+                self.saving = false;
+                match resp {
+                    Ok(DeleteEntryResponse::Success) | Ok(DeleteEntryResponse::NotFound) => {
+                        self.entries.retain(|entry| entry.id != id);
+                        if self.running.as_ref().map(|entry| entry.id) == Some(id) {
+                            self.set_running(ctx, None);
+                        }
+                    }
+                    Ok(DeleteEntryResponse::InvalidRequest) => {
+                        self.failure = Some(String::from(api::REJECTED))
+                    }
+                    Err(e) => toast::api_error(&e),
+                }
+                true
+            }
+        }
     }
 
-    fn view(&self, _ctx: &Context<Self>) -> Html {
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let today = time::start_of_day(time::now());
+        let failure_text = self.failure.clone().unwrap_or_default();
         html! {
             <>
                 <Nav route={AppRoute::Track} />
                 <main class="content" id="track">
+                    { self.timer(ctx) }
+                    <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>{ failure_text }</p>
+                    <section id="track-totals">
+                        <p>{ "Today" }<span>{ time::format_duration(self.total(today, time::add_days(today, 1))) }</span></p>
+                        <p>{ "This week" }<span>{ time::format_duration(self.total(self.week, time::add_days(self.week, 7))) }</span></p>
+                    </section>
+                    { self.week_view(ctx) }
                 </main>
             </>
         }
@@ -0,0 +1,161 @@
+use crate::api::{Entry, SaveEntryRequest};
+use crate::time;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement, MouseEvent};
+use yew::prelude::*;
+
+#[derive(Clone, Debug)]
+pub enum EditorMsg {
+    Save,
+    Cancel,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct EditorProps {
+    // Entry to change, None to add a new one
+    pub entry: Option<Entry>,
+    // Waiting on the server, the save button is disabled meanwhile
+    pub saving: bool,
+    pub onsave: Callback<SaveEntryRequest>,
+    pub oncancel: Callback<()>,
+}
+
+// Form for a time entry added or changed by hand
+pub struct EntryEditor {
+    // When the editor was opened, so new entries don't shift under the user
+    opened: u64,
+    failure: Option<&'static str>,
+    activityref: NodeRef,
+    dateref: NodeRef,
+    startref: NodeRef,
+    endref: NodeRef,
+    notesref: NodeRef,
+}
+
+impl EntryEditor {
+    fn input(node: &NodeRef) -> String {
+        node.cast::<HtmlInputElement>()
+            .map(|input| input.value())
+            .unwrap_or_default()
+    }
+
+    // Read the form into a request, or say what is wrong with it
+    fn request(&self, entry: Option<&Entry>) -> Result<SaveEntryRequest, &'static str> {
+        let activity = Self::input(&self.activityref).trim().to_string();
+        if activity.is_empty() {
+            return Err("Missing activity!");
+        }
+        let date = Self::input(&self.dateref);
+        let start =
+            time::parse_input(&date, &Self::input(&self.startref)).ok_or("Invalid start time!")?;
+        let mut end =
+            time::parse_input(&date, &Self::input(&self.endref)).ok_or("Invalid end time!")?;
+        if end <= start {
+            // Went past midnight
+            end = time::add_days(end, 1);
+        }
+        let notes = self
+            .notesref
+            .cast::<HtmlTextAreaElement>()
+            .map(|notes| notes.value())
+            .unwrap_or_default();
+        Ok(SaveEntryRequest {
+            id: entry.map(|entry| entry.id),
+            activity,
+            start,
+            end,
+            notes,
+        })
+    }
+}
+
+impl Component for EntryEditor {
+    type Message = EditorMsg;
+    type Properties = EditorProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            opened: time::now(),
+            failure: None,
+            activityref: NodeRef::default(),
+            dateref: NodeRef::default(),
+            startref: NodeRef::default(),
+            endref: NodeRef::default(),
+            notesref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            EditorMsg::Save => {
+                if ctx.props().saving {
+                    return false;
+                }
+                match self.request(ctx.props().entry.as_ref()) {
+                    Ok(req) => {
+                        self.failure = None;
+                        ctx.props().onsave.emit(req);
+                    }
+                    Err(failure) => self.failure = Some(failure),
+                }
+                true
+            }
+            EditorMsg::Cancel => {
+                ctx.props().oncancel.emit(());
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let save = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            EditorMsg::Save
+        });
+        let cancel = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            EditorMsg::Cancel
+        });
+
+        // A new entry starts out as the last hour
+        let now = self.opened;
+        let (activity, start, end, notes) = match &ctx.props().entry {
+            Some(entry) => (
+                entry.activity.clone(),
+                entry.start,
+                entry.end.unwrap_or(now),
+                entry.notes.clone(),
+            ),
+            None => (
+                String::new(),
+                now.saturating_sub(time::HOUR),
+                now,
+                String::new(),
+            ),
+        };
+        let failure_text = self.failure.unwrap_or_default();
+        html! {
+            <form class="entryeditor">
+                <label>{ "Activity" }
+                    <input type="text" name="activity" value={activity} ref={self.activityref.clone()} />
+                </label>
+                <label>{ "Date" }
+                    <input type="date" name="date" value={time::date_input(start)} ref={self.dateref.clone()} />
+                </label>
+                <label>{ "From" }
+                    <input type="time" name="start" value={time::time_input(start)} ref={self.startref.clone()} />
+                </label>
+                <label>{ "To" }
+                    <input type="time" name="end" value={time::time_input(end)} ref={self.endref.clone()} />
+                </label>
+                <label>{ "Notes" }
+                    <textarea name="notes" value={notes} ref={self.notesref.clone()} />
+                </label>
+                <p class="failuretext" style={if failure_text.is_empty() { "display: none;" } else { "" }}>{ failure_text }</p>
+                <div class="editorbuttons">
+                    <button type="submit" onclick={save} disabled={ctx.props().saving}>{ "Save" }</button>
+                    <button onclick={cancel}>{ "Cancel" }</button>
+                </div>
+            </form>
+        }
+    }
+}
@@ -1,81 +1,37 @@
-use crate::consts;
+use crate::api::{Client, RevokeRequest, RevokeResponse};
+use crate::config;
+use crate::prefs;
+use crate::session;
+use crate::sync;
 use crate::timers;
-use gloo::storage::{self, LocalStorage, SessionStorage, Storage};
-use gloo::timers::future::TimeoutFuture;
+use crate::toast::{self, Level};
+use gloo::storage::{self, LocalStorage, Storage};
+use js_sys::{Array, Date};
 use nittei_common::auth::{
-    AuthToken, PersistLoginRequest, PersistLoginResponse, PersistRequest, PersistResponse,
-    PersistToken, RenewResponse,
+    PersistLoginRequest, PersistLoginResponse, PersistRequest, PersistResponse, PersistToken,
 };
-use reqwasm::http::Request;
+use passwords::{analyzer, scorer};
 use serde::{Deserialize, Serialize};
-use wasm_bindgen_futures::spawn_local;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{Blob, BlobPropertyBag, File, HtmlAnchorElement, Url};
+use yew::Callback;
 
 // Try to login with remember me token, or renew session token
 pub fn try_login() {
-    spawn_local(async {
-        // First, try to renew the session
-        let session: storage::Result<AuthToken> = SessionStorage::get("session_key");
-        if session.is_err() {
+    if session::current().token.is_some() {
+        // There is a session, let's renew it (and keep renewing it)
+        timers::session_renew();
+    } else {
+        spawn_local(async {
+            // Another tab might already be logged in
+            if sync::ask_for_session().await {
+                return;
+            }
             // No session, try to login with persistent token
             try_login_persist().await;
-            return;
-        }
-
-        // There is a session, let's try to renew it
-        let session = session.unwrap();
-        let jwt = session.to_string();
-
-        let req = Request::get(&format!("{}/auth/renew", consts::URL))
-            .header("Content-Type", "application/x-renew-request")
-            .header("Authorization", &format!("Bearer {}", jwt));
-        let resp = req.send().await;
-        if resp.is_err() {
-            // No connection, try again later (5 seconds)
-            TimeoutFuture::new(1000 * 5).await;
-            try_login();
-            return;
-        }
-        let resp = resp.unwrap();
-
-        if resp.status() != 200 && resp.status() != 401 {
-            // Failed to login! Try again soon
-            TimeoutFuture::new(1000 * 5).await;
-            try_login();
-            return;
-        } else if resp.status() == 401 {
-            SessionStorage::delete("session_key");
-            SessionStorage::delete("session");
-            try_login();
-        }
-
-        let resp = resp.text().await;
-        if resp.is_err() {
-            // Failed to get text! Try again soon
-            TimeoutFuture::new(1000 * 5).await;
-            try_login();
-            return;
-        }
-
-        let resp = ron::from_str::<RenewResponse>(&resp.unwrap());
-        if resp.is_err() {
-            TimeoutFuture::new(1000 * 5).await;
-            try_login();
-            return;
-        }
-        let resp = resp.unwrap();
-
-        if let RenewResponse::Success(token) = resp {
-            // Update the session key!
-            SessionStorage::set("session_key", token).expect("Token not set");
-            return;
-        }
-
-        // Failed to update key. We must be invalid. Remove the key, and try to login
-        // again (check if there is remember me token)
-        SessionStorage::delete("session_key");
-        SessionStorage::delete("session");
-        try_login();
-    });
+        });
+    }
 }
 
 // Try to login with persistent token
@@ -86,53 +42,67 @@ pub async fn try_login_persist() {
         return;
     }
     let remember_me = remember_me.unwrap();
+    session::authenticating();
 
     // Have a remember me token, request a login
-    let ron_req = PersistLoginRequest {
+    let req = PersistLoginRequest {
         email: remember_me.email,
         token: remember_me.token,
     };
-    let ron_req = ron::to_string(&ron_req);
-    if ron_req.is_err() {
-        return;
-    }
-    let req = Request::post(&format!("{}/auth/persist_login", consts::URL))
-        .header("Content-Type", "application/x-persist-login-request")
-        .body(&ron_req.unwrap());
-    let resp = req.send().await;
-    if resp.is_err() {
-        return;
-    }
-    let resp = resp.unwrap();
-    if resp.status() != 200 {
-        return;
-    }
-    let resp = resp.text().await;
-    if resp.is_err() {
-        return;
-    }
-
-    let resp = ron::from_str::<PersistLoginResponse>(&resp.unwrap());
-    if resp.is_err() {
-        return;
-    }
-    let resp = resp.unwrap();
+    let resp = Client::anonymous().persist_login(&req).await;
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(e) => {
+            // Keep the token, it may still work once the server is back.
+            // Only this tab gave up, the others keep their sessions.
+            session::adopt_logout();
+            let retry = Callback::from(|_| spawn_local(try_login_persist()));
+            toast::api_error_with_retry(&e, Some(retry));
+            return;
+        }
+    };
 
     if let PersistLoginResponse::Success(token, claim) = resp {
-        SessionStorage::set("session", claim).expect("Failed to set session");
-        SessionStorage::set("session_key", token).expect("Failed to set session key");
+        session::login(token, claim).expect("Failed to set session");
         timers::session_refresh();
+        return;
     }
 
     // Oh no! Our remember me token is invalid! Delete it.
+    // Other tabs may still have a valid session, so don't log them out.
     LocalStorage::delete("persist");
+    session::adopt_logout();
 }
 
-// Check to see if we should treat the user as logged in.
-pub fn logged_in() -> bool {
+// Check to see if there is a remember me token
+pub fn remembered() -> bool {
+    let persist: storage::Result<PersistentLogin> = LocalStorage::get("persist");
+    persist.is_ok()
+}
+
+// Sign out, revoking the session and remember me token on the server.
+// Everything local is cleared even if the server can not be reached.
+pub async fn logout(everywhere: bool) {
+    timers::cancel_session_refresh();
+
     let persist: storage::Result<PersistentLogin> = LocalStorage::get("persist");
-    let session: storage::Result<AuthToken> = SessionStorage::get("session_key");
-    persist.is_ok() || session.is_ok()
+    let client = Client::new();
+    if client.has_token() {
+        let req = RevokeRequest {
+            persist: persist.ok().map(|persist| persist.token),
+            everywhere,
+        };
+        match client.revoke(&req).await {
+            Ok(RevokeResponse::Success) => (),
+            Ok(resp) => console_web::error!(format!("/auth/revoke: {:?}", resp)),
+            // Signed out here, but the session may live on at the server
+            Err(e) => toast::api_error(&e),
+        }
+    }
+
+    LocalStorage::delete("persist");
+    prefs::clear();
+    session::clear();
 }
 
 #[derive(Serialize, Debug, Deserialize)]
@@ -148,60 +118,140 @@ pub fn request_persistence(email: String, password: String) {
 }
 
 async fn request_persistence_inner(email: String, password: String) {
-    let jwt: storage::Result<AuthToken> = SessionStorage::get("session_key");
-    if jwt.is_err() {
+    let client = Client::new();
+    if !client.has_token() {
         console_web::error!("Persist: No session found.");
         return;
     }
-    let jwt = jwt.unwrap().to_string();
 
     let req = PersistRequest {
         email: email.clone(),
         password,
     };
-    let req = ron::to_string(&req);
-    if req.is_err() {
-        console_web::error!("Persist: RON Serialization error.");
-        return;
-    }
-    let req = req.unwrap();
-
-    let req = Request::post(&format!("{}/auth/persist_request", consts::URL))
-        .header("Content-Type", "application/x-persist-request")
-        .header("Authorization", &format!("Bearer {}", jwt))
-        .body(req);
-    let resp = req.send().await;
-    if resp.is_err() {
-        console_web::error!("Persist: Request error.");
-        return;
+    let resp = match client.persist_request(&req).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            toast::api_error(&e);
+            return;
+        }
+    };
+
+    if let PersistResponse::Success(token) = resp {
+        let persist = PersistentLogin { email, token };
+
+        LocalStorage::set("persist", persist).expect("Failed to save persist token");
+    } else {
+        console_web::error!(format!("/auth/persist_request: {:?}", resp));
+        toast::show(
+            Level::Warning,
+            String::from("Could not remember this device, you will need to log in next time."),
+            None,
+            Vec::new(),
+        );
     }
-    let resp = resp.unwrap();
+}
 
-    if resp.status() != 200 {
-        console_web::error!("Persist: HTTP Not Ok");
-        return;
+// Username rules, the same ones the server answers InvalidUsername for
+pub const USERNAME_MAX: usize = 30;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UsernameProblem {
+    Empty,
+    Long,
+    // A character that isn't a letter, digit, '_' or '-'
+    Character(char),
+}
+
+fn username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+// Check a username against the username rules
+pub fn check_username(username: &str) -> Option<UsernameProblem> {
+    if username.is_empty() {
+        return Some(UsernameProblem::Empty);
+    }
+    if username.chars().count() > USERNAME_MAX {
+        return Some(UsernameProblem::Long);
     }
+    username
+        .chars()
+        .find(|c| !username_char(*c))
+        .map(UsernameProblem::Character)
+}
 
-    let resp = resp.text().await;
-    if resp.is_err() {
-        console_web::error!("Persist: Text not Ok");
-        return;
+// Password rules, shared by everything that sets a password.
+// The length limits come from config so deployments can allow long passphrases.
+pub const PASSWORD_SCORE: f64 = 70.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PasswordProblem {
+    Short,
+    Long,
+    Weak,
+}
+
+impl PasswordProblem {
+    // Something to tell the user
+    pub fn message(&self) -> String {
+        match self {
+            PasswordProblem::Short => format!(
+                "Password must be at least {} characters.",
+                config::get().password_min
+            ),
+            PasswordProblem::Long => format!(
+                "Password must be no longer than {} characters.",
+                config::get().password_max
+            ),
+            PasswordProblem::Weak => String::from("Password too weak!"),
+        }
     }
-    let resp = resp.unwrap();
+}
 
-    let resp = ron::from_str::<PersistResponse>(&resp);
-    if resp.is_err() {
-        console_web::error!("Persist: Deserialization error.");
-        return;
+// Check a new password against the password rules
+pub fn check_password(password: &str) -> Option<PasswordProblem> {
+    let config = config::get();
+    let length = password.chars().count();
+    if length < config.password_min {
+        return Some(PasswordProblem::Short);
+    }
+    if length > config.password_max {
+        return Some(PasswordProblem::Long);
     }
-    if let PersistResponse::Success(token) = resp.unwrap() {
-        let persist = PersistentLogin {
-            email: email,
-            token,
-        };
 
-        LocalStorage::set("persist", persist).expect("Failed to save persist token");
+    let analyzed = analyzer::analyze(password);
+    if scorer::score(&analyzed) < PASSWORD_SCORE {
+        Some(PasswordProblem::Weak)
     } else {
-        console_web::error!("Persist: Bad Response");
+        None
     }
 }
+
+// Show a unix timestamp (in seconds) in the user's locale
+pub fn format_timestamp(secs: u64) -> String {
+    let date = Date::new(&JsValue::from_f64(secs as f64 * 1000.0));
+    date.to_locale_string("default", &JsValue::UNDEFINED).into()
+}
+
+// Hand text to the user as a file download
+pub fn download(name: &str, mime: &str, text: &str) -> Result<(), JsValue> {
+    let parts = Array::of1(&JsValue::from_str(text));
+    let blob = Blob::new_with_str_sequence_and_options(&parts, BlobPropertyBag::new().type_(mime))?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("No document"))?;
+    let link: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    link.set_href(&url);
+    link.set_download(name);
+    link.click();
+    Url::revoke_object_url(&url)
+}
+
+// Read a file the user picked as text
+pub async fn read_file(file: &File) -> Result<String, JsValue> {
+    JsFuture::from(file.text())
+        .await?
+        .as_string()
+        .ok_or_else(|| JsValue::from_str("Not text"))
+}
@@ -1,21 +1,36 @@
+use crate::api::{
+    self, ApiError, ApiResult, Client, ResendVerifyResponse, Throttled, VerifyRequest,
+    VerifyResponse,
+};
 use crate::app::AppRoute;
-use crate::nav::{Anchor, Nav};
-use crate::util::logged_in;
+use crate::lockout::{self, Countdown};
+use crate::nav::Nav;
+use crate::session;
+use crate::toast;
 use web_sys::MouseEvent;
 use yew::prelude::*;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Debug)]
 pub enum VerifyMsg {
     Clicked,
+    Resend,
+    VerifyRecieved(ApiResult<VerifyResponse>),
+    ResendRecieved(ApiResult<Throttled<ResendVerifyResponse>>),
+    // A lockout ran out
+    Unlocked,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum VerifyState {
     New,
-    NotLoggedIn,
+    Verifying,
     ExpiredCode,
     BadCode,
-    Disconnected,
+    Rejected,
+    LockedOut,
+    Failed,
+    Resending,
+    Resent,
     Verified,
 }
 
@@ -27,6 +42,29 @@ pub struct VerifyProps {
 pub struct Verify {
     state: VerifyState,
     code: u128,
+    // While LockedOut: when it ends, in milliseconds since the epoch,
+    // and the state to go back to
+    locked_until: f64,
+    unlocked: VerifyState,
+}
+
+impl Verify {
+    fn lock(&mut self, until: f64, unlocked: VerifyState) -> VerifyState {
+        self.locked_until = until;
+        self.unlocked = unlocked;
+        VerifyState::LockedOut
+    }
+
+    // Rate limited requests get a countdown, anything else a toast
+    fn error(&mut self, err: &ApiError, state: VerifyState) -> VerifyState {
+        match lockout::from_error(err) {
+            Some(until) => self.lock(until, state),
+            None => {
+                toast::api_error(err);
+                state
+            }
+        }
+    }
 }
 
 impl Component for Verify {
@@ -34,21 +72,72 @@ impl Component for Verify {
     type Properties = VerifyProps;
 
     fn create(ctx: &Context<Self>) -> Self {
-        if !logged_in() {
-            Self {
-                state: VerifyState::NotLoggedIn,
-                code: ctx.props().code,
-            }
-        } else {
-            Self {
-                state: VerifyState::New,
-                code: ctx.props().code,
-            }
+        Self {
+            state: VerifyState::New,
+            code: ctx.props().code,
+            locked_until: 0.0,
+            unlocked: VerifyState::New,
         }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, _msg: Self::Message) -> bool {
-        false
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            VerifyMsg::Clicked => {
+                let req = VerifyRequest { code: self.code };
+                ctx.link().send_future(async move {
+                    VerifyMsg::VerifyRecieved(Client::new().verify(&req).await)
+                });
+                self.state = VerifyState::Verifying;
+                true
+            }
+            VerifyMsg::Resend => {
+                ctx.link().send_future(async {
+                    VerifyMsg::ResendRecieved(Client::new().resend_verify().await)
+                });
+                self.state = VerifyState::Resending;
+                true
+            }
+            VerifyMsg::VerifyRecieved(resp) => {
+                self.state = match resp {
+                    Ok(VerifyResponse::Success(token, claim)) => {
+                        // Pick up the verified flag everywhere
+                        if session::login(token, claim).is_err() {
+                            VerifyState::Failed
+                        } else {
+                            VerifyState::Verified
+                        }
+                    }
+                    Ok(VerifyResponse::ExpiredCode) => VerifyState::ExpiredCode,
+                    Ok(VerifyResponse::BadCode) => VerifyState::BadCode,
+                    Ok(VerifyResponse::InvalidRequest) => VerifyState::Rejected,
+                    // On 401 the route guard sends the user to login
+                    Err(e) => self.error(&e, VerifyState::New),
+                };
+                true
+            }
+            VerifyMsg::ResendRecieved(resp) => {
+                self.state = match resp {
+                    Ok(resp) => match resp.body {
+                        ResendVerifyResponse::Success => VerifyState::Resent,
+                        // Someone else used a newer link already
+                        ResendVerifyResponse::AlreadyVerified => VerifyState::Verified,
+                        ResendVerifyResponse::Lockout => {
+                            self.lock(lockout::until(resp.retry_after), VerifyState::ExpiredCode)
+                        }
+                        ResendVerifyResponse::InvalidRequest => VerifyState::Rejected,
+                    },
+                    Err(e) => self.error(&e, VerifyState::ExpiredCode),
+                };
+                true
+            }
+            VerifyMsg::Unlocked => {
+                if self.state != VerifyState::LockedOut {
+                    return false;
+                }
+                self.state = self.unlocked;
+                true
+            }
+        }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
@@ -66,32 +155,64 @@ impl Component for Verify {
                                     </>
                                 }
                             },
-                            VerifyState::NotLoggedIn => html_nested! {
+                            VerifyState::BadCode => html_nested! {
                                 <>
                                     <p class="failuretext">
-                                        { "Not logged in!" }
+                                        { "Invalid verification code!" }
                                     </p>
-                                    <Anchor route={AppRoute::LoginVerify{code: self.code }}>{ "Login" }</Anchor>
                                 </>
                             },
-                            VerifyState::BadCode => html_nested! {
+                            VerifyState::Verifying => html_nested! {
                                 <>
-                                    <p class="failuretext">
-                                        { "Invalid verification code!" }
+                                    <p>
+                                        { "Verifying..." }
+                                    </p>
+                                </>
+                            },
+                            VerifyState::ExpiredCode => {
+                                let cb = ctx.link().callback(|_: MouseEvent| VerifyMsg::Resend);
+                                html_nested! {
+                                    <>
+                                        <p class="failuretext">
+                                            { "Verification code expired!" }
+                                        </p>
+                                        <button onclick={cb} id="resendbutton">{ "Resend Email" }</button>
+                                    </>
+                                }
+                            },
+                            VerifyState::Resending => html_nested! {
+                                <>
+                                    <p>
+                                        { "Sending a new verification email..." }
+                                    </p>
+                                </>
+                            },
+                            VerifyState::Resent => html_nested! {
+                                <>
+                                    <p>
+                                        { "A new verification email is on its way." }
                                     </p>
                                 </>
                             },
-                            VerifyState::ExpiredCode => html_nested! {
+                            VerifyState::Rejected => html_nested! {
                                 <>
                                     <p class="failuretext">
-                                        { "Verification code expired!" }
+                                        { api::REJECTED }
                                     </p>
                                 </>
                             },
-                            VerifyState::Disconnected => html_nested! {
+                            VerifyState::LockedOut => {
+                                let onelapsed = ctx.link().callback(|_| VerifyMsg::Unlocked);
+                                html_nested! {
+                                    <>
+                                        <Countdown until={self.locked_until} onelapsed={onelapsed} />
+                                    </>
+                                }
+                            },
+                            VerifyState::Failed => html_nested! {
                                 <>
                                     <p class="failuretext">
-                                        { "Can not connect to server!" }
+                                        { "Something went wrong. Please try again." }
                                     </p>
                                 </>
                             },